@@ -0,0 +1,139 @@
+//! The same work on a [`BoardArr`] and on a [`Bitboard`] of a middle game position.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use renju::board::bitboard::Bitboard;
+use renju::board::engine::{evaluate, Engine};
+use renju::board::rules::RuleSet;
+use renju::board::{BoardArr, Point, Stone};
+
+const DIRECTIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+const WEIGHTS: [i32; 5] = [0, 1, 12, 150, 2_000];
+
+/// `stones` stones around the center of a 15x15 board, without a five.
+fn position(stones: usize) -> BoardArr {
+    let mut board = BoardArr::new(15);
+    let mut state: u32 = 0x5EED;
+    let mut stone = Stone::Black;
+    while board.iter().filter(|m| !m.color.is_empty()).count() < stones {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let point = Point::new(3 + (state >> 8) % 9, 3 + (state >> 16) % 9);
+        if board.get_point(point).is_some_and(|m| m.color.is_empty())
+            && board.outcome(RuleSet::Renju).is_none()
+        {
+            board.set_point(point, stone).unwrap();
+            if board.outcome(RuleSet::Renju).is_some() {
+                board.set_point(point, Stone::Empty).unwrap();
+                continue;
+            }
+            stone = stone.opposite();
+        }
+    }
+    board
+}
+
+/// [`evaluate`] a row of five at a time, the way it would be done on a [`BoardArr`].
+fn evaluate_by_row(board: &BoardArr) -> i32 {
+    let size = i64::from(board.size());
+    let color_at = |x: i64, y: i64| {
+        let (x, y) = (u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+        (x < board.size() && y < board.size()).then(|| board.get_xy(x, y).unwrap().color)
+    };
+    let mut score = 0;
+    for (x, y) in (0..size).flat_map(|y| (0..size).map(move |x| (x, y))) {
+        for (dx, dy) in DIRECTIONS {
+            let row = (0..5)
+                .map(|step| color_at(x + dx * step, y + dy * step))
+                .collect::<Option<Vec<_>>>();
+            let Some(row) = row else { continue };
+            let black = row.iter().filter(|s| s.is_black()).count();
+            let white = row.iter().filter(|s| s.is_white()).count();
+            score += match (black, white) {
+                (black, 0) => WEIGHTS[black.min(4)],
+                (0, white) => -WEIGHTS[white.min(4)],
+                _ => 0,
+            };
+        }
+    }
+    score
+}
+
+fn bitboard(c: &mut Criterion) {
+    let board = position(40);
+    let bitboard = Bitboard::from(&board);
+    assert_eq!(evaluate_by_row(&board), evaluate(&bitboard, Stone::Black));
+
+    let mut group = c.benchmark_group("clone");
+    group.bench_function("BoardArr", |b| b.iter(|| black_box(&board).clone()));
+    group.bench_function("Bitboard", |b| b.iter(|| black_box(&bitboard).clone()));
+    group.finish();
+
+    let mut group = c.benchmark_group("place_and_remove");
+    let point = Point::new(0, 0);
+    group.bench_function("BoardArr", |b| {
+        let mut board = board.clone();
+        b.iter(|| {
+            board.set_point(black_box(point), Stone::Black).unwrap();
+            board.set_point(black_box(point), Stone::Empty).unwrap();
+        });
+    });
+    group.bench_function("Bitboard", |b| {
+        let mut bitboard = bitboard.clone();
+        b.iter(|| {
+            bitboard.set_point(black_box(point), Stone::Black);
+            bitboard.set_point(black_box(point), Stone::Empty);
+        });
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("evaluate");
+    group.bench_function("BoardArr", |b| {
+        b.iter(|| evaluate_by_row(black_box(&board)))
+    });
+    group.bench_function("Bitboard", |b| {
+        b.iter(|| evaluate(black_box(&bitboard), Stone::Black))
+    });
+    group.finish();
+
+    // the rules are only on a bitboard, a board is converted first
+    let mut group = c.benchmark_group("conditions");
+    group.bench_function("BoardArr", |b| {
+        b.iter(|| board.conditions(Stone::Black, RuleSet::Renju, None))
+    });
+    group.bench_function("Bitboard", |b| {
+        b.iter(|| bitboard.conditions(Stone::Black, RuleSet::Renju, None))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("winning_moves");
+    group.bench_function("every empty point", |b| {
+        b.iter(|| {
+            bitboard
+                .points(bitboard.stones(Stone::Empty))
+                .filter(|point| bitboard.wins_with(*point, Stone::Black, RuleSet::Renju))
+                .count()
+        });
+    });
+    group.bench_function("fives", |b| {
+        b.iter(|| {
+            bitboard
+                .points(bitboard.fives(Stone::Black))
+                .filter(|point| bitboard.wins_with(*point, Stone::Black, RuleSet::Renju))
+                .count()
+        });
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("engine");
+    group.sample_size(10);
+    let opening = position(10);
+    group.bench_function("depth 4", |b| {
+        b.iter(|| {
+            Engine::new(RuleSet::Renju)
+                .with_depth(4)
+                .analyse(&opening, opening.to_move())
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bitboard);
+criterion_main!(benches);
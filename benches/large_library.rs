@@ -0,0 +1,34 @@
+//! Loading and saving a synthetic library with about as many nodes as all games on renju.net.
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use renju::board::Board;
+use renju::file_reader::renlib::{parse_lib, write_lib, Version};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn large_library(c: &mut Criterion) {
+    let board = common::synthetic_library(5_000, 40, 0x5EED);
+    let bytes = common::synthetic_lib_bytes(5_000, 40, 0x5EED);
+
+    let mut group = c.benchmark_group("large_library");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("parse_lib", |b| {
+        b.iter(|| {
+            let mut board = Board::new();
+            parse_lib(bytes.as_slice(), &mut board).unwrap();
+            board
+        });
+    });
+    group.bench_function("write_lib", |b| {
+        b.iter(|| {
+            let mut out = Vec::with_capacity(bytes.len());
+            write_lib(&mut out, &board, Version::V30).unwrap();
+            out
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, large_library);
+criterion_main!(benches);
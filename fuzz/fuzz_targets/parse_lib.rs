@@ -0,0 +1,14 @@
+//! Parse a whole library into a board.
+//!
+//! Run with `cargo +nightly fuzz run parse_lib examplefiles` from the repository root, to start from
+//! the example files.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use renju::board::Board;
+use renju::file_reader::renlib::parse_lib;
+
+fuzz_target!(|data: &[u8]| {
+    let mut board = Board::new();
+    let _ = parse_lib(data, &mut board);
+});
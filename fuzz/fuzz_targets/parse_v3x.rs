@@ -0,0 +1,11 @@
+//! Parse the nodes of a library, without the header.
+//!
+//! Run with `cargo +nightly fuzz run parse_v3x` from the repository root.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use renju::file_reader::renlib::{parser::parse_v3x, Version};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_v3x(data, Version::V30, 20);
+});
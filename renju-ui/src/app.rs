@@ -1,4 +1,4 @@
-use egui::{Button, TextEdit, Widget};
+use egui::{Button, Key, KeyboardShortcut, Modifiers, TextEdit, Ui, Widget};
 use poll_promise::Promise;
 
 use crate::board::UIBoard;
@@ -18,6 +18,9 @@ pub struct RenjuApp {
     picker_promise: Option<Promise<Option<Vec<u8>>>>,
     #[serde(skip)]
     just_clicked: bool,
+    /// Moves to go to, in standard notation.
+    #[serde(skip)]
+    goto: String,
 }
 
 impl Default for RenjuApp {
@@ -29,6 +32,7 @@ impl Default for RenjuApp {
             board: UIBoard::new(),
             picker_promise: None,
             just_clicked: false,
+            goto: String::new(),
         }
     }
 }
@@ -65,8 +69,28 @@ impl eframe::App for RenjuApp {
             board,
             picker_promise,
             just_clicked,
+            goto,
         } = self;
 
+        // text fields undo their own typing while they have focus
+        if ctx.memory(|memory| memory.focus().is_none()) {
+            let (redo, undo) = ctx.input_mut(|input| {
+                let redo = input.consume_shortcut(&KeyboardShortcut::new(
+                    Modifiers::COMMAND | Modifiers::SHIFT,
+                    Key::Z,
+                )) || input
+                    .consume_shortcut(&KeyboardShortcut::new(Modifiers::CTRL, Key::Y));
+                let undo =
+                    input.consume_shortcut(&KeyboardShortcut::new(Modifiers::COMMAND, Key::Z));
+                (redo, undo)
+            });
+            if redo {
+                board.redo();
+            } else if undo {
+                board.undo();
+            }
+        }
+
         if *just_clicked {
             *just_clicked = false;
 
@@ -81,10 +105,67 @@ impl eframe::App for RenjuApp {
             // The top panel is often a good place for a menu bar:
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    for size in [15, 19, 20] {
+                        if ui.button(format!("New {size}x{size}")).clicked() {
+                            match UIBoard::with_size(size) {
+                                Ok(new) => *board = new,
+                                Err(error) => tracing::error!(%error, "couldn't make a board"),
+                            }
+                            ui.close_menu();
+                        }
+                    }
                     if ui.button("Quit").clicked() {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
+                ui.menu_button("Edit", |ui| {
+                    let shortcut = |ui: &Ui, modifiers, key| {
+                        ui.ctx()
+                            .format_shortcut(&KeyboardShortcut::new(modifiers, key))
+                    };
+                    let undo =
+                        Button::new("Undo").shortcut_text(shortcut(ui, Modifiers::COMMAND, Key::Z));
+                    if ui.add_enabled(board.can_undo(), undo).clicked() {
+                        board.undo();
+                        ui.close_menu();
+                    }
+                    let redo = Button::new("Redo").shortcut_text(shortcut(
+                        ui,
+                        Modifiers::COMMAND | Modifiers::SHIFT,
+                        Key::Z,
+                    ));
+                    if ui.add_enabled(board.can_redo(), redo).clicked() {
+                        board.redo();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let is_root = board.graph().index() == 0;
+                    if ui
+                        .add_enabled(!is_root, Button::new("Delete move"))
+                        .clicked()
+                    {
+                        board.remove_current_move();
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(!is_root, Button::new("Promote variation"))
+                        .clicked()
+                    {
+                        board.promote_current_move();
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Rules", |ui| {
+                    for rules in renju::board::rules::RuleSet::ALL {
+                        if ui
+                            .radio(board.rules() == rules, rules.to_string())
+                            .clicked()
+                        {
+                            board.set_rules(rules);
+                            ui.close_menu();
+                        }
+                    }
+                });
                 ui.menu_button("Transform", |ui| {
                     if ui.button("rotate").clicked() {
                         board.transform_mut().rotate(renju::board::Rotation::Deg90);
@@ -116,12 +197,17 @@ impl eframe::App for RenjuApp {
                             std::task::Poll::Ready(Some(bytes)) => {
                                 // lol
                                 let curr_move = board.graph().current_move();
-                                renju::file_reader::read_bytes(
+                                // read into a copy, so a bad file leaves the library and its
+                                // undo history as they were
+                                let mut graph = board.graph().clone();
+                                match renju::file_reader::read_bytes(
                                     bytes.as_slice(),
-                                    Some(&renju::file_reader::FileType::Lib),
-                                    board.graph_mut(),
-                                )
-                                .unwrap();
+                                    None,
+                                    &mut graph,
+                                ) {
+                                    Ok(()) => *board.graph_mut() = graph,
+                                    Err(error) => tracing::error!(%error, "couldn't read file"),
+                                }
 
                                 board.change_current_move(&curr_move);
                             }
@@ -146,7 +232,24 @@ impl eframe::App for RenjuApp {
                         .add_enabled(prev.is_some(), Button::new("back"))
                         .clicked()
                     {
-                        board.change_current_move(&prev.unwrap())
+                        board.back()
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Go to: ");
+                    let response = TextEdit::singleline(goto).hint_text("h8 i9 g7").ui(ui);
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        let graph = board.graph();
+                        let root = graph.get_root();
+                        match renju::board::Point::parse_line_with_size(goto, graph.size())
+                            .and_then(|points| graph.find_line(&root, points))
+                        {
+                            Ok(path) => {
+                                let node = path.last().copied().unwrap_or(root);
+                                board.change_current_move(&node);
+                            }
+                            Err(error) => tracing::warn!(%error, "couldn't go to moves"),
+                        }
                     }
                 });
                 let moves = board
@@ -156,8 +259,21 @@ impl eframe::App for RenjuApp {
                     .filter_map(|i| board.graph().get_move(*i))
                     .collect::<Vec<_>>();
                 let moves_i = board.graph().move_list().iter().collect::<Vec<_>>();
-                ui.text_edit_multiline(&mut format!("Moves: {moves:?}"));
+                ui.text_edit_multiline(&mut format!(
+                    "Moves: {}",
+                    moves
+                        .iter()
+                        .map(|m| m.point.notation(board.graph().size()))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ));
                 ui.text_edit_multiline(&mut format!("Moves I: {moves_i:?}"));
+                if let Some((opening, _)) = board
+                    .graph()
+                    .canonical_opening(&board.graph().current_move())
+                {
+                    ui.label(format!("Opening: {opening} ({})", opening.code()));
+                }
                 ui.text_edit_multiline(&mut format!(
                     "Positions: {:?}",
                     board
@@ -173,7 +289,8 @@ impl eframe::App for RenjuApp {
                 ui.text_edit_multiline(&mut format!("Transform: {:?}", board.transform()));
 
                 ui.with_layout(egui::Layout::bottom_up(egui::Align::RIGHT), |ui| {
-                    let current = board.current_move_mut();
+                    let mut marker = board.current_move().clone();
+                    let current = &mut marker;
                     let multi = current.multiline_comment.as_mut();
                     ui.label("Comment:");
                     match multi {
@@ -199,6 +316,7 @@ impl eframe::App for RenjuApp {
                             current.set_oneline_comment(one);
                         }
                     }
+                    board.set_current_marker(marker);
 
                     ui.horizontal(|ui| {
                         if cfg!(debug_assertions) {
@@ -1,9 +1,16 @@
 use egui::{style::Margin, *};
 use renju::{
-    board::{evaluator::RenjuConditions, BoardArr, BoardMarker, Point, Stone},
-    board::{Board, MoveIndex, Transformation, VariantType},
+    board::{
+        bitboard::Bitboard,
+        cursor::Cursor,
+        evaluator::{Evaluator, RenjuConditions},
+        history::{Edit, History},
+        rules::RuleSet,
+        BoardArr, BoardMarker, Point, Stone,
+    },
+    board::{Board, EditError, MoveIndex, Transformation, VariantType},
+    errors::ParseError,
     file_reader::renlib::CommandVariant,
-    p,
 };
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -14,19 +21,44 @@ pub struct UIBoard {
     variants_and_transformations: Vec<(BoardMarker, MoveIndex, Transformation, VariantType)>,
     #[serde(skip)]
     conditions: RenjuConditions,
+    /// Keeps the conditions up to date as the position changes, made on the first update.
+    #[serde(skip)]
+    evaluator: Option<Evaluator>,
     transform: Transformation,
+    rules: RuleSet,
+    /// Undo and redo of the changes made to the graph since it was made or read.
+    #[serde(skip)]
+    history: History,
 }
 
 impl UIBoard {
     pub fn new() -> Self {
-        Self {
-            board: BoardArr::new(15),
+        Self::with_size(renju::board::DEFAULT_SIZE).expect("the default size is valid")
+    }
+
+    /// An empty board of `size`x`size`.
+    pub fn with_size(size: u32) -> Result<Self, ParseError> {
+        Ok(Self {
+            board: BoardArr::new(size),
             moves: vec![],
-            graph: Board::new(),
+            graph: Board::with_size(size)?,
             variants_and_transformations: vec![],
             conditions: RenjuConditions::default(),
+            evaluator: None,
             transform: Transformation::identity(),
-        }
+            rules: RuleSet::default(),
+            history: History::new(),
+        })
+    }
+
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    /// Play by `rules` from now on.
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+        self.update();
     }
 
     pub fn moves(&self) -> &[Point] {
@@ -42,7 +74,10 @@ impl UIBoard {
     }
 
     /// Take care to ensure you call update after doing your stuff.
+    ///
+    /// Changes made here can't be undone, and the ones made before can't be undone any more either.
     pub fn graph_mut(&mut self) -> &mut Board {
+        self.history.clear();
         &mut self.graph
     }
 
@@ -55,9 +90,83 @@ impl UIBoard {
             .get_move(self.graph().current_move())
             .expect("oops")
     }
-    pub fn current_move_mut(&mut self) -> &mut BoardMarker {
-        let curr = self.graph().current_move();
-        self.graph_mut().get_move_mut(curr).expect("oops")
+
+    /// Change the marker of the current move, like its comments. Changes of the same move one after
+    /// another are undone at once.
+    pub fn set_current_marker(&mut self, marker: BoardMarker) {
+        // the editor leaves out empty comments, which a file can have
+        let normalised = |marker: &BoardMarker| {
+            let mut marker = marker.clone();
+            marker.oneline_comment = marker.oneline_comment.filter(|c| !c.is_empty());
+            marker.multiline_comment = marker.multiline_comment.filter(|c| !c.is_empty());
+            marker
+        };
+        if normalised(self.current_move()) == normalised(&marker) {
+            return;
+        }
+        let edit = self
+            .graph
+            .path_to(self.graph.current_move())
+            .map(|node| Edit::SetMarker { node, marker });
+        if let Err(error) = edit.and_then(|edit| self.history.apply_merged(&mut self.graph, edit)) {
+            tracing::error!(%error, "couldn't change the current move");
+        }
+        self.refresh();
+    }
+
+    /// Remove the current move and the moves after it, going back to the move before it.
+    pub fn remove_current_move(&mut self) {
+        let edit = self
+            .graph
+            .path_to(self.graph.current_move())
+            .map(|node| Edit::RemoveSubtree { node });
+        if let Err(error) = edit.and_then(|edit| self.history.apply(&mut self.graph, edit)) {
+            tracing::warn!(%error, "couldn't remove the current move");
+        }
+        self.refresh();
+    }
+
+    /// Make the current move the first one played from the move before it.
+    pub fn promote_current_move(&mut self) {
+        let current = self.graph.current_move();
+        let edit = self.graph.path_to(current).and_then(|mut parent| {
+            let position = parent.pop().ok_or(EditError::Root(current))?;
+            let siblings = self
+                .graph
+                .find_path(&parent)
+                .map_or(0, |parent| self.graph.get_move_children(&parent).len());
+            let mut order = (0..siblings).collect::<Vec<_>>();
+            order[..=position].rotate_right(1);
+            Ok(Edit::Reorder { parent, order })
+        });
+        if let Err(error) = edit.and_then(|edit| self.history.apply(&mut self.graph, edit)) {
+            tracing::warn!(%error, "couldn't promote the current move");
+        }
+        self.refresh();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Undo the last change of the graph, going back to the move that was current before it.
+    pub fn undo(&mut self) {
+        if let Err(error) = self.history.undo(&mut self.graph) {
+            tracing::error!(%error, "couldn't undo");
+        }
+        self.refresh();
+    }
+
+    /// Make the last undone change again.
+    pub fn redo(&mut self) {
+        if let Err(error) = self.history.redo(&mut self.graph) {
+            tracing::error!(%error, "couldn't redo");
+        }
+        self.refresh();
     }
 
     pub fn variants(&self) -> &[(BoardMarker, MoveIndex, Transformation, VariantType)] {
@@ -85,6 +194,7 @@ struct BoardRender {
     y_offset: f32,
     sq_size: f32,
     transform: Transformation,
+    rules: RuleSet,
 }
 
 impl BoardRender {
@@ -119,8 +229,19 @@ impl BoardRender {
                 );
             }
         }
-        // paint guide squares
-        for point in [p![H, 8], p![D, 12], p![D, 4], p![L, 4], p![L, 12]] {
+        // paint guide squares, in the center and four lines in from the corners of a 15x15 board
+        let (center, edge) = (
+            u32::from(lines) / 2,
+            (u32::from(lines) / 2).saturating_sub(4),
+        );
+        let far = u32::from(lines) - 1 - edge;
+        for point in [
+            Point::new(center, center),
+            Point::new(edge, edge),
+            Point::new(edge, far),
+            Point::new(far, far),
+            Point::new(far, edge),
+        ] {
             painter.rect_filled(
                 Rect::from_center_size(self.pos_at(&point).0, Vec2::splat(sq_size * 0.01)),
                 Rounding::ZERO,
@@ -157,7 +278,7 @@ impl BoardRender {
             painter.text(
                 Pos2::new(x_range - 20.0, y_range + incr * line as f32),
                 Align2::CENTER_CENTER,
-                &(15 - line).to_string(),
+                &(lines - line).to_string(),
                 FontId::default(),
                 Color32::DARK_GRAY,
             );
@@ -167,10 +288,10 @@ impl BoardRender {
     fn marks(&self, painter: &Painter, board: &UIBoard) {
         let BoardRender { .. } = *self;
 
-        let children = board.graph.get_children(&board.graph.current_move());
+        let children = board.graph.next_moves(&board.graph.current_move());
         // find other trees with same outcome if placed
         for (m, mi, _transform, variant_type) in &board.variants_and_transformations {
-            if children.iter().any(|m| m == mi) {
+            if children.clone().any(|(m, ..)| m == *mi) {
                 continue;
             }
             if board.board.get_point(m.point).unwrap().color.is_empty() && m.command.is_move() {
@@ -182,8 +303,7 @@ impl BoardRender {
                 }
             }
         }
-        for child in children {
-            let marker = board.graph.get_move(child).unwrap();
+        for (_, marker, _) in children {
             let (_, pos) = self.pos_at(&marker.point);
             painter.circle(pos, 3.0, Color32::WHITE, Stroke::new(2.0, Color32::BLACK));
         }
@@ -224,7 +344,7 @@ impl BoardRender {
                 y_offset + rect.top() + incr * point.y as f32,
             ),
             {
-                let point = transform.apply(*point);
+                let point = transform.apply(*point, u32::from(self.lines));
                 Pos2::new(
                     x_offset + rect.left() + incr * point.x as f32,
                     y_offset + rect.top() + incr * point.y as f32,
@@ -279,7 +399,7 @@ impl BoardRender {
             let point = Point::new(x_div as u32, y_div as u32);
             let (real_pos, _trans_pos) = self.pos_at(&point);
             if real_pos.distance(pos) <= (incr / 2.4) {
-                Some(self.transform.inverse_apply(point))
+                Some(self.transform.inverse_apply(point, u32::from(lines)))
             } else {
                 None
             }
@@ -345,14 +465,31 @@ impl UIBoard {
             match &ui.input(|i| i.keys_down.clone()) {
                 keys if ui.input(|i| i.modifiers.shift) => match keys {
                     _ if ui.input(|i| i.key_pressed(Key::ArrowRight)) => {
-                        let (walked, _children) =
-                            self.graph.up_to_branch(&self.graph().current_move());
-                        if let Some(last) = walked.last() {
+                        // follow the only move until there is a choice
+                        let mut last = None;
+                        let mut node = self.graph().current_move();
+                        loop {
+                            let mut next = self.graph().next_moves(&node);
+                            match (next.next(), next.next()) {
+                                (Some((child, ..)), None) => {
+                                    last = Some(child);
+                                    node = child;
+                                }
+                                _ => break,
+                            }
+                        }
+                        if let Some(last) = &last {
                             self.change_current_move(last);
                         }
                     }
                     _ if ui.input(|i| i.key_pressed(Key::ArrowLeft)) => {
-                        let down = self.graph().get_down(&self.graph().current_move());
+                        // back to the last move with a choice
+                        let down = self
+                            .graph()
+                            .ancestors(&self.graph().current_move())
+                            .skip(1)
+                            .find(|(node, ..)| self.graph().next_moves(node).len() > 1)
+                            .map(|(node, ..)| node);
                         if let Some(parent) = &down {
                             self.change_current_move(parent);
                         }
@@ -360,17 +497,16 @@ impl UIBoard {
                     _ => (),
                 },
                 _ if ui.input(|i| i.key_pressed(Key::ArrowRight)) => {
-                    let up = self.graph().get_children(&self.graph().current_move());
-                    if let &[child] = &up[..] {
-                        self.change_current_move(&child);
-                    }
-                }
-                _ if ui.input(|i| i.key_pressed(Key::ArrowLeft)) => {
-                    let down = self.graph().get_parent_strong(&self.graph().current_move());
-                    if let Some(parent) = &down {
-                        self.change_current_move(parent);
+                    let mut up = self.graph().next_moves(&self.graph().current_move());
+                    let only = match (up.next(), up.next()) {
+                        (Some((child, ..)), None) => Some(child),
+                        _ => None,
+                    };
+                    if let Some(child) = only {
+                        self.play(child);
                     }
                 }
+                _ if ui.input(|i| i.key_pressed(Key::ArrowLeft)) => self.back(),
                 _ => (),
             }
         }
@@ -448,14 +584,18 @@ impl UIBoard {
                                                 self.transform = self.transform.transform(*transform);
                                                 self.change_current_move(&variant);
                                             } else {
-                                                marker.color =
-                                                    Stone::from_bool(self.moves.len() % 2 == 0);
-                                                if let Some((_,mi,t,_)) = self.variants().iter().find(|(m, _, _,vt)|vt == &VariantType::Transformation && m.point == marker.point).cloned() {
+                                                marker.color = self
+                                                    .graph
+                                                    .side_to_move(&self.graph.current_move());
+                                                if let Err(error) = self.board.check_move(point, marker.color, self.rules) {
+                                                    tracing::warn!(%error, "refusing illegal move");
+                                                } else if let Some((_,mi,t,_)) = self.variants().iter().find(|(m, _, _,vt)|vt == &VariantType::Transformation && m.point == marker.point).cloned() {
                                                     tracing::info!(transform = ?t, "entering transform");
                                                     self.transform = self.transform.transform(t);
                                                     self.change_current_move(&mi);
                                                 } else {
                                                     tracing::info!("entering normal move which may be a child");
+                                                    self.history.begin_group(&self.graph);
                                                     let existed = self.add_marker(marker);
                                                     if !existed {
                                                         if let Some((_variant, index, _transform, _variant_type)) = self
@@ -463,14 +603,16 @@ impl UIBoard {
                                                         .iter()
                                                         .find(|(m, _, _transform, _variant_type)| m.point == point)
                                                         {
-                                                            if let Err(e) = self.graph.add_edge(
-                                                                index,
-                                                                &self.graph.current_move(),
-                                                            ) {
+                                                            let link = self.graph.path_to(*index).and_then(|from| {
+                                                                let to = self.graph.path_to(self.graph.current_move())?;
+                                                                self.history.apply(&mut self.graph, Edit::Link { from, to })
+                                                            });
+                                                            if let Err(e) = link {
                                                                 tracing::error!(error = ?e, "Oh no!")
                                                             }
                                                         }
                                                     }
+                                                    self.history.end_group();
                                                 }
                                             }
                                             }
@@ -513,15 +655,9 @@ impl UIBoard {
     pub fn add_marker(&mut self, marker: BoardMarker) -> bool {
         let existing = if marker.command.is_move() {
             self.graph
-                .get_children(&self.graph.current_move())
-                .into_iter()
-                .find(|f| {
-                    if let Some(m) = self.graph.get_move(*f) {
-                        m.point == marker.point
-                    } else {
-                        false
-                    }
-                })
+                .next_moves(&self.graph.current_move())
+                .find(|(_, m, _)| m.point == marker.point)
+                .map(|(f, ..)| f)
         } else {
             None
         };
@@ -531,23 +667,74 @@ impl UIBoard {
             existing
         } else {
             tracing::debug!(?marker, "marker added");
-            self.graph.add_move(self.graph.current_move(), marker)
+            let current = self.graph.current_move();
+            let is_move = marker.command.is_move();
+            let parent = self
+                .graph
+                .path_to(current)
+                .expect("the current move is in the graph");
+            self.history
+                .apply(&mut self.graph, Edit::AddMove { parent, marker })
+                .expect("the current move is in the graph");
+            let added = *self
+                .graph
+                .get_move_children(&current)
+                .last()
+                .expect("a move was just added");
+            if is_move {
+                self.graph.add_move_to_move_list(added);
+            }
+            added
         };
-        let (board, moves) = self.graph.as_board(&idx).unwrap();
-        self.board = board;
-        self.moves = moves;
-        self.update();
+        if idx == self.graph.current_move() {
+            self.refresh();
+        } else {
+            let (board, moves) = self.graph.as_board(&idx).unwrap();
+            self.board = board;
+            self.moves = moves;
+            self.update();
+        }
         existing.is_some()
     }
 
+    /// Go to `node` on the line of moves it was played from.
     #[tracing::instrument(skip(self))]
     pub fn change_current_move(&mut self, node: &MoveIndex) {
-        let mut nodes = self.graph.down_to_root(node);
-        nodes.reverse();
-        self.graph.set_moves(nodes.len() - 1, nodes);
-        let (board, moves) = self.graph.as_board(&self.graph.current_move()).unwrap();
-        self.moves = moves;
+        match Cursor::to(&self.graph, *node) {
+            Ok(cursor) => self.set_cursor(cursor),
+            Err(error) => tracing::warn!(%error, "couldn't go to move"),
+        }
+    }
+
+    /// Go on to `node`, a move played from or linked to from the current move.
+    pub fn play(&mut self, node: MoveIndex) {
+        let mut cursor = self.graph.cursor().clone();
+        match cursor.play(&self.graph, node) {
+            Ok(()) => self.set_cursor(cursor),
+            Err(error) => tracing::warn!(%error, "couldn't play move"),
+        }
+    }
+
+    /// Go back to the move before the current one, the way it was reached.
+    pub fn back(&mut self) {
+        let mut cursor = self.graph.cursor().clone();
+        if cursor.back() {
+            self.set_cursor(cursor);
+        }
+    }
+
+    pub fn set_cursor(&mut self, cursor: Cursor) {
+        if let Err(error) = self.graph.set_cursor(cursor) {
+            tracing::warn!(%error, "couldn't set the cursor");
+        }
+        self.refresh();
+    }
+
+    /// Show the current move again after the graph changed.
+    fn refresh(&mut self) {
+        let (board, moves) = self.graph.cursor().as_board(&self.graph).unwrap();
         self.board = board;
+        self.moves = moves;
         self.update();
     }
 
@@ -555,7 +742,12 @@ impl UIBoard {
         self.update_variants();
         let stone = self.current_move().color;
         if !stone.is_empty() {
-            self.conditions = self.board.renju_conditions(stone.opposite(), None);
+            let evaluator = self
+                .evaluator
+                .get_or_insert_with(|| Evaluator::new(Bitboard::from(&self.board), self.rules));
+            evaluator.set_rules(self.rules);
+            evaluator.update_to(&self.board);
+            self.conditions = evaluator.conditions(stone.opposite()).clone();
             tracing::debug!(?self.conditions.forbidden, "updated conditions");
         } else {
             self.conditions = RenjuConditions::default();
@@ -569,9 +761,8 @@ impl UIBoard {
             .unwrap();
         let children = self
             .graph
-            .get_children(&current_move)
-            .into_iter()
-            .filter_map(|mi| self.graph.get_move(mi));
+            .next_moves(&current_move)
+            .map(|(_, marker, _)| marker);
         self.variants_and_transformations.retain(|(p, .., typ)| {
             !children
                 .clone()
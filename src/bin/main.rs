@@ -1,11 +1,13 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use renju::errors::ParseError;
 
-
-
 use color_eyre::eyre::WrapErr;
-use renju::board::{Board, BoardArr, BoardMarker, MoveIndex, Point};
-use renju::file_reader::open_file_path;
+use renju::board::cursor::Cursor;
+use renju::board::merge::{CommentMerge, MergeOptions};
+use renju::board::rules::RuleSet;
+use renju::board::{Board, BoardArr, BoardMarker, Point};
+use renju::file_reader::encoding::TextEncoding;
+use renju::file_reader::{open_file_path_with_encoding, save_file_path};
 
 fn main() -> Result<(), color_eyre::Report> {
     let _ = dotenv::dotenv();
@@ -16,19 +18,101 @@ fn main() -> Result<(), color_eyre::Report> {
             Arg::new("file")
                 .index(1)
                 .help("File to read from")
+                .value_parser(clap::value_parser!(std::path::PathBuf))
                 .required(true),
         )
-        .arg(Arg::new("output").short('o').help("File to output to"))
-        .arg(Arg::new("no-interactive").short('I'))
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .help("File to output to")
+                .value_parser(clap::value_parser!(std::path::PathBuf)),
+        )
+        .arg(
+            Arg::new("encoding")
+                .long("encoding")
+                .help("Encoding of texts in the file, e.g gbk or shift-jis. Detected if not given")
+                .value_parser(|s: &str| s.parse::<TextEncoding>()),
+        )
+        .arg(
+            Arg::new("rules")
+                .long("rules")
+                .help("Rules to check moves with: renju, freestyle, standard or caro")
+                .default_value("renju")
+                .value_parser(|s: &str| s.parse::<RuleSet>()),
+        )
+        .arg(
+            Arg::new("link-transpositions")
+                .long("link-transpositions")
+                .help("Link moves that lead to the same position as an earlier move, .lib files are linked when they are read")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-interactive")
+                .short('I')
+                .action(ArgAction::SetTrue),
+        )
+        .subcommand(
+            Command::new("merge")
+                .about("Merge libraries into one, the moves of the first file come first")
+                .arg(
+                    Arg::new("files")
+                        .help("Files to merge")
+                        .num_args(2..)
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .help("File to output to")
+                        .value_parser(clap::value_parser!(std::path::PathBuf))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("encoding")
+                        .long("encoding")
+                        .help("Encoding of texts in the files, detected if not given")
+                        .value_parser(|s: &str| s.parse::<TextEncoding>()),
+                )
+                .arg(
+                    Arg::new("symmetry")
+                        .long("symmetry")
+                        .help("Also merge lines that are the same up to rotation and mirroring")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("mark-conflicts")
+                        .long("mark-conflicts")
+                        .help("Mark different comments of the same move instead of joining them")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
         .get_matches();
 
+    if let Some(("merge", matches)) = matches.subcommand() {
+        return merge(matches);
+    }
+
     let path = matches.get_one::<std::path::PathBuf>("file").unwrap();
     tracing::info!("File: {:?}", path);
-    let graph = open_file_path(path).wrap_err_with(|| format!("while parsing file {:?}", path))?;
+    let encoding = matches.get_one::<TextEncoding>("encoding").copied();
+    let rules = *matches.get_one::<RuleSet>("rules").unwrap();
+    let mut graph = open_file_path_with_encoding(path, encoding)
+        .wrap_err_with(|| format!("while parsing file {:?}", path))?;
+    if matches.get_flag("link-transpositions") {
+        let links = graph.link_transpositions();
+        tracing::info!(links, "linked transpositions");
+    }
 
     //let mut file = OpenOptions::new().write(true).create(true).open(format!("{}.dot",path.file_stem().unwrap().to_str().unwrap())).expect("Couldn't create .dot file");
     //write!(file, "{:?}", graph).chain_err(|| "while writing to file");
-    if matches.contains_id("no-interactive") {
+    if let Some(output) = matches.get_one::<std::path::PathBuf>("output") {
+        save_file_path(output, &graph)
+            .wrap_err_with(|| format!("while saving file {:?}", output))?;
+    }
+    if matches.get_flag("no-interactive") {
         return Ok(());
     }
     eprintln!("{:?}", graph);
@@ -48,9 +132,40 @@ fn main() -> Result<(), color_eyre::Report> {
                 return Ok(());
             }
             Ok(line) => {
-                let node = line.parse()?;
-                let (board, moves) = traverse(&graph, node)?;
+                // either a node index or moves from the root, like `h8 i9 g7`
+                let cursor = match line.parse() {
+                    Ok(node) => match Cursor::to(&graph, node) {
+                        Ok(cursor) => cursor,
+                        Err(error) => {
+                            tracing::error!("{error}");
+                            continue;
+                        }
+                    },
+                    Err(_) => {
+                        let points = Point::parse_line_with_size(&line, graph.size())?;
+                        match play(&mut graph, points, rules) {
+                            Ok(cursor) => cursor,
+                            Err(error) => {
+                                tracing::error!("{error}");
+                                continue;
+                            }
+                        }
+                    }
+                };
+                let (board, moves) = traverse(&graph, &cursor)?;
                 eprintln!("{}", board);
+                eprintln!(
+                    "{}",
+                    moves
+                        .iter()
+                        .map(|point| point.notation(graph.size()))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                );
+                match board.outcome(rules) {
+                    Some(outcome) => tracing::info!(?outcome, "game is over"),
+                    None => tracing::info!(to_move = ?graph.side_to_move(&cursor.current())),
+                }
                 if let Some(last_point) = moves.last() {
                     if let Some(BoardMarker {
                         multiline_comment,
@@ -75,6 +190,59 @@ fn main() -> Result<(), color_eyre::Report> {
     }
 }
 
-fn traverse(graph: &Board, index: MoveIndex) -> Result<(BoardArr, Vec<Point>), ParseError> {
-    graph.as_board(&index)
+/// Merge every file of the `merge` subcommand into the first one and save the result.
+fn merge(matches: &clap::ArgMatches) -> Result<(), color_eyre::Report> {
+    let encoding = matches.get_one::<TextEncoding>("encoding").copied();
+    let comments = if matches.get_flag("mark-conflicts") {
+        CommentMerge::MarkConflicts
+    } else {
+        CommentMerge::Concatenate
+    };
+    let options = MergeOptions::new()
+        .with_symmetry(matches.get_flag("symmetry"))
+        .with_comments(comments);
+    let mut paths = matches.get_many::<std::path::PathBuf>("files").unwrap();
+    let first = paths.next().unwrap();
+    let mut graph = open_file_path_with_encoding(first, encoding)
+        .wrap_err_with(|| format!("while parsing file {:?}", first))?;
+    for path in paths {
+        let other = open_file_path_with_encoding(path, encoding)
+            .wrap_err_with(|| format!("while parsing file {:?}", path))?;
+        let stats = graph
+            .merge_with(&other, options)
+            .wrap_err_with(|| format!("while merging file {:?}", path))?;
+        tracing::info!(file = ?path, %stats, "merged");
+    }
+    let output = matches.get_one::<std::path::PathBuf>("output").unwrap();
+    save_file_path(output, &graph).wrap_err_with(|| format!("while saving file {:?}", output))
+}
+
+/// Follow `points` from the root, playing the moves that are not in the tree yet if they are legal.
+fn play(
+    graph: &mut Board,
+    points: Vec<Point>,
+    rules: RuleSet,
+) -> Result<Cursor, color_eyre::Report> {
+    let mut cursor = Cursor::new(graph);
+    for point in points {
+        let node = cursor.current();
+        let child = match graph.find_move_child(&node, &point) {
+            Some(child) => child,
+            None => {
+                let (board, _) = cursor.as_board(graph)?;
+                // passes don't show in the position
+                let stone = graph.side_to_move(&node);
+                board
+                    .check_move(point, stone, rules)
+                    .wrap_err_with(|| format!("can't play {}", point.notation(graph.size())))?;
+                graph.insert_move(node, BoardMarker::new(point, stone))
+            }
+        };
+        cursor.play(graph, child)?;
+    }
+    Ok(cursor)
+}
+
+fn traverse(graph: &Board, cursor: &Cursor) -> Result<(BoardArr, Vec<Point>), ParseError> {
+    cursor.as_board(graph)
 }
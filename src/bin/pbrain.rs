@@ -0,0 +1,25 @@
+//! The engine as a Gomocup brain, reading the protocol on stdin and answering on stdout.
+use clap::{Arg, Command};
+use renju::board::engine::Engine;
+use renju::gomocup::Server;
+
+fn main() -> Result<(), color_eyre::Report> {
+    color_eyre::install()?;
+    renju::util::build_logger()?;
+    let matches = Command::new("pbrain-renju")
+        .arg(
+            Arg::new("depth")
+                .long("depth")
+                .help("Deepest search, in moves")
+                .value_parser(clap::value_parser!(u32)),
+        )
+        .get_matches();
+    let depth = matches
+        .get_one::<u32>("depth")
+        .copied()
+        .unwrap_or(Engine::DEPTH);
+    Server::new()
+        .with_depth(depth)
+        .run(std::io::stdin().lock(), std::io::stdout().lock())?;
+    Ok(())
+}
@@ -1,18 +1,38 @@
 use crate::errors::ParseError;
-pub use board_logic::{BoardArr, BoardMarker, Point, Stone};
+use crate::file_reader::encoding::TextEncoding;
+pub use board_logic::{BoardArr, BoardMarker, Point, Stone, DEFAULT_SIZE, MAX_SIZE};
+use cursor::Cursor;
 use daggy;
 use daggy::Walker;
+use opening::CanonicalOpening;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use zobrist::{PositionIndex, Zobrist};
 
 use std::str::FromStr;
 
+pub mod bitboard;
 pub mod board_logic;
+pub mod cursor;
+pub mod engine;
 pub mod evaluator;
+pub mod history;
+pub mod merge;
+pub mod opening;
+pub mod rules;
+pub mod solver;
+pub mod traversal;
+pub mod zobrist;
 
 pub type BigU = usize;
 pub type NodeIndex = daggy::NodeIndex<BigU>;
 pub type EdgeIndex = daggy::EdgeIndex<BigU>;
 
+/// Edge weight of an edge made by playing a move, see [`Board::insert_move`].
+const MOVE_EDGE: BigU = 255;
+/// Edge weight of an edge linking to an existing node, see [`Board::add_edge`].
+const LINK_EDGE: BigU = 0;
+
 //unsafe impl daggy::petgraph::IndexType for BigU {
 //    #[inline(always)]
 //    fn new (x: BigU) -> Self { x }
@@ -82,12 +102,20 @@ impl FromStr for MoveIndex {
     }
 }
 
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Board {
     graph: daggy::Dag<BoardMarker, BigU, BigU>,
-    /// List of moves currently done
-    move_list: Vec<MoveIndex>,
-    index: usize,
+    /// The current line, see [`Board::cursor`].
+    cursor: Cursor,
+    /// How many times moves were renumbered by removing others, see [`Cursor::check`].
+    generation: usize,
+    /// The position after every move, see [`Board::position_index`].
+    positions: PositionIndex,
+    /// Encoding of the texts in the file this was read from, used when saving it again.
+    text_encoding: Option<TextEncoding>,
+    /// Width and height of the board.
+    size: u32,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -100,21 +128,38 @@ pub enum VariantType {
 }
 
 impl Board {
+    /// Make an empty tree for a standard 15x15 board.
     #[must_use]
     pub fn new() -> Self {
+        Self::with_size(DEFAULT_SIZE).expect("the default size is valid")
+    }
+
+    /// Make an empty tree for a board of `size`x`size`.
+    ///
+    /// Returns [`ParseError::InvalidBoardSize`] if `size` is zero or larger than [`MAX_SIZE`].
+    pub fn with_size(size: u32) -> Result<Self, ParseError> {
+        if !(1..=MAX_SIZE).contains(&size) {
+            return Err(ParseError::InvalidBoardSize(size));
+        }
         let mut board = Self {
             graph: daggy::Dag::with_capacity(255, 255),
-            move_list: vec![],
-            index: 0,
+            cursor: Cursor::root(MoveIndex::new_node(NodeIndex::new(0))),
+            generation: 0,
+            positions: PositionIndex::default(),
+            text_encoding: None,
+            size,
         };
 
         let root = board.new_root(BoardMarker::null());
-        board.move_list.push(root);
-        board
+        board.cursor = Cursor::root(root);
+        Ok(board)
     }
 
     fn new_root(&mut self, marker: BoardMarker) -> MoveIndex {
-        MoveIndex::new_node(self.graph.add_node(marker))
+        let (zobrist, indexed) = self.position_after(Zobrist::new(self.size), &marker);
+        let node = self.graph.add_node(marker);
+        debug_assert_eq!(self.positions.push(zobrist, indexed), node);
+        MoveIndex::new_node(node)
     }
 
     pub fn insert_move(&mut self, parent: MoveIndex, marker: BoardMarker) -> MoveIndex {
@@ -122,7 +167,129 @@ impl Board {
         //     index_in_file = format!("0x{:X}", marker.index_in_file.unwrap_or_default()),
         //     "inserting move to graph"
         // );
-        MoveIndex::new(self.graph.add_child(parent.node_index, 255, marker))
+        let before = self
+            .positions
+            .position(parent.node_index)
+            .copied()
+            .unwrap_or_else(|| Zobrist::new(self.size));
+        let (zobrist, indexed) = self.position_after(before, &marker);
+        let index = MoveIndex::new(self.graph.add_child(parent.node_index, MOVE_EDGE, marker));
+        debug_assert_eq!(self.positions.push(zobrist, indexed), index.node_index);
+        index
+    }
+
+    /// The position after `marker` is put down on `before`, like [`Board::as_board`] does, and
+    /// whether it's a move to index by it.
+    fn position_after(&self, mut before: Zobrist, marker: &BoardMarker) -> (Zobrist, bool) {
+        before.toggle(marker.point, marker.color);
+        (before, marker.command.is_move() && !marker.point.is_null)
+    }
+
+    /// Update the positions after `node` and the moves played after it, when its marker changed or
+    /// it was played from another move.
+    fn reindex(&mut self, node: NodeIndex) {
+        let visits = self
+            .depth_first(&MoveIndex::new_node(node))
+            .map(|(node, _, _)| node.node_index)
+            .collect::<Vec<_>>();
+        for node in visits {
+            let before = self
+                .move_parent(MoveIndex::new_node(node))
+                .ok()
+                .and_then(|parent| self.positions.position(parent).copied())
+                .unwrap_or_else(|| Zobrist::new(self.size));
+            let marker = self
+                .graph
+                .node_weight(node)
+                .expect("visited nodes are in the tree");
+            let (zobrist, indexed) = self.position_after(before, marker);
+            self.positions.set(node, zobrist, indexed);
+        }
+    }
+
+    /// Follow the move from `parent` to the same point as `marker`, inserting `marker` if there is none.
+    ///
+    /// Used when merging lines, so that common prefixes are shared.
+    pub fn get_or_insert_move(&mut self, parent: MoveIndex, marker: BoardMarker) -> MoveIndex {
+        let existing = marker
+            .command
+            .is_move()
+            .then(|| self.find_move_child(&parent, &marker.point))
+            .flatten();
+        match existing {
+            Some(existing) => existing,
+            None => self.insert_move(parent, marker),
+        }
+    }
+
+    /// Find the move played at `point` from `parent`, if any. Markers that are not moves are skipped.
+    #[must_use]
+    pub fn find_move_child(&self, parent: &MoveIndex, point: &Point) -> Option<MoveIndex> {
+        self.graph
+            .children(parent.node_index)
+            .iter(&self.graph)
+            .filter(|(edge, _)| self.graph.edge_weight(*edge) == Some(&MOVE_EDGE))
+            .map(MoveIndex::new)
+            .find(|child| {
+                self.get_move(*child)
+                    .is_some_and(|m| m.command.is_move() && m.point == *point)
+            })
+    }
+
+    /// Follow or insert every marker in `line` starting from `parent`, see [`Board::get_or_insert_move`].
+    ///
+    /// Returns the last move of the line, or `parent` if `line` is empty.
+    pub fn get_or_insert_line(
+        &mut self,
+        parent: MoveIndex,
+        line: impl IntoIterator<Item = BoardMarker>,
+    ) -> MoveIndex {
+        line.into_iter().fold(parent, |latest, marker| {
+            self.get_or_insert_move(latest, marker)
+        })
+    }
+
+    /// Follow the moves at `points` starting from `from`, see [`Point::parse_line`].
+    ///
+    /// Returns the path taken, not including `from`, or [`ParseError::MoveNotFound`] at the first
+    /// point that hasn't been played.
+    pub fn find_line(
+        &self,
+        from: &MoveIndex,
+        points: impl IntoIterator<Item = Point>,
+    ) -> Result<Vec<MoveIndex>, ParseError> {
+        let mut latest = *from;
+        points
+            .into_iter()
+            .map(|point| {
+                latest = self
+                    .find_move_child(&latest, &point)
+                    .ok_or(ParseError::MoveNotFound(point))?;
+                Ok(latest)
+            })
+            .collect()
+    }
+
+    /// Play `points` from `from` with alternating colors, following moves that are already there.
+    ///
+    /// The first point gets the opposite color of `from`, or black if `from` is the root. Returns the
+    /// last move of the line.
+    pub fn get_or_insert_points(
+        &mut self,
+        from: MoveIndex,
+        points: impl IntoIterator<Item = Point>,
+    ) -> MoveIndex {
+        let mut color = match self.get_move(from).map(|m| m.color) {
+            Some(Stone::Empty) | None => Stone::White,
+            Some(color) => color,
+        };
+        self.get_or_insert_line(
+            from,
+            points.into_iter().map(|point| {
+                color = color.opposite();
+                BoardMarker::new(point, color)
+            }),
+        )
     }
 
     #[tracing::instrument(skip(self))]
@@ -132,10 +299,10 @@ impl Board {
         right: &MoveIndex,
     ) -> Result<(), daggy::WouldCycle<usize>> {
         self.graph
-            .add_edge(left.node_index, right.node_index, 0)
+            .add_edge(left.node_index, right.node_index, LINK_EDGE)
             .map(|_| ())
     }
-    /// Add move to graph and move_list
+    /// Add move to graph and to the current line
     pub fn add_move(&mut self, parent: MoveIndex, marker: BoardMarker) -> MoveIndex {
         let idx = self.insert_move(parent, marker.clone());
         if marker.command.is_move() {
@@ -143,17 +310,29 @@ impl Board {
         }
         idx
     }
+    /// Go on to `index`, which has to be played from the current move, forgetting the rest of the
+    /// current line. See [`Cursor::play`] to check that it is.
     pub fn add_move_to_move_list(&mut self, index: MoveIndex) {
-        // tracing::trace!(move_list = ?self.move_list, "adding move to move list");
-        self.move_list.push(index);
-        self.index = self.index.checked_add(1).unwrap();
+        self.cursor.push(index);
     }
 
-    pub fn set_moves(&mut self, idx: usize, list: Vec<MoveIndex>) {
-        self.move_list = list;
-        self.index = idx;
+    /// The current line, that the methods that go back and forth in the tree move.
+    #[must_use]
+    pub fn cursor(&self) -> &Cursor {
+        &self.cursor
+    }
+
+    /// Make `cursor` the current line, if its moves are on this board and follow each other.
+    pub fn set_cursor(&mut self, cursor: Cursor) -> Result<(), EditError> {
+        cursor.check(self)?;
+        self.cursor = cursor;
+        Ok(())
     }
 
+    /// The marker of `node`, to change its texts.
+    ///
+    /// Changing its point or color leaves [`Board::position_index`] behind, use
+    /// [`Board::set_move`] or [`Board::set_pos`] for that.
     pub fn get_move_mut(&mut self, node: MoveIndex) -> Option<&mut BoardMarker> {
         self.graph.node_weight_mut(node.node_index)
     }
@@ -163,10 +342,46 @@ impl Board {
         self.graph.node_weight(node.node_index)
     }
 
+    /// Remove only `node`, leaving its children without a parent. See [`Board::remove_subtree`] to
+    /// remove the moves after it as well.
+    ///
+    /// The last node takes the place of the removed one. The current line stops before `node` if
+    /// it went through it, like after [`Board::remove_subtree`]. The root can't be removed, so
+    /// `None` is returned for it as for a node that isn't on the board.
     pub fn rm_move(&mut self, node: MoveIndex) -> Option<BoardMarker> {
-        self.graph.remove_node(node.node_index)
+        if node.node_index == self.get_root().node_index {
+            return None;
+        }
+        let last = NodeIndex::new(self.graph.node_count().checked_sub(1)?);
+        let removed = self.graph.remove_node(node.node_index)?;
+        self.positions.swap_remove(node.node_index);
+        self.generation += 1;
+        // the moves after it are no longer played from the root either
+        let kept = self
+            .cursor
+            .moves()
+            .iter()
+            .take_while(|m| m.node_index != node.node_index)
+            .map(|m| match m.node_index {
+                moved if moved == last => Some(node.node_index),
+                other => Some(other),
+            });
+        let resolved = kept
+            .chain(std::iter::repeat(None))
+            .take(self.cursor.moves().len())
+            .collect::<Vec<_>>();
+        self.follow_cursor(&resolved);
+        Some(removed)
     }
 
+    /// Changes every time moves are removed from the tree, and the last moves take their places.
+    /// A [`Cursor`] made before that is stale.
+    #[must_use]
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    #[deprecated(note = "walk `Board::next_moves` instead")]
     #[must_use]
     pub fn get_children(&self, parent: &MoveIndex) -> Vec<MoveIndex> {
         let mut result: Vec<MoveIndex> = Vec::new();
@@ -176,13 +391,34 @@ impl Board {
         result
     }
 
+    /// Get the children of `parent` that were played from it, in the order they were inserted.
+    ///
+    /// Unlike [`Board::next_moves`], nodes linked with [`Board::add_edge`] are not included, so
+    /// following these from the root visits every node exactly once.
+    #[must_use]
+    pub fn get_move_children(&self, parent: &MoveIndex) -> Vec<MoveIndex> {
+        let mut result: Vec<MoveIndex> = self
+            .graph
+            .children(parent.node_index)
+            .iter(&self.graph)
+            .filter(|(edge, _)| self.graph.edge_weight(*edge) == Some(&MOVE_EDGE))
+            .map(MoveIndex::new)
+            .collect();
+        result.sort_by_key(|m| m.edge_index);
+        result
+    }
+
+    /// The move `child` was played from, or else one it's linked from. A [`Cursor`] knows which
+    /// one it came from.
     #[must_use]
     #[inline]
     pub fn get_parent_strong(&self, child: &MoveIndex) -> Option<MoveIndex> {
+        // the parent it was played from comes before parents linked with `add_edge`
+        let is_move = |edge| self.graph.edge_weight(edge) == Some(&MOVE_EDGE);
         let mut parents = self.graph.parents(child.node_index);
         if let Some(mut parent) = parents.walk_next(&self.graph) {
             while let Some(other) = parents.walk_next(&self.graph) {
-                if other.0 > parent.0 {
+                if (is_move(other.0), other.0) > (is_move(parent.0), parent.0) {
                     parent = other;
                     tracing::debug!("found better fit for parent");
                 }
@@ -193,6 +429,8 @@ impl Board {
         }
     }
 
+    #[deprecated(note = "walk `Board::next_moves` of the parent instead")]
+    #[allow(deprecated)]
     #[must_use]
     pub fn get_siblings(&self, child: &MoveIndex) -> Vec<MoveIndex> {
         let parent_opt = self.get_parent_strong(child);
@@ -204,7 +442,9 @@ impl Board {
     // Convenience methods, like set comment, set pos etc. Also walk down node until multiple
     // choices. etc.
 
-    /// Gives a simple vec of all the traversed parents including root.
+    /// Gives a simple vec of all the traversed parents including root. See [`Board::ancestors`] to
+    /// walk them without one.
+    #[deprecated(note = "walk `Board::ancestors` instead")]
     #[must_use]
     pub fn down_to_root(&self, node: &MoveIndex) -> Vec<MoveIndex> {
         let mut parent: Option<MoveIndex> = self.get_parent_strong(node);
@@ -221,6 +461,7 @@ impl Board {
     }
 
     /// Gives the amount of moves to travel to root.
+    #[deprecated(note = "count `Board::ancestors` instead")]
     #[must_use]
     pub fn moves_to_root(&self, node: &MoveIndex) -> usize {
         let mut parent: Option<MoveIndex> = self.get_parent_strong(node);
@@ -237,33 +478,105 @@ impl Board {
 
     /// Returns the board as it would look like when `end_node` was played.
     pub fn as_board(&self, end_node: &MoveIndex) -> Result<(BoardArr, Vec<Point>), ParseError> {
-        let move_list: Vec<MoveIndex> = self.down_to_root(end_node);
-        let mut moves: Vec<Point> = Vec::with_capacity(move_list.len());
+        Cursor::to(self, *end_node)?.as_board(self)
+    }
+    /// The stone to play after `node`, the opposite of the latest stone played up to it, or black
+    /// if none was.
+    ///
+    /// Unlike [`BoardArr::to_move`], which counts the stones, this knows about passes.
+    #[must_use]
+    pub fn side_to_move(&self, node: &MoveIndex) -> Stone {
+        self.ancestors(node)
+            .map(|(_, marker, _)| marker.color)
+            .find(|color| !color.is_empty())
+            .map_or(Stone::Black, Stone::opposite)
+    }
 
-        let mut board: BoardArr = BoardArr::new(15);
-        for index_marker in move_list.iter().rev() {
-            let m = match self.get_move(*index_marker) {
-                Some(val) => val.clone(),
-                None => {
-                    return Err(ParseError::Other(format!(
-                        "Couldn't get move at: {:?}",
-                        index_marker
-                    )))
+    /// The canonical opening of the game up to `node`, see [`CanonicalOpening::recognize`].
+    #[must_use]
+    pub fn canonical_opening(
+        &self,
+        node: &MoveIndex,
+    ) -> Option<(CanonicalOpening, Transformation)> {
+        let (_, moves) = self.as_board(node).ok()?;
+        CanonicalOpening::recognize(&moves, self.size)
+    }
+
+    /// Index the games in the tree by their canonical opening.
+    ///
+    /// Returns the third moves of every opening that was played, in the order they were inserted.
+    #[must_use]
+    pub fn openings(&self) -> BTreeMap<CanonicalOpening, Vec<MoveIndex>> {
+        let mut openings = BTreeMap::<_, Vec<_>>::new();
+        let mut stack = vec![(self.get_root(), Vec::with_capacity(3))];
+        while let Some((node, mut moves)) = stack.pop() {
+            if let Some(marker) = self.get_move(node) {
+                if marker.command.is_move() && !marker.point.is_null {
+                    moves.push(marker.point);
                 }
-            };
-            if m.command.is_move() {
-                moves.push(m.point)
-            };
-            if !m.point.is_null {
-                board.set(m)?;
+            }
+            if moves.len() == 3 {
+                if let Some((opening, _)) = CanonicalOpening::recognize(&moves, self.size) {
+                    openings.entry(opening).or_default().push(node);
+                }
+                continue;
+            }
+            for child in self.get_move_children(&node).into_iter().rev() {
+                stack.push((child, moves.clone()));
             }
         }
-        //tracing::info!("board is = {}", board.board);
-        Ok((board, moves))
+        openings
+    }
+
+    /// Every move in the tree by the position it leads to, so transpositions and symmetric
+    /// positions can be looked up by their [`Zobrist`] hash.
+    ///
+    /// It's kept up to date as moves are inserted, removed and moved, so a lookup doesn't walk the
+    /// tree.
+    #[must_use]
+    pub fn position_index(&self) -> &PositionIndex {
+        &self.positions
+    }
+
+    /// The position after `node`, see [`Board::position_index`].
+    #[must_use]
+    pub fn zobrist(&self, node: &MoveIndex) -> Option<&Zobrist> {
+        self.positions.position(node.node_index)
     }
+
+    /// Link every move that leads to the same position as an earlier one to it with
+    /// [`Board::add_edge`], so the lines after both can be found from either.
+    ///
+    /// Only exact transpositions are linked, not symmetric ones, and only with the same side to
+    /// move. Returns the number of links added.
+    pub fn link_transpositions(&mut self) -> usize {
+        let mut links = 0;
+        let groups = self.positions.groups().collect::<Vec<_>>();
+        for group in groups {
+            let group = group
+                .into_iter()
+                .map(|node| self.move_index(node))
+                .collect::<Vec<_>>();
+            let (first, rest) = group.split_first().expect("groups have two or more nodes");
+            for node in rest {
+                let already = self
+                    .graph
+                    .find_edge(node.node_index, first.node_index)
+                    .is_some();
+                if !already && self.same_position(first, node) && self.add_edge(node, first).is_ok()
+                {
+                    links += 1;
+                }
+            }
+        }
+        links
+    }
+
     /// Move up in the tree until there is a branch, i.e multiple choices for the next move, or no more moves.
     ///
     /// Returns the children that were walked  and the children that caused the branch, if any.
+    #[deprecated(note = "walk `Board::next_moves` instead")]
+    #[allow(deprecated)]
     #[must_use]
     pub fn up_to_branch(&self, node: &MoveIndex) -> (Vec<MoveIndex>, Vec<MoveIndex>) {
         // Check if we should wrap the result in an option.
@@ -275,9 +588,28 @@ impl Board {
         }
         (branch_decendants, children)
     }
+    /// Follow the first move played from every node, starting at `node`, until there are no more moves.
+    ///
+    /// Returns the walked moves, not including `node`.
+    #[must_use]
+    pub fn main_line(&self, node: &MoveIndex) -> Vec<MoveIndex> {
+        let mut line = Vec::new();
+        let mut current = *node;
+        while let Some(next) = self
+            .get_move_children(&current)
+            .into_iter()
+            .find(|child| self.get_move(*child).is_some_and(|m| m.command.is_move()))
+        {
+            line.push(next);
+            current = next;
+        }
+        line
+    }
     /// Move down in tree until there is a branch, i.e move has multiple children.
     ///
     /// Returns the branching node, e.g the node which has multiple children, if any.
+    #[deprecated(note = "find the move in `Board::ancestors` instead")]
+    #[allow(deprecated)]
     #[must_use]
     pub fn down_to_branch(&self, node: &MoveIndex) -> Option<MoveIndex> {
         let mut parent: Option<MoveIndex> = self.get_parent_strong(node);
@@ -311,256 +643,689 @@ impl Board {
             };
             marker.set_pos(&point);
         }
+        self.reindex(node.node_index);
         Ok(())
     }
 
+    /// Replace the marker of `node`, returning the one it had.
+    ///
+    /// Unlike changing it with [`Board::get_move_mut`], this keeps [`Board::position_index`] up to
+    /// date if the point or color changes.
+    pub fn set_move(
+        &mut self,
+        node: MoveIndex,
+        marker: BoardMarker,
+    ) -> Result<BoardMarker, EditError> {
+        self.check_node(node)?;
+        let current = self.get_move_mut(node).expect("the move is in the tree");
+        let changed = (current.point, current.color, current.command.is_move())
+            != (marker.point, marker.color, marker.command.is_move());
+        let previous = std::mem::replace(current, marker);
+        if changed {
+            self.reindex(node.node_index);
+        }
+        Ok(previous)
+    }
+
+    /// Remove `node` and every move played after it.
+    ///
+    /// The current line is cut off before `node` if it goes through it. The last moves of the tree
+    /// take the places of the removed ones, so their [`MoveIndex`]es change, see [`Renumbered`].
+    /// The other moves keep their nodes, only the edges of the moves that changed are new.
+    pub fn remove_subtree(&mut self, node: MoveIndex) -> Result<Renumbered, EditError> {
+        let parent = self.move_parent(node)?;
+        let mut children = HashMap::new();
+        let mut siblings = self.children_of(parent, &children);
+        siblings.retain(|sibling| *sibling != node.node_index);
+        children.insert(parent, siblings);
+        Ok(self.edit(&children, &HashMap::new(), &[]))
+    }
+
+    /// Make `node` the first move played from its parent, the one [`Board::main_line`] follows.
+    ///
+    /// Returns the new index of `node`.
+    pub fn promote_variation(&mut self, node: MoveIndex) -> Result<MoveIndex, EditError> {
+        let parent = self.move_parent(node)?;
+        let mut siblings = self.children_of(parent, &HashMap::new());
+        siblings.retain(|sibling| *sibling != node.node_index);
+        siblings.insert(0, node.node_index);
+        self.edit(&HashMap::from([(parent, siblings)]), &HashMap::new(), &[]);
+        Ok(self.move_index(node.node_index))
+    }
+
+    /// Promote `node` and every move before it, see [`Board::promote_variation`], so the main line
+    /// from the root goes through `node`.
+    ///
+    /// Returns the new index of `node`.
+    pub fn make_main_line(&mut self, node: MoveIndex) -> Result<MoveIndex, EditError> {
+        self.move_parent(node)?;
+        let mut children = HashMap::new();
+        let mut child = node.node_index;
+        while let Ok(parent) = self.move_parent(MoveIndex::new_node(child)) {
+            let mut siblings = self.children_of(parent, &children);
+            siblings.retain(|sibling| *sibling != child);
+            siblings.insert(0, child);
+            children.insert(parent, siblings);
+            child = parent;
+        }
+        self.edit(&children, &HashMap::new(), &[]);
+        Ok(self.move_index(node.node_index))
+    }
+
+    /// Put the moves played from `parent` in the order of `order`, which has to have every one of
+    /// them once, see [`Board::get_move_children`].
+    ///
+    /// Returns the new indices of the moves, in the new order.
+    pub fn reorder_children(
+        &mut self,
+        parent: MoveIndex,
+        order: &[MoveIndex],
+    ) -> Result<Vec<MoveIndex>, EditError> {
+        self.check_node(parent)?;
+        let mut children = self.children_of(parent.node_index, &HashMap::new());
+        let order = order.iter().map(|m| m.node_index).collect::<Vec<_>>();
+        for child in &order {
+            if !children.contains(child) {
+                return Err(EditError::NotChild(MoveIndex::new_node(*child), parent));
+            }
+        }
+        children.sort();
+        let mut sorted = order.clone();
+        sorted.sort();
+        if sorted != children {
+            return Err(EditError::NotAPermutation(parent));
+        }
+        self.edit(
+            &HashMap::from([(parent.node_index, order.clone())]),
+            &HashMap::new(),
+            &[],
+        );
+        Ok(order
+            .into_iter()
+            .map(|child| self.move_index(child))
+            .collect())
+    }
+
+    /// Move `node` and the moves after it to be played from `new_parent` instead, after the moves
+    /// that are already played from it.
+    ///
+    /// The colors of the moved stones are kept. Returns the new index of `node`, or
+    /// [`EditError::WouldCycle`] if a link from one of the moved moves leads to `new_parent`.
+    pub fn move_subtree(
+        &mut self,
+        node: MoveIndex,
+        new_parent: MoveIndex,
+    ) -> Result<MoveIndex, EditError> {
+        let parent = self.move_parent(node)?;
+        self.check_node(new_parent)?;
+        let mut ancestor = Some(new_parent.node_index);
+        while let Some(current) = ancestor {
+            if current == node.node_index {
+                return Err(EditError::IntoItself { node, new_parent });
+            }
+            ancestor = self.move_parent(MoveIndex::new_node(current)).ok();
+        }
+        // only through a link, the link would be dropped by the edit
+        if self.reaches(node.node_index, new_parent.node_index) {
+            return Err(EditError::WouldCycle(node, new_parent));
+        }
+        let mut children = HashMap::new();
+        let mut siblings = self.children_of(parent, &children);
+        siblings.retain(|sibling| *sibling != node.node_index);
+        children.insert(parent, siblings);
+        let mut moved = self.children_of(new_parent.node_index, &children);
+        moved.push(node.node_index);
+        children.insert(new_parent.node_index, moved);
+        self.edit(&children, &HashMap::new(), &[]);
+        Ok(self.move_index(node.node_index))
+    }
+
+    /// Merge `other` into `keep`, two moves on the same point played from the same parent.
+    ///
+    /// The moves played after `other` are moved to `keep`, merging the ones on the same point as a
+    /// move after `keep` the same way. Comments of `other` are only kept where `keep` has none, and
+    /// links to `other` go to `keep`.
+    ///
+    /// The merged moves are removed, which renumbers others like [`Board::remove_subtree`].
+    /// Returns the new index of `keep` and how the moves were renumbered.
+    pub fn merge_siblings(
+        &mut self,
+        keep: MoveIndex,
+        other: MoveIndex,
+    ) -> Result<(MoveIndex, Renumbered), EditError> {
+        let parent = self.move_parent(keep)?;
+        if self.move_parent(other)? != parent || keep.node_index == other.node_index {
+            return Err(EditError::NotSiblings(keep, other));
+        }
+        if !self.same_move(keep.node_index, other.node_index) {
+            return Err(EditError::DifferentMoves(keep, other));
+        }
+        let mut children = HashMap::new();
+        let mut siblings = self.children_of(parent, &children);
+        siblings.retain(|sibling| *sibling != other.node_index);
+        children.insert(parent, siblings);
+        let mut merged = HashMap::new();
+        self.merge_into(
+            keep.node_index,
+            other.node_index,
+            &mut children,
+            &mut merged,
+        );
+        let renumbered = self.edit(&children, &merged, &[]);
+        Ok((
+            self.move_index(renumbered.node(keep.node_index)),
+            renumbered,
+        ))
+    }
+
+    /// The position of every move on the way from the root to `node` among the moves played
+    /// before it, see [`Board::get_move_children`].
+    ///
+    /// Unlike a [`MoveIndex`], this stays the same when other parts of the tree are edited, and
+    /// [`Board::find_path`] finds the move again.
+    pub fn path_to(&self, node: MoveIndex) -> Result<Vec<usize>, EditError> {
+        self.check_node(node)?;
+        let mut path = Vec::new();
+        let mut child = node.node_index;
+        while let Ok(parent) = self.move_parent(MoveIndex::new_node(child)) {
+            let position = self
+                .children_of(parent, &HashMap::new())
+                .iter()
+                .position(|sibling| *sibling == child)
+                .expect("a move is one of the moves played from its parent");
+            path.push(position);
+            child = parent;
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// Find the move at `path`, see [`Board::path_to`].
+    #[must_use]
+    pub fn find_path(&self, path: &[usize]) -> Option<MoveIndex> {
+        path.iter().try_fold(self.get_root(), |node, position| {
+            self.get_move_children(&node).get(*position).copied()
+        })
+    }
+
+    /// Make `node` the current move, with the moves played before it as the current line, see
+    /// [`Cursor::to`].
+    pub fn set_current_move(&mut self, node: MoveIndex) -> Result<(), EditError> {
+        self.cursor = Cursor::to(self, node)?;
+        Ok(())
+    }
+
+    /// Every link added with [`Board::add_edge`], from the linked move to the move it is linked to.
+    #[must_use]
+    pub fn links(&self) -> Vec<(MoveIndex, MoveIndex)> {
+        self.graph
+            .raw_edges()
+            .iter()
+            .filter(|edge| edge.weight == LINK_EDGE)
+            .map(|edge| {
+                (
+                    self.move_index(edge.source()),
+                    self.move_index(edge.target()),
+                )
+            })
+            .collect()
+    }
+
+    /// Remove the link from `from` to `to` added with [`Board::add_edge`].
+    pub fn remove_link(&mut self, from: MoveIndex, to: MoveIndex) -> Result<(), EditError> {
+        // a link can be next to the edge the move was played with
+        let linked = self.graph.raw_edges().iter().any(|edge| {
+            edge.weight == LINK_EDGE
+                && (edge.source(), edge.target()) == (from.node_index, to.node_index)
+        });
+        if !linked {
+            return Err(EditError::NotLinked(from, to));
+        }
+        self.edit(
+            &HashMap::new(),
+            &HashMap::new(),
+            &[(from.node_index, to.node_index)],
+        );
+        Ok(())
+    }
+
+    fn merge_into(
+        &mut self,
+        keep: NodeIndex,
+        other: NodeIndex,
+        children: &mut HashMap<NodeIndex, Vec<NodeIndex>>,
+        merged: &mut HashMap<NodeIndex, NodeIndex>,
+    ) {
+        merged.insert(other, keep);
+        let other_marker = self.graph[other].clone();
+        let marker = &mut self.graph[keep];
+        for (comment, other) in [
+            (&mut marker.oneline_comment, other_marker.oneline_comment),
+            (
+                &mut marker.multiline_comment,
+                other_marker.multiline_comment,
+            ),
+            (&mut marker.board_text, other_marker.board_text),
+        ] {
+            if comment.is_none() {
+                *comment = other;
+            }
+        }
+        let mut kept = self.children_of(keep, children);
+        for child in self.children_of(other, children) {
+            match kept.iter().find(|kept| self.same_move(**kept, child)) {
+                Some(&same) => self.merge_into(same, child, children, merged),
+                None => kept.push(child),
+            }
+        }
+        children.insert(keep, kept);
+    }
+
+    /// Returns `true` if `left` and `right` are moves on the same point.
+    fn same_move(&self, left: NodeIndex, right: NodeIndex) -> bool {
+        let (left, right) = (&self.graph[left], &self.graph[right]);
+        left.command.is_move() && right.command.is_move() && left.point == right.point
+    }
+
+    fn check_node(&self, node: MoveIndex) -> Result<(), EditError> {
+        match self.graph.node_weight(node.node_index) {
+            Some(_) => Ok(()),
+            None => Err(EditError::NotFound(node)),
+        }
+    }
+
+    /// The node `node` was played from.
+    fn move_parent(&self, node: MoveIndex) -> Result<NodeIndex, EditError> {
+        self.check_node(node)?;
+        self.graph
+            .parents(node.node_index)
+            .iter(&self.graph)
+            .find(|(edge, _)| self.graph.edge_weight(*edge) == Some(&MOVE_EDGE))
+            .map(|(_, parent)| parent)
+            .ok_or(EditError::Root(node))
+    }
+
+    /// Whether `to` is played after `from`, or linked to from a move that is.
+    fn reaches(&self, from: NodeIndex, to: NodeIndex) -> bool {
+        daggy::petgraph::algo::has_path_connecting(self.graph.graph(), from, to, None)
+    }
+
+    /// Remove the nodes and edges added since the graph had `nodes` nodes and `edges` edges.
+    ///
+    /// They are the last ones, so unlike [`Board::rm_move`] no other node or edge is renumbered.
+    fn truncate(&mut self, nodes: usize, edges: usize) {
+        for edge in (edges..self.graph.edge_count()).rev() {
+            self.graph.remove_edge(EdgeIndex::new(edge));
+        }
+        for node in (nodes..self.graph.node_count()).rev() {
+            let node = NodeIndex::new(node);
+            self.graph.remove_node(node);
+            self.positions.swap_remove(node);
+        }
+    }
+
+    /// The index of `node`, with the edge it was played from.
+    fn move_index(&self, node: NodeIndex) -> MoveIndex {
+        self.graph
+            .parents(node)
+            .iter(&self.graph)
+            .find(|(edge, _)| self.graph.edge_weight(*edge) == Some(&MOVE_EDGE))
+            .map_or(MoveIndex::new_node(node), |(edge, _)| {
+                MoveIndex::new((edge, node))
+            })
+    }
+
+    /// The moves played from `node`, in order, from `children` if it has them.
+    fn children_of(
+        &self,
+        node: NodeIndex,
+        children: &HashMap<NodeIndex, Vec<NodeIndex>>,
+    ) -> Vec<NodeIndex> {
+        children.get(&node).cloned().unwrap_or_else(|| {
+            self.get_move_children(&MoveIndex::new_node(node))
+                .into_iter()
+                .map(|child| child.node_index)
+                .collect()
+        })
+    }
+
+    /// Play the moves in `children` from their nodes instead, in that order. The moves that are no
+    /// longer played from anything are removed with the moves after them, links to nodes in
+    /// `merged` go to the node they were merged into instead and the links in `unlinked` are
+    /// removed.
+    ///
+    /// The order of moves is the order of their edges, and removing an edge moves the last edge to
+    /// its place, so the edges from the first one that changes on are taken off the end and added
+    /// again. The edges before it and the nodes stay where they are, except that the last nodes
+    /// take the places of the removed ones.
+    fn edit(
+        &mut self,
+        children: &HashMap<NodeIndex, Vec<NodeIndex>>,
+        merged: &HashMap<NodeIndex, NodeIndex>,
+        unlinked: &[(NodeIndex, NodeIndex)],
+    ) -> Renumbered {
+        let listed = children.values().flatten().collect::<HashSet<_>>();
+        // the moves played from another move than before, whose positions change
+        let replayed = children
+            .iter()
+            .flat_map(|(parent, children)| children.iter().map(move |child| (*parent, *child)))
+            .filter(|(parent, child)| self.move_parent(MoveIndex::new_node(*child)) != Ok(*parent))
+            .map(|(_, child)| child)
+            .collect::<Vec<_>>();
+        let mut stack = children
+            .keys()
+            .flat_map(|parent| self.children_of(*parent, &HashMap::new()))
+            .collect::<Vec<_>>();
+        let mut dropped = HashSet::new();
+        while let Some(node) = stack.pop() {
+            if !listed.contains(&node) && dropped.insert(node) {
+                stack.extend(self.children_of(node, &HashMap::new()));
+            }
+        }
+
+        let changes = |edge: &daggy::petgraph::graph::Edge<BigU, BigU>| {
+            let ends = (edge.source(), edge.target());
+            (edge.weight == MOVE_EDGE && children.contains_key(&ends.0))
+                || dropped.contains(&ends.0)
+                || dropped.contains(&ends.1)
+                || (edge.weight == LINK_EDGE && unlinked.contains(&ends))
+        };
+        let edges = self.graph.edge_count();
+        let cut = self
+            .graph
+            .raw_edges()
+            .iter()
+            .position(changes)
+            .unwrap_or(edges);
+        let mut tail = (cut..edges)
+            .rev()
+            .filter_map(|edge| {
+                let edge = EdgeIndex::new(edge);
+                let (source, target) = self.graph.edge_endpoints(edge)?;
+                Some((source, target, self.graph.remove_edge(edge)?))
+            })
+            .collect::<Vec<_>>();
+        tail.reverse();
+
+        let resolve = |mut node: NodeIndex| {
+            while let Some(into) = merged.get(&node) {
+                node = *into;
+            }
+            (!dropped.contains(&node)).then_some(node)
+        };
+        let mut moves = Vec::new();
+        let mut links = Vec::new();
+        for (source, target, weight) in tail {
+            if weight == MOVE_EDGE {
+                if !children.contains_key(&source) && !dropped.contains(&source) {
+                    moves.push((source, target, MOVE_EDGE));
+                }
+            } else if !unlinked.contains(&(source, target)) {
+                if let (Some(source), Some(target)) = (resolve(source), resolve(target)) {
+                    links.push((source, target));
+                }
+            }
+        }
+        let mut parents = children.keys().collect::<Vec<_>>();
+        parents.sort_unstable();
+        for parent in parents {
+            moves.extend(
+                children[parent]
+                    .iter()
+                    .map(|child| (*parent, *child, MOVE_EDGE)),
+            );
+        }
+        self.graph
+            .add_edges(moves)
+            .expect("the moves played from each other can't make a cycle");
+        for (source, target) in links {
+            if source != target {
+                // a link that would make a cycle after a merge is dropped
+                let _ = self.graph.add_edge(source, target, LINK_EDGE);
+            }
+        }
+
+        let resolved = self
+            .cursor
+            .moves()
+            .iter()
+            .map(|m| resolve(m.node_index))
+            .collect::<Vec<_>>();
+        let mut renumbered = Renumbered {
+            moved: HashMap::new(),
+            removed: dropped.len(),
+        };
+        // the node at an index that was moved there, by the index it had before
+        let mut moved_from = HashMap::new();
+        let mut dropped = dropped.into_iter().collect::<Vec<_>>();
+        dropped.sort_unstable_by(|a, b| b.cmp(a));
+        if !dropped.is_empty() {
+            self.generation += 1;
+        }
+        for node in dropped {
+            let last = NodeIndex::new(self.graph.node_count() - 1);
+            self.graph.remove_node(node);
+            self.positions.swap_remove(node);
+            let original = moved_from.remove(&last).unwrap_or(last);
+            if last != node {
+                moved_from.insert(node, original);
+                renumbered.moved.insert(original, node);
+            }
+        }
+        let resolved = resolved
+            .into_iter()
+            .map(|node| node.map(|node| renumbered.node(node)))
+            .collect::<Vec<_>>();
+        for node in replayed {
+            self.reindex(renumbered.node(node));
+        }
+        self.follow_cursor(&resolved);
+        renumbered
+    }
+
+    /// Rebuild the current line after an edit, from the node each of its moves is now, or `None`
+    /// if it was removed.
+    fn follow_cursor(&mut self, resolved: &[Option<NodeIndex>]) {
+        // the current line goes to the current move, or to the last move before it that is left,
+        // and on with the moves after it as long as they are still played from each other
+        let index = self.cursor.index();
+        let current = resolved
+            .get(index)
+            .copied()
+            .flatten()
+            .or_else(|| resolved.iter().map_while(|node| *node).last())
+            .expect("the root is always kept");
+        let mut line = vec![current];
+        while let Ok(parent) = self.move_parent(MoveIndex::new_node(line[line.len() - 1])) {
+            line.push(parent);
+        }
+        line.reverse();
+        let after = resolved.get(index + 1..).unwrap_or_default();
+        let index = line.len() - 1;
+        for next in after.iter().map_while(|node| *node) {
+            if self.move_parent(MoveIndex::new_node(next)) != Ok(line[line.len() - 1]) {
+                break;
+            }
+            line.push(next);
+        }
+        let line = line.into_iter().map(|node| self.move_index(node)).collect();
+        self.cursor = Cursor::from_line(line, index, self.generation);
+    }
+
     /// get the first branch below this node. The children of this node are the branches
     ///
     /// Only works if the node is marked as down
+    #[deprecated(note = "find the move in `Board::ancestors` instead")]
+    #[allow(deprecated)]
     #[must_use]
     pub fn get_down(&self, index: &MoveIndex) -> Option<MoveIndex> {
         self.down_to_branch(index)
     }
 
     /// get the branches next to this node
+    #[deprecated(note = "walk `Board::next_moves` instead")]
+    #[allow(deprecated)]
     #[must_use]
     pub fn get_right(&self, index: &MoveIndex) -> Vec<MoveIndex> {
         self.get_children(index)
     }
 
-    /// Get indexes for all paths which lead to the same outcome
+    /// The moves elsewhere in the tree that lead to the position at `index` with one more stone,
+    /// found with [`Board::position_index`].
+    ///
+    /// The marker is the move that adds the stone, with its point as seen from `index`, and the
+    /// index is the move that reaches the position. A move reached from the same stones played in
+    /// another order is a [`VariantType::Variant`], one after the same moves in a symmetric
+    /// position is a [`VariantType::Transformation`]. The moves played from `index` itself are
+    /// left out.
     #[tracing::instrument(skip(self))]
     pub fn get_variants_and_transformations(
         &self,
         index: MoveIndex,
     ) -> Result<Vec<(BoardMarker, MoveIndex, Transformation, VariantType)>, ParseError> {
-        // recursive walk up the tree, discarding all branches that don't fit.
-        fn walk_up(
-            walked: Vec<(Point, &Stone, &MoveIndex)>,
-            graph: &Board,
-            move_list: &Vec<(&Point, &Stone, &MoveIndex)>,
-            index: MoveIndex,
-        ) -> Vec<(BoardMarker, MoveIndex, Transformation, VariantType)> {
-            //tracing::debug!(?walked, ?move_list, ?index);
-            // FIXME: currently we assume that no node can hit the same point twice, that might not be wise.
-
-            //tracing::trace!("{:?}", &walked[..walked.len().saturating_sub(1)]);
-            //tracing::trace!("{move_list:?}");
-
-            let mut diff_explored = 0;
-            'transform: for transform in Transformation::types() {
-                // FIXME: single H8 is special, there are no valid variants on it except identity.
-                if transform != Transformation::identity()
-                    && matches!(
-                        move_list[..],
-                        [(
-                            &Point {
-                                x: 7,
-                                y: 7,
-                                is_null: false
-                            },
-                            ..
-                        )]
-                    )
-                {
-                    diff_explored += 1;
-                    continue;
-                }
-
-                let mut diff = None;
-                let walked = walked
-                    .iter()
-                    .map(|(p, c, i)| (transform.apply(*p), c, i))
-                    .collect::<Vec<_>>();
-
-                // FIXME: We should discard transforms we already know are not possible.
-                // We could just check the last two moves walked I think
-                for (point, stone, &index) in &walked {
-                    if !move_list.iter().any(|(p, s, _)| (p, s) == (&&point, stone)) {
-                        // if there's two mismatches, this couldn't possible be the right path...
-                        if diff.is_some() {
-                            tracing::trace!("found mismatches");
-                            diff_explored += 1;
-                            continue 'transform;
-                        }
-                        diff = Some((point, index));
-                    }
-                }
-
-                if walked.len() == move_list.len() + 1 && diff.is_some() {
-                    //tracing::debug!("diff {diff:?}, walked: {walked:?}, move_list: {move_list:?}");
-                    // if exactly the same path, not a variant...
-                    let mut same = true;
-                    for (w, ml) in walked
-                        .iter()
-                        .filter(|m| !m.1.is_empty())
-                        .zip(move_list.iter().filter(|m| !m.1.is_empty()))
-                    {
-                        if w.0 != *ml.0 || w.1 != &ml.1 {
-                            same = false;
-                            break;
-                        }
-                    }
+        self.check_node(index)
+            .map_err(|error| ParseError::Other(format!("Couldn't get variants: {error}")))?;
+        let line = self.stone_line(&index);
+        if line.is_empty() {
+            return Ok(vec![]);
+        }
+        let position = *self.zobrist(&index).expect("the move was found");
+        let mut stones = line.clone();
+        stones.sort_unstable();
+
+        let mut result = Vec::new();
+        for (x, y) in (0..self.size).flat_map(|y| (0..self.size).map(move |x| (x, y))) {
+            let point = Point::new(x, y);
+            if line.iter().any(|(p, _)| *p == point) {
+                continue;
+            }
+            for stone in [Stone::Black, Stone::White] {
+                let mut next = position;
+                next.toggle(point, stone);
+                for (node, transform) in self.positions.symmetric(&next) {
+                    let node = self.move_index(node);
+                    let other = self
+                        .stone_line(&node)
+                        .into_iter()
+                        .map(|(p, s)| (transform.apply(p, self.size), s))
+                        .collect::<Vec<_>>();
+                    let Some(added) = other.iter().position(|played| *played == (point, stone))
+                    else {
+                        continue;
+                    };
+                    let same = other[..added] == line[..];
                     if same && transform == Transformation::identity() {
                         continue;
                     }
-                    if same
-                        && matches!(
-                            transform,
-                            Transformation {
-                                rotation: Rotation::None,
-                                mirror: Mirror::Horizontal | Mirror::Vertical
-                            }
-                        )
-                    {
+                    let mut rest = other;
+                    rest.remove(added);
+                    rest.sort_unstable();
+                    // different stones with the same hash
+                    if rest != stones {
                         continue;
                     }
-                    let diff = diff.unwrap();
-                    // we've found a variant, return it.
-                    let mut marker = graph.get_move(*diff.1).unwrap().clone();
-                    marker.point = *diff.0;
-                    tracing::debug!("we got a variant on {transform:?}, {marker:?} at {index:?}");
-                    let variant_type = if move_list
-                        .iter()
-                        .map(|(p, ..)| p)
-                        .zip(walked.iter().map(|(p, ..)| p))
-                        .any(|(p1, p2)| p1 != &p2)
-                    {
-                        VariantType::Variant
-                    } else {
+                    let Some(mut marker) = self
+                        .stone_moves(&node)
+                        .nth(rest.len() - added)
+                        .map(|(_, marker, _)| marker.clone())
+                    else {
+                        continue;
+                    };
+                    marker.point = point;
+                    let variant_type = if same {
                         VariantType::Transformation
+                    } else {
+                        VariantType::Variant
                     };
-                    return vec![(marker, index, transform, variant_type)];
-                }
-            }
-            if diff_explored == Transformation::types().len() {
-                return vec![];
-            }
-            let children = graph.get_children(&index);
-            tracing::trace!(?children);
-
-            let mut result = Vec::new();
-            for child in children {
-                if let Some(child_m) = graph.get_move(child) {
-                    let mut new_walked = walked.clone();
-                    new_walked.push((child_m.point, &child_m.color, &child));
-                    result.extend(walk_up(new_walked, graph, move_list, child));
-                } else {
-                    todo!()
+                    tracing::debug!(?transform, ?marker, ?node, "found a variant");
+                    result.push((marker, node, transform, variant_type));
                 }
             }
-            result
         }
-
-        let moves = self
-            .move_list()
-            .iter()
-            .filter_map(|mi| Some((self.get_move(*mi)?, mi)))
-            .filter(|(m, _)| !m.color.is_empty())
-            .map(|(m, mi)| (&m.point, &m.color, mi))
-            .collect::<Vec<_>>();
-        if moves.is_empty() {
-            return Ok(vec![]);
-        }
-        tracing::info!(moves = moves.len().saturating_sub(1), "starting walk");
-        let result = walk_up(Default::default(), self, &moves, self.get_root());
-        tracing::info!("walk ended");
         Ok(result)
     }
 
-    /// Only used for renlib parsing
-    ///
-    /// RenLib/RenLibDoc.cpp:625
-    #[must_use]
-    pub(crate) fn get_variant_weird(
-        &self,
-        index: &MoveIndex,
-        point: &Point,
-        _color: &Stone,
-    ) -> Option<(&BoardMarker, MoveIndex)> {
-        // this function does something.
-        // Get the first branch below this node
-        if let Some(node) = self.get_down(index) {
-            if let Some(
-                marker @ BoardMarker {
-                    point: point2,
-                    color: _color2,
-                    command,
-                    ..
-                },
-            ) = self.get_move(node)
-            {
-                if !command.is_down() {
-                    return None;
-                }
-                // if that branch is the same point return it.
-                if point2 == point {
-                    return Some((marker, node));
-                } else if command.is_right() {
-                    // Get
-                    for right in self.get_right(&node) {
-                        if let Some(marker @ BoardMarker { point: point2, .. }) =
-                            self.get_move(right)
-                        {
-                            if point2 == point {
-                                return Some((marker, right));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        None
+    /// Whether the same stones are on the board after `left` and `right`, with the same side to
+    /// move. Positions with the same hash can still differ, either by a collision or by a pass.
+    fn same_position(&self, left: &MoveIndex, right: &MoveIndex) -> bool {
+        let stones = |node| {
+            let mut stones = self.stone_line(node);
+            stones.sort_unstable();
+            stones
+        };
+        stones(left) == stones(right) && self.side_to_move(left) == self.side_to_move(right)
+    }
+
+    /// The moves that put a stone on the board, from `node` back to the root.
+    fn stone_moves(&self, node: &MoveIndex) -> impl Iterator<Item = traversal::Visit<'_>> {
+        self.ancestors(node)
+            .filter(|(_, marker, _)| !marker.color.is_empty() && !marker.point.is_null)
+    }
+
+    /// The stones played from the root to `node`, in order.
+    fn stone_line(&self, node: &MoveIndex) -> Vec<(Point, Stone)> {
+        let mut line = self
+            .stone_moves(node)
+            .map(|(_, marker, _)| (marker.point, marker.color))
+            .collect::<Vec<_>>();
+        line.reverse();
+        line
     }
 
     #[must_use]
     #[track_caller]
     pub fn current_move(&self) -> MoveIndex {
-        *self
-            .move_list
-            .get(self.index)
-            .expect("index should be up to date with move_list")
+        self.cursor.current()
     }
 
     #[must_use]
     pub fn get_root(&self) -> MoveIndex {
-        *self
-            .move_list
-            .first()
-            .expect("move_list should never be empty")
+        self.cursor.root_move()
     }
 
     #[must_use]
     pub fn prev_move(&self) -> Option<MoveIndex> {
-        self.move_list.get(self.index.checked_sub(1)?).copied()
+        self.cursor.prev()
     }
 
     #[must_use]
     pub fn next_move(&self) -> Option<MoveIndex> {
-        self.move_list.get(self.index + 1).copied()
+        self.cursor.next()
     }
 
     pub fn move_to_root(&mut self) {
-        self.set_index(0).unwrap();
+        self.cursor.to_root();
     }
 
     #[must_use]
     pub fn index(&self) -> usize {
-        self.index
+        self.cursor.index()
     }
 
+    /// Go to the move at `index` of the current line, see [`Cursor::set_index`].
     pub fn set_index(&mut self, index: usize) -> Result<(), IndexOutOfBoundsError> {
-        if index <= self.move_list.len() {
-            self.index = index;
-            self.move_list.truncate(index + 1);
-            Ok(())
-        } else {
-            Err(IndexOutOfBoundsError)
-        }
+        self.cursor.set_index(index)
     }
 
+    /// The current line, with the moves after the current move, see [`Cursor::moves`].
     pub fn move_list(&self) -> &[MoveIndex] {
-        self.move_list.as_ref()
+        self.cursor.moves()
+    }
+
+    /// Encoding of the texts in the file this was read from, `None` if it's not known.
+    #[must_use]
+    pub fn text_encoding(&self) -> Option<TextEncoding> {
+        self.text_encoding
+    }
+
+    pub fn set_text_encoding(&mut self, encoding: Option<TextEncoding>) {
+        self.text_encoding = encoding;
+    }
+
+    /// Width and height of the board.
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
     }
 }
 
@@ -598,18 +1363,20 @@ impl Transformation {
         self
     }
 
-    pub fn apply(&self, point: Point) -> Point {
-        self.mirror.apply(self.rotation.apply(point))
+    /// Transform `point` on a board of `size`x`size`.
+    pub fn apply(&self, point: Point, size: u32) -> Point {
+        self.mirror.apply(self.rotation.apply(point, size), size)
     }
 
-    pub fn inverse_apply(mut self, point: Point) -> Point {
+    /// Undo [`Transformation::apply`] of `point` on a board of `size`x`size`.
+    pub fn inverse_apply(mut self, point: Point, size: u32) -> Point {
         self.rotation = match self.rotation {
             Rotation::None => Rotation::None,
             Rotation::Deg90 => Rotation::Deg270,
             Rotation::Deg180 => Rotation::Deg180,
             Rotation::Deg270 => Rotation::Deg90,
         };
-        self.rotation.apply(self.mirror.apply(point))
+        self.rotation.apply(self.mirror.apply(point, size), size)
     }
     pub fn apply_f32(&self, point: (f32, f32)) -> (f32, f32) {
         self.mirror.apply_f32(self.rotation.apply_f32(point))
@@ -688,10 +1455,12 @@ pub enum Rotation {
 }
 
 impl Rotation {
-    pub fn apply(&self, p: Point) -> Point {
-        let (x, y) = (p.x as f32 - 7.0, p.y as f32 - 7.0);
+    /// Rotate `p` around the center of a board of `size`x`size`.
+    pub fn apply(&self, p: Point, size: u32) -> Point {
+        let center = (size as f32 - 1.0) / 2.0;
+        let (x, y) = (p.x as f32 - center, p.y as f32 - center);
         let (x, y) = self.apply_f32((x, y));
-        Point::new((x + 7.0).round() as u32, (y + 7.0).round() as u32)
+        Point::new((x + center).round() as u32, (y + center).round() as u32)
         // // Assumes grid of 15x15
         // match self {
         //     Rotation::None => p,
@@ -769,10 +1538,12 @@ impl Mirror {
         &[Mirror::None, Mirror::Horizontal, Mirror::Vertical]
     }
 
-    pub fn apply(&self, p: Point) -> Point {
-        let (x, y) = (p.x as f32 - 7.0, p.y as f32 - 7.0);
+    /// Mirror `p` through the center of a board of `size`x`size`.
+    pub fn apply(&self, p: Point, size: u32) -> Point {
+        let center = (size as f32 - 1.0) / 2.0;
+        let (x, y) = (p.x as f32 - center, p.y as f32 - center);
         let (x, y) = self.apply_f32((x, y));
-        Point::new((x + 7.0).round() as u32, (y + 7.0).round() as u32)
+        Point::new((x + center).round() as u32, (y + center).round() as u32)
         // // Assumes grid of 15x15
         // match self {
         //     Mirror::None => p,
@@ -823,7 +1594,7 @@ mod tests {
         }
 
         fn apply(t: Transformation) -> Vec<Point> {
-            points().iter().map(|p| t.apply(*p)).collect()
+            points().iter().map(|p| t.apply(*p, DEFAULT_SIZE)).collect()
         }
 
         assert_eq!(apply(Transformation::identity()), points());
@@ -858,24 +1629,534 @@ mod tests {
                     moves
                         .clone()
                         .into_iter()
-                        .map(|p| variant.apply(p))
+                        .map(|p| variant.apply(p, DEFAULT_SIZE))
                         .collect::<Vec<_>>(),
                     moves
                         .clone()
                         .into_iter()
-                        .map(|p| other.apply(p))
+                        .map(|p| other.apply(p, DEFAULT_SIZE))
                         .collect::<Vec<_>>(),
                     "{i} = {e}"
                 );
             }
         }
     }
+
+    #[test]
+    fn transforms_on_other_sizes() {
+        for size in [19, 20] {
+            let max = size - 1;
+            let corner = Point::new(0, 0);
+            let mut corners = Transformation::types()
+                .map(|t| t.apply(corner, size))
+                .to_vec();
+            corners.sort();
+            corners.dedup();
+            assert_eq!(
+                corners,
+                [
+                    Point::new(0, 0),
+                    Point::new(0, max),
+                    Point::new(max, 0),
+                    Point::new(max, max)
+                ]
+            );
+            for t in Transformation::types() {
+                for idx in 0..size * size {
+                    let point = Point::from_1d(idx, size);
+                    let transformed = t.apply(point, size);
+                    assert!(transformed.is_valid(size), "{t:?} of {point:?}");
+                    assert_eq!(t.inverse_apply(transformed, size), point);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn side_to_move_after_a_pass() {
+        use crate::file_reader::renlib::{Command, CommandVariant};
+        let mut board = Board::new();
+        let root = board.get_root();
+        assert_eq!(board.side_to_move(&root), Stone::Black);
+        let pass = board.get_or_insert_points(root, [p![H, 8], Point::null()]);
+        assert_eq!(board.get_move(pass).unwrap().color, Stone::White);
+        // the position has one black stone, but white passed
+        assert_eq!(board.as_board(&pass).unwrap().0.to_move(), Stone::White);
+        assert_eq!(board.side_to_move(&pass), Stone::Black);
+        // markers that aren't moves don't change it
+        let mut text = BoardMarker::new(p![A, 1], Stone::Empty);
+        text.command = Command::new(CommandVariant::NOMOVE.bits()).unwrap();
+        let text = board.insert_move(pass, text);
+        assert_eq!(board.side_to_move(&text), Stone::Black);
+    }
+
+    #[test]
+    fn board_with_size() {
+        for size in [0, MAX_SIZE + 1] {
+            assert!(matches!(
+                Board::with_size(size),
+                Err(ParseError::InvalidBoardSize(s)) if s == size
+            ));
+        }
+        let mut board = Board::with_size(19).unwrap();
+        assert_eq!(board.size(), 19);
+        let root = board.get_root();
+        let last =
+            board.get_or_insert_points(root, Point::parse_line_with_size("j10 s19", 19).unwrap());
+        let (arr, moves) = board.as_board(&last).unwrap();
+        assert_eq!(arr.size(), 19);
+        assert_eq!(moves, vec![Point::new(9, 9), Point::new(18, 0)]);
+        assert_eq!(
+            arr.get_point(Point::new(18, 0)).unwrap().color,
+            Stone::White
+        );
+    }
+
+    #[test]
+    fn lines_in_notation() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let last = board.get_or_insert_points(root, Point::parse_line("h8 i9 g7").unwrap());
+        board.get_or_insert_points(root, Point::parse_line("h8 h9").unwrap());
+        let branch = board.get_or_insert_points(last, Point::parse_line("h7").unwrap());
+        assert_eq!(board.get_move_children(&root).len(), 1);
+
+        let path = board
+            .find_line(&root, Point::parse_line("h8i9g7h7").unwrap())
+            .unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(path[2], last);
+        assert_eq!(path[3], branch);
+        let colors = path
+            .iter()
+            .map(|m| board.get_move(*m).unwrap().color)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            colors,
+            [Stone::Black, Stone::White, Stone::Black, Stone::White]
+        );
+        assert!(matches!(
+            board.find_line(&root, Point::parse_line("h8 i9 j10").unwrap()),
+            Err(ParseError::MoveNotFound(p)) if p == p![J, 10]
+        ));
+        assert_eq!(board.find_line(&last, []).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn openings() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let kagetsu = board.get_or_insert_points(root, Point::parse_line("h8 h9 i9 j10").unwrap());
+        let mirrored = board.get_or_insert_points(root, Point::parse_line("h8 h9 g9").unwrap());
+        let chosei = board.get_or_insert_points(root, Point::parse_line("h8 g7 f6").unwrap());
+        board.get_or_insert_points(root, Point::parse_line("a1 b2 c3").unwrap());
+
+        let (opening, transform) = board.canonical_opening(&kagetsu).unwrap();
+        assert_eq!(opening, CanonicalOpening::Kagetsu);
+        assert_eq!(transform, Transformation::identity());
+        assert_eq!(
+            board.canonical_opening(&mirrored).unwrap().0,
+            CanonicalOpening::Kagetsu
+        );
+        assert_eq!(board.canonical_opening(&root), None);
+
+        let openings = board.openings();
+        assert_eq!(openings.len(), 2);
+        let third = board
+            .find_line(&root, Point::parse_line("h8 h9 i9").unwrap())
+            .unwrap()[2];
+        assert_eq!(openings[&CanonicalOpening::Kagetsu], vec![third, mirrored]);
+        assert_eq!(openings[&CanonicalOpening::Chosei], vec![chosei]);
+    }
+
+    #[test]
+    fn transpositions() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let first = board.get_or_insert_points(root, line("h8 i9 g7 h7"));
+        let transposed = board.get_or_insert_points(root, line("g7 h7 h8 i9"));
+        let mirrored = board.get_or_insert_points(root, line("h8 g9 i7 h7"));
+        let other = board.get_or_insert_points(root, line("h8 i9 g7 h6"));
+
+        let index = board.position_index();
+        // the lines share h8 and h8 i9 g7
+        assert_eq!(index.len(), 12);
+        let (position, _) = board.as_board(&first).unwrap();
+        let zobrist = position.zobrist();
+        assert_eq!(board.zobrist(&first), Some(zobrist));
+        assert_eq!(
+            index.transpositions(zobrist).collect::<Vec<_>>(),
+            vec![first.node_index, transposed.node_index]
+        );
+        let symmetric = index.symmetric(zobrist).collect::<Vec<_>>();
+        assert_eq!(symmetric.len(), 3);
+        let (_, transform) = symmetric
+            .iter()
+            .find(|(node, _)| *node == mirrored.node_index)
+            .unwrap();
+        assert_eq!(transform.apply(p![G, 9], 15), p![I, 9]);
+        assert!(!symmetric.iter().any(|(node, _)| *node == other.node_index));
+
+        assert_eq!(board.link_transpositions(), 1);
+        assert_eq!(board.link_transpositions(), 0);
+        let linked = board.next_moves(&transposed).collect::<Vec<_>>();
+        assert_eq!(linked.len(), 1);
+        assert_eq!(linked[0].0.node_index, first.node_index);
+        assert!(board.get_move_children(&transposed).is_empty());
+        // linking doesn't change how a position is reached
+        let (_, moves) = board.as_board(&first).unwrap();
+        assert_eq!(moves, line("h8 i9 g7 h7"));
+
+        // the same stones, but black is to move after the second line
+        let mut board = Board::new();
+        let root = board.get_root();
+        let [h8, i9, j10, k11] = p![[H, 8], [I, 9], [J, 10], [K, 11]];
+        let pass = Point::null();
+        let first = board.get_or_insert_points(root, [h8, i9, j10, pass, k11]);
+        let second = board.get_or_insert_points(root, [h8, pass, j10, pass, k11, i9]);
+        let (position, _) = board.as_board(&first).unwrap();
+        assert_eq!(
+            board
+                .position_index()
+                .transpositions(position.zobrist())
+                .collect::<Vec<_>>(),
+            vec![first.node_index, second.node_index]
+        );
+        assert_eq!(board.link_transpositions(), 0);
+    }
+
+    /// Check every move's position in the index against the stones played up to it.
+    #[track_caller]
+    fn assert_index_is_current(board: &Board) {
+        let mut moves = 0;
+        for (node, marker, _) in board.depth_first(&board.get_root()) {
+            let zobrist = Zobrist::from_stones(board.size(), board.stone_line(&node));
+            assert_eq!(board.zobrist(&node), Some(&zobrist), "{node:?}");
+            if marker.command.is_move() && !marker.point.is_null {
+                moves += 1;
+                assert!(board
+                    .position_index()
+                    .transpositions(&zobrist)
+                    .any(|other| other == node.node_index));
+            }
+        }
+        assert_eq!(board.position_index().len(), moves);
+    }
+
+    #[test]
+    fn position_index_follows_edits() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let find = |board: &Board, s| *board.find_line(&root, line(s)).unwrap().last().unwrap();
+        board.get_or_insert_points(root, line("h8 i9 g7 h7"));
+        board.get_or_insert_points(root, line("h8 h9 j10"));
+        board.get_or_insert_points(root, line("g7 i9 h8 k11"));
+        board.get_or_insert_points(root, line("j10 j11"));
+        assert_index_is_current(&board);
+
+        // the last moves take the places of the removed ones
+        let renumbered = board.remove_subtree(find(&board, "h8 h9")).unwrap();
+        assert!(renumbered.moved().next().is_some());
+        assert_index_is_current(&board);
+
+        board
+            .move_subtree(find(&board, "g7 i9 h8"), find(&board, "j10"))
+            .unwrap();
+        assert_index_is_current(&board);
+        let h7 = find(&board, "h8 i9 g7 h7");
+        let mut marker = board.get_move(h7).unwrap().clone();
+        marker.point = p![K, 7];
+        board.set_move(h7, marker).unwrap();
+        board.set_pos(find(&board, "j10"), p![A, 1]).unwrap();
+        assert_index_is_current(&board);
+
+        let keep = board.get_or_insert_points(root, line("h8"));
+        let other = board.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        board.get_or_insert_points(other, line("h9 g9"));
+        board.merge_siblings(keep, other).unwrap();
+        assert_index_is_current(&board);
+        board.rm_move(find(&board, "h8 h9 g9"));
+        assert_index_is_current(&board);
+    }
+
+    #[test]
+    fn variants() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let first = board.get_or_insert_points(root, line("h8 i9 g6 h7"));
+        let mirrored = board.get_or_insert_points(root, line("h8 g9 i6 h7"));
+        let transposed = board.get_or_insert_points(root, line("g6 i9 h8"));
+        let g6 = board.get_parent_strong(&first).unwrap();
+        let variants = |node| {
+            board
+                .get_variants_and_transformations(node)
+                .unwrap()
+                .into_iter()
+                .map(|(marker, node, _, variant_type)| {
+                    (marker.point.notation(15), node, variant_type)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            variants(transposed),
+            [
+                ("h7".to_owned(), first, VariantType::Variant),
+                ("h7".to_owned(), mirrored, VariantType::Variant)
+            ]
+        );
+        // h7 is played from g6 already
+        assert_eq!(
+            variants(g6),
+            [("h7".to_owned(), mirrored, VariantType::Transformation)]
+        );
+        let (_, _, transform, _) = board.get_variants_and_transformations(g6).unwrap()[0];
+        assert_eq!(transform.apply(p![G, 9], 15), p![I, 9]);
+        assert!(variants(root).is_empty());
+        assert!(board
+            .get_variants_and_transformations(MoveIndex::new_node(NodeIndex::new(100)))
+            .is_err());
+    }
+
+    /// Every line from the root to a last move, in the order of the tree.
+    fn lines(board: &Board) -> Vec<String> {
+        fn walk(board: &Board, node: MoveIndex, line: &mut Vec<String>, lines: &mut Vec<String>) {
+            let children = board.get_move_children(&node);
+            if children.is_empty() {
+                lines.push(line.join(" "));
+            }
+            for child in children {
+                line.push(board.get_move(child).unwrap().point.notation(board.size()));
+                walk(board, child, line, lines);
+                line.pop();
+            }
+        }
+        let mut lines = vec![];
+        walk(board, board.get_root(), &mut vec![], &mut lines);
+        lines
+    }
+
+    fn current_line(board: &Board) -> String {
+        let (_, moves) = board.as_board(&board.current_move()).unwrap();
+        moves
+            .iter()
+            .map(|point| point.notation(board.size()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn edit_tree() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        for line in ["h8 i9 g7", "h8 i9 j7", "h8 h9 g7", "h8 h9 j10"] {
+            board.get_or_insert_points(root, Point::parse_line(line).unwrap());
+        }
+        let find = |board: &Board, line: &str| {
+            *board
+                .find_line(&board.get_root(), Point::parse_line(line).unwrap())
+                .unwrap()
+                .last()
+                .unwrap()
+        };
+        board.set_current_move(find(&board, "h8 h9 j10")).unwrap();
+        // the last move added, moving it around keeps its node
+        let last = find(&board, "h8 h9 j10");
+
+        let j10 = board.make_main_line(find(&board, "h8 h9 j10")).unwrap();
+        assert_eq!(j10.node_index, last.node_index);
+        assert_eq!(
+            lines(&board),
+            ["h8 h9 j10", "h8 h9 g7", "h8 i9 g7", "h8 i9 j7"]
+        );
+        assert_eq!(board.current_move(), j10);
+        assert_eq!(current_line(&board), "h8 h9 j10");
+        assert_eq!(board.main_line(&board.get_root()).last(), Some(&j10));
+
+        let h9 = find(&board, "h8 h9");
+        let order = [find(&board, "h8 h9 g7"), j10];
+        let reordered = board.reorder_children(h9, &order).unwrap();
+        assert_eq!(
+            lines(&board),
+            ["h8 h9 g7", "h8 h9 j10", "h8 i9 g7", "h8 i9 j7"]
+        );
+        assert_eq!(board.current_move(), reordered[1]);
+        let h9 = find(&board, "h8 h9");
+        assert_eq!(
+            board.reorder_children(h9, &[find(&board, "h8 h9 g7")]),
+            Err(EditError::NotAPermutation(h9))
+        );
+
+        board.promote_variation(find(&board, "h8 i9")).unwrap();
+        board.promote_variation(find(&board, "h8 i9 j7")).unwrap();
+        assert_eq!(
+            lines(&board),
+            ["h8 i9 j7", "h8 i9 g7", "h8 h9 g7", "h8 h9 j10"]
+        );
+
+        let moved = board
+            .move_subtree(find(&board, "h8 h9 j10"), find(&board, "h8 i9"))
+            .unwrap();
+        assert_eq!(
+            lines(&board),
+            ["h8 i9 j7", "h8 i9 g7", "h8 i9 j10", "h8 h9 g7"]
+        );
+        assert_eq!(board.current_move(), moved);
+        assert_eq!(moved.node_index, last.node_index);
+        assert_eq!(current_line(&board), "h8 i9 j10");
+        let (h8, i9) = (find(&board, "h8"), find(&board, "h8 i9"));
+        assert_eq!(
+            board.move_subtree(h8, i9),
+            Err(EditError::IntoItself {
+                node: h8,
+                new_parent: i9
+            })
+        );
+
+        // removing moves puts the last ones in their places
+        let g7 = find(&board, "h8 h9 g7");
+        let renumbered = board.remove_subtree(find(&board, "h8 i9")).unwrap();
+        assert_eq!(renumbered.removed(), 4);
+        assert_eq!(lines(&board), ["h8 h9 g7"]);
+        let g7 = renumbered.node(g7.node_index);
+        assert_eq!(find(&board, "h8 h9 g7").node_index, g7);
+        assert!(renumbered.moved().all(
+            |(old, new)| old.index() >= board.graph.node_count() && new == renumbered.node(old)
+        ));
+        assert_eq!(current_line(&board), "h8");
+        assert_eq!(board.index(), 1);
+        let root = board.get_root();
+        assert_eq!(board.remove_subtree(root), Err(EditError::Root(root)));
+        assert_eq!(
+            board.remove_subtree(find(&board, "h8")).unwrap().removed(),
+            3
+        );
+        assert_eq!(lines(&board), [""]);
+        assert_eq!(board.move_list(), [board.get_root()]);
+    }
+
+    #[test]
+    fn merge_siblings() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let g7 = board.get_or_insert_points(root, line("h8 i9 g7"));
+        let first = board.find_line(&root, line("h8")).unwrap()[0];
+        // a second h8, like a library made from two files
+        let second = board.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        board
+            .get_move_mut(second)
+            .unwrap()
+            .set_oneline_comment("second".to_owned());
+        let j10 = board.get_or_insert_points(second, line("i9 j10"));
+        let h9 = board.get_or_insert_points(second, line("h9"));
+        let other = board.get_or_insert_points(root, line("j10"));
+        board.add_edge(&other, &h9).unwrap();
+        board.set_current_move(j10).unwrap();
+
+        assert_eq!(
+            board.merge_siblings(first, other),
+            Err(EditError::DifferentMoves(first, other))
+        );
+        assert_eq!(
+            board.merge_siblings(first, g7),
+            Err(EditError::NotSiblings(first, g7))
+        );
+        let (merged, renumbered) = board.merge_siblings(first, second).unwrap();
+        assert_eq!(renumbered.removed(), 2);
+        assert_eq!(lines(&board), ["h8 i9 g7", "h8 i9 j10", "h8 h9", "j10"]);
+        assert_eq!(
+            board.get_move(merged).unwrap().oneline_comment.as_deref(),
+            Some("second")
+        );
+        assert_eq!(current_line(&board), "h8 i9 j10");
+        // the link to the h9 of the second h8 goes to the one that is left
+        let other = board.find_line(&board.get_root(), line("j10")).unwrap()[0];
+        let linked = board.next_moves(&other).collect::<Vec<_>>();
+        assert_eq!(linked.len(), 1);
+        assert_eq!(board.as_board(&linked[0].0).unwrap().1, line("h8 h9"));
+    }
 }
 
-#[derive(thiserror::Error, Clone, Debug)]
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
 #[error("index is out of bounds")]
 pub struct IndexOutOfBoundsError;
 
+/// The moves an edit of the tree of a [`Board`] moved to another node, see
+/// [`Board::remove_subtree`].
+///
+/// Edits keep the nodes of the moves that are left, except where a move is removed: the last node
+/// of the tree takes its place. A [`MoveIndex`] of a moved move finds another move, or nothing,
+/// after the edit.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Renumbered {
+    /// The new node of every moved move, by its old one.
+    moved: HashMap<NodeIndex, NodeIndex>,
+    removed: usize,
+}
+
+impl Renumbered {
+    /// The node of the move that was at `node` before the edit, if it is still in the tree.
+    #[must_use]
+    pub fn node(&self, node: NodeIndex) -> NodeIndex {
+        self.moved.get(&node).copied().unwrap_or(node)
+    }
+
+    /// The number of moves that were removed.
+    #[must_use]
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// The moves that were moved to another node, as `(old, new)`.
+    pub fn moved(&self) -> impl Iterator<Item = (NodeIndex, NodeIndex)> + '_ {
+        self.moved.iter().map(|(old, new)| (*old, *new))
+    }
+}
+
+/// Why an edit of the tree of a [`Board`] can't be made.
+#[derive(thiserror::Error, Clone, Debug, PartialEq)]
+pub enum EditError {
+    #[error("{0:?} is not in the tree")]
+    NotFound(MoveIndex),
+    /// The root can't be moved or removed.
+    #[error("{0:?} is the root")]
+    Root(MoveIndex),
+    #[error("{0:?} is not played from {1:?}")]
+    NotChild(MoveIndex, MoveIndex),
+    /// The new order of the moves from a node leaves some out or has some twice.
+    #[error("the new order doesn't have every move from {0:?} once")]
+    NotAPermutation(MoveIndex),
+    #[error("{node:?} can't be moved to {new_parent:?}, which is played after it")]
+    IntoItself {
+        node: MoveIndex,
+        new_parent: MoveIndex,
+    },
+    #[error("{0:?} and {1:?} are not played from the same move")]
+    NotSiblings(MoveIndex, MoveIndex),
+    #[error("{0:?} and {1:?} are not moves on the same point")]
+    DifferentMoves(MoveIndex, MoveIndex),
+    #[error("{0:?} is not linked to {1:?}")]
+    NotLinked(MoveIndex, MoveIndex),
+    #[error("{0:?} is already linked to {1:?}")]
+    AlreadyLinked(MoveIndex, MoveIndex),
+    /// A link would go from a move to one played before it.
+    #[error("linking {0:?} to {1:?} would make a cycle")]
+    WouldCycle(MoveIndex, MoveIndex),
+    /// Only boards of the same size can be merged, see [`Board::merge`].
+    #[error("can't merge a {1}x{1} board into a {0}x{0} board")]
+    DifferentSizes(u32, u32),
+    /// No move is at this path from the root, see [`Board::path_to`].
+    #[error("there is no move at {0:?}")]
+    NoPath(Vec<usize>),
+    /// A [`Cursor`] was made before moves were removed, see [`Board::generation`].
+    #[error("the cursor was made before moves were removed from the tree")]
+    Stale,
+}
+
 impl Default for Board {
     fn default() -> Self {
         Self::new()
@@ -920,8 +2201,15 @@ fn does_it_work() {
     }
     // for i in
     tracing::info!("{:?}", graph);
-    tracing::info!("Children of {:?} {:?}", b_1, graph.get_children(&a_1));
-    let branched_down = graph.down_to_branch(&a_1_2);
+    tracing::info!(
+        "Children of {:?} {:?}",
+        b_1,
+        graph.next_moves(&a_1).collect::<Vec<_>>()
+    );
+    let branched_down = graph
+        .ancestors(&a_1_2)
+        .skip(1)
+        .find(|(node, ..)| graph.next_moves(node).len() > 1);
     tracing::info!(
         "Moving down on {:?} gives: end = {:?}",
         a_1_2,
@@ -0,0 +1,548 @@
+//! A compact position, with the stones of each color in a bitset.
+//!
+//! Point (`x`, `y`) is bit `x + y * (size + 1)`. The extra column is never part of the board, so
+//! shifting a bitset by the [`Bitboard::step`] of a [`Direction`] moves every point one step along
+//! that direction without wrapping around to the next row. A row of five points that runs off the
+//! board always goes through that column, which makes it easy to find rows with a few shifts and
+//! ands instead of walking the board.
+//!
+//! A [`BoardArr`] carries comments and commands for every point, a [`Bitboard`] only the stones,
+//! which is what evaluating and searching positions needs. Every [`BoardArr`] keeps one, see
+//! [`BoardArr::bitboard`].
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not};
+
+use super::evaluator::Direction;
+use super::zobrist::Zobrist;
+use super::{BoardArr, Point, Stone, MAX_SIZE};
+
+const WORDS: usize = ((MAX_SIZE * (MAX_SIZE + 1)) as usize).div_ceil(64);
+
+/// A set of points of a [`Bitboard`], by their index.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bits([u64; WORDS]);
+
+impl Bits {
+    #[must_use]
+    pub fn contains(&self, index: usize) -> bool {
+        self.0
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.0[index / 64] &= !(1 << (index % 64));
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// The indices in the set, in order.
+    pub fn indices(self) -> impl Iterator<Item = usize> {
+        self.0.into_iter().enumerate().flat_map(|(i, mut word)| {
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    i * 64 + bit
+                })
+            })
+        })
+    }
+
+    /// Move every index up by `n`, or down if `n` is negative. Indices that end up outside the set
+    /// are dropped.
+    #[must_use]
+    pub fn shift(self, n: isize) -> Self {
+        let (words, bits) = (n.unsigned_abs() / 64, (n.unsigned_abs() % 64) as u32);
+        let mut shifted = [0; WORDS];
+        for (i, word) in shifted.iter_mut().enumerate() {
+            let (from, carry) = if n >= 0 {
+                (i.checked_sub(words), i.checked_sub(words + 1))
+            } else {
+                (
+                    Some(i + words).filter(|i| *i < WORDS),
+                    Some(i + words + 1).filter(|i| *i < WORDS),
+                )
+            };
+            let from = from.map_or(0, |from| self.0[from]);
+            let carry = carry.map_or(0, |carry| self.0[carry]);
+            *word = match (n >= 0, bits) {
+                (_, 0) => from,
+                (true, bits) => from << bits | carry >> (64 - bits),
+                (false, bits) => from >> bits | carry << (64 - bits),
+            };
+        }
+        Self(shifted)
+    }
+}
+
+impl fmt::Debug for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.indices()).finish()
+    }
+}
+
+impl BitAnd for Bits {
+    type Output = Self;
+
+    fn bitand(mut self, rhs: Self) -> Self {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitAndAssign for Bits {
+    fn bitand_assign(&mut self, rhs: Self) {
+        for (word, other) in self.0.iter_mut().zip(rhs.0) {
+            *word &= other;
+        }
+    }
+}
+
+impl BitOr for Bits {
+    type Output = Self;
+
+    fn bitor(mut self, rhs: Self) -> Self {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for Bits {
+    fn bitor_assign(&mut self, rhs: Self) {
+        for (word, other) in self.0.iter_mut().zip(rhs.0) {
+            *word |= other;
+        }
+    }
+}
+
+impl BitXor for Bits {
+    type Output = Self;
+
+    fn bitxor(mut self, rhs: Self) -> Self {
+        for (word, other) in self.0.iter_mut().zip(rhs.0) {
+            *word ^= other;
+        }
+        self
+    }
+}
+
+/// Every index that isn't in the set, including the ones outside the board.
+impl Not for Bits {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0.map(|word| !word))
+    }
+}
+
+/// The stones of a position, see the [module docs](self).
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bitboard {
+    size: u32,
+    /// Black and white stones.
+    stones: [Bits; 2],
+    /// Every point of the board.
+    board: Bits,
+    zobrist: Zobrist,
+}
+
+impl Bitboard {
+    #[must_use]
+    pub fn new(size: u32) -> Self {
+        assert!(size <= MAX_SIZE, "{size} is larger than {MAX_SIZE}");
+        let mut board = Bits::default();
+        for y in 0..size {
+            for x in 0..size {
+                board.insert((x + y * (size + 1)) as usize);
+            }
+        }
+        Self {
+            size,
+            stones: [Bits::default(); 2],
+            board,
+            zobrist: Zobrist::new(size),
+        }
+    }
+
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The hash of the stones on the board.
+    #[must_use]
+    pub fn zobrist(&self) -> &Zobrist {
+        &self.zobrist
+    }
+
+    /// The bit of `point`.
+    #[must_use]
+    pub fn index(&self, point: Point) -> usize {
+        (point.x + point.y * (self.size + 1)) as usize
+    }
+
+    /// The point of bit `index`.
+    #[must_use]
+    pub fn point(&self, index: usize) -> Point {
+        let stride = self.size as usize + 1;
+        Point::new((index % stride) as u32, (index / stride) as u32)
+    }
+
+    /// How much the index changes with one step in `direction`.
+    #[must_use]
+    pub fn step(&self, direction: Direction) -> isize {
+        let stride = self.size as isize + 1;
+        match direction {
+            Direction::Horizontal => 1,
+            Direction::Vertical => stride,
+            // x + 1, y + 1
+            Direction::Diagonal { bottom: false } => stride + 1,
+            // x - 1, y + 1
+            Direction::Diagonal { bottom: true } => stride - 1,
+        }
+    }
+
+    /// The color on `point`, `None` if it's outside the board.
+    #[must_use]
+    pub fn get(&self, point: Point) -> Option<Stone> {
+        if !point.is_valid(self.size) {
+            return None;
+        }
+        let index = self.index(point);
+        Some(if self.stones[0].contains(index) {
+            Stone::Black
+        } else if self.stones[1].contains(index) {
+            Stone::White
+        } else {
+            Stone::Empty
+        })
+    }
+
+    /// Sets `point` to `stone`, which may be [`Stone::Empty`] to remove a stone.
+    pub fn set_point(&mut self, point: Point, stone: Stone) {
+        assert!(point.is_valid(self.size), "{point} is outside the board");
+        let index = self.index(point);
+        let old = self.get(point).unwrap_or(Stone::Empty);
+        self.zobrist.toggle(point, old);
+        self.zobrist.toggle(point, stone);
+        self.stones[0].remove(index);
+        self.stones[1].remove(index);
+        match stone {
+            Stone::Black => self.stones[0].insert(index),
+            Stone::White => self.stones[1].insert(index),
+            Stone::Empty => {}
+        }
+    }
+
+    /// The points with `stone` on them, the empty points for [`Stone::Empty`].
+    #[must_use]
+    pub fn stones(&self, stone: Stone) -> Bits {
+        match stone {
+            Stone::Black => self.stones[0],
+            Stone::White => self.stones[1],
+            Stone::Empty => self.board & !(self.stones[0] | self.stones[1]),
+        }
+    }
+
+    /// Every point of the board.
+    #[must_use]
+    pub fn board(&self) -> Bits {
+        self.board
+    }
+
+    /// The points in `bits`.
+    pub fn points(&self, bits: Bits) -> impl Iterator<Item = Point> + '_ {
+        bits.indices().map(|index| self.point(index))
+    }
+
+    /// `bits` seen from `steps` points along `direction`: an index is in the view if the index
+    /// `steps` steps away is in `bits`.
+    #[must_use]
+    pub fn view(&self, bits: Bits, direction: Direction, steps: isize) -> Bits {
+        bits.shift(-steps * self.step(direction))
+    }
+
+    /// [`Bitboard::view`] of the points with `stone` on them.
+    #[must_use]
+    pub fn shifted(&self, stone: Stone, direction: Direction, steps: isize) -> Bits {
+        self.view(self.stones(stone), direction, steps)
+    }
+
+    /// The points that start a row of at least `length` `stone`s in `direction`.
+    #[must_use]
+    pub fn rows(&self, stone: Stone, direction: Direction, length: usize) -> Bits {
+        (0..length as isize).fold(self.board, |rows, step| {
+            rows & self.shifted(stone, direction, step)
+        })
+    }
+
+    /// The empty points where `stone` makes a row of five, without looking at what is next to
+    /// the row. A winning move is always one of them, see [`Bitboard::wins_with`] for which are.
+    #[must_use]
+    pub fn fives(&self, stone: Stone) -> Bits {
+        let mut fives = Bits::default();
+        for direction in Direction::directions() {
+            let stones: [Bits; 5] =
+                std::array::from_fn(|step| self.shifted(stone, direction, step as isize));
+            for gap in 0..5 {
+                let starts = (0..5).filter(|step| *step != gap).fold(
+                    self.shifted(Stone::Empty, direction, gap as isize),
+                    |starts, step| starts & stones[step],
+                );
+                fives |= self.view(starts, direction, -(gap as isize));
+            }
+        }
+        fives
+    }
+
+    /// The points one step from a point of `bits` in any direction, and the points of `bits`.
+    #[must_use]
+    pub fn neighbours(&self, bits: Bits) -> Bits {
+        let mut near = bits;
+        for direction in Direction::directions() {
+            near |= self.view(bits, direction, 1) | self.view(bits, direction, -1);
+        }
+        near & self.board
+    }
+}
+
+/// The stones by their points in standard notation, like [`BoardArr`].
+impl fmt::Debug for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stones = |stone| {
+            self.points(self.stones(stone))
+                .map(|point| point.notation(self.size))
+                .collect::<Vec<_>>()
+        };
+        f.debug_struct("Bitboard")
+            .field("size", &self.size)
+            .field("black", &stones(Stone::Black))
+            .field("white", &stones(Stone::White))
+            .finish()
+    }
+}
+
+/// Same as [`BoardArr::bitboard`], cloned.
+impl From<&BoardArr> for Bitboard {
+    fn from(board: &BoardArr) -> Self {
+        board.bitboard().clone()
+    }
+}
+
+impl From<&Bitboard> for BoardArr {
+    fn from(bitboard: &Bitboard) -> Self {
+        let mut board = Self::new(bitboard.size);
+        for stone in [Stone::Black, Stone::White] {
+            for point in bitboard.points(bitboard.stones(stone)) {
+                board
+                    .set_point(point, stone)
+                    .expect("the points are on the board");
+            }
+        }
+        board
+    }
+}
+
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        BoardArr::from(self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use test_log::test;
+
+    #[test]
+    fn shift() {
+        let mut bits = Bits::default();
+        for index in [0, 63, 64, 200, 700] {
+            bits.insert(index);
+        }
+        assert_eq!(
+            bits.shift(1).indices().collect::<Vec<_>>(),
+            [1, 64, 65, 201, 701]
+        );
+        assert_eq!(bits.shift(-64).indices().collect::<Vec<_>>(), [0, 136, 636]);
+        assert_eq!(bits.shift(130).shift(-130), {
+            let mut kept = bits;
+            kept.remove(700);
+            kept
+        });
+        assert_eq!(bits.shift(0), bits);
+        assert_eq!(bits.len(), 5);
+        assert!(bits.shift(-701).is_empty());
+    }
+
+    #[test]
+    fn conversions() {
+        let mut board = BoardArr::new(15);
+        for (point, stone) in [
+            (p![H, 8], Stone::Black),
+            (p![I, 9], Stone::White),
+            (p![A, 1], Stone::Black),
+            (p![O, 15], Stone::White),
+        ] {
+            board.set_point(point, stone).unwrap();
+        }
+        let bitboard = Bitboard::from(&board);
+        assert_eq!(bitboard.zobrist(), board.zobrist());
+        for marker in board.iter() {
+            assert_eq!(bitboard.get(marker.point), Some(marker.color));
+        }
+        assert_eq!(bitboard.get(Point::new(15, 0)), None);
+        let back = BoardArr::from(&bitboard);
+        assert!(board
+            .iter()
+            .zip(back.iter())
+            .all(|(a, b)| a.point == b.point && a.color == b.color));
+        assert_eq!(bitboard.stones(Stone::Empty).len(), 15 * 15 - 4);
+        assert_eq!(
+            bitboard
+                .points(bitboard.stones(Stone::Black))
+                .collect::<Vec<_>>(),
+            [p![H, 8], p![A, 1]]
+        );
+        let mut larger = Bitboard::new(19);
+        larger.set_point(Point::new(18, 0), Stone::Black);
+        assert_eq!(
+            format!("{larger:?}"),
+            r#"Bitboard { size: 19, black: ["s19"], white: [] }"#
+        );
+    }
+
+    #[test]
+    fn rows_stay_on_their_line() {
+        for size in [15, MAX_SIZE] {
+            let mut board = Bitboard::new(size);
+            // four on the right edge and one on the left of the next row
+            for y in 0..4 {
+                board.set_point(Point::new(size - 1, y), Stone::Black);
+            }
+            for x in size - 4..size {
+                board.set_point(Point::new(x, 5), Stone::Black);
+            }
+            board.set_point(Point::new(0, 6), Stone::Black);
+            for direction in Direction::directions() {
+                assert!(board.rows(Stone::Black, direction, 5).is_empty());
+            }
+            assert_eq!(
+                board
+                    .points(board.rows(Stone::Black, Direction::Vertical, 4))
+                    .collect::<Vec<_>>(),
+                [Point::new(size - 1, 0)]
+            );
+            // with the stone of the row below, six down the edge
+            board.set_point(Point::new(size - 1, 4), Stone::Black);
+            assert_eq!(
+                board
+                    .points(board.rows(Stone::Black, Direction::Vertical, 5))
+                    .collect::<Vec<_>>(),
+                [Point::new(size - 1, 0), Point::new(size - 1, 1)]
+            );
+            // the diagonals through the corners
+            let mut board = Bitboard::new(size);
+            for i in 0..5 {
+                board.set_point(Point::new(size - 1 - i, i), Stone::White);
+                board.set_point(Point::new(i, i), Stone::Black);
+            }
+            assert_eq!(
+                board
+                    .points(board.rows(Stone::White, Direction::Diagonal { bottom: true }, 5))
+                    .collect::<Vec<_>>(),
+                [Point::new(size - 1, 0)]
+            );
+            assert_eq!(
+                board
+                    .points(board.rows(Stone::Black, Direction::Diagonal { bottom: false }, 5))
+                    .collect::<Vec<_>>(),
+                [Point::new(0, 0)]
+            );
+        }
+    }
+
+    #[test]
+    fn neighbours() {
+        let mut bits = Bits::default();
+        let board = Bitboard::new(15);
+        bits.insert(board.index(Point::new(0, 0)));
+        let near = board.neighbours(bits);
+        assert_eq!(
+            board.points(near).collect::<Vec<_>>(),
+            [
+                Point::new(0, 0),
+                Point::new(1, 0),
+                Point::new(0, 1),
+                Point::new(1, 1)
+            ]
+        );
+        assert_eq!(board.neighbours(near).len(), 9);
+    }
+
+    mod random {
+        use super::*;
+        use crate::board::rules::RuleSet;
+        use proptest::prelude::*;
+        use test_log::test;
+
+        fn position() -> impl Strategy<Value = BoardArr> {
+            prop::sample::select(vec![9u32, 15, MAX_SIZE]).prop_flat_map(|size| {
+                let stone = (0..size, 0..size, any::<bool>());
+                prop::collection::vec(stone, 0..120).prop_map(move |stones| {
+                    let mut board = BoardArr::new(size);
+                    for (x, y, black) in stones {
+                        board
+                            .set_point(Point::new(x, y), Stone::from_bool(black))
+                            .unwrap();
+                    }
+                    board
+                })
+            })
+        }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(64))]
+
+            #[test]
+            fn round_trip(board in position()) {
+                let bitboard = Bitboard::from(&board);
+                for marker in board.iter() {
+                    prop_assert_eq!(bitboard.get(marker.point), Some(marker.color));
+                }
+                prop_assert_eq!(&Bitboard::from(&BoardArr::from(&bitboard)), &bitboard);
+            }
+
+            #[test]
+            fn fives_has_every_win(board in position()) {
+                let bitboard = Bitboard::from(&board);
+                for rules in RuleSet::ALL {
+                    for stone in [Stone::Black, Stone::White] {
+                        let fives = bitboard.fives(stone);
+                        for point in bitboard.points(bitboard.stones(Stone::Empty)) {
+                            if bitboard.wins_with(point, stone, rules) {
+                                prop_assert!(fives.contains(bitboard.index(point)), "{point}");
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use super::bitboard::Bitboard;
+use super::zobrist::Zobrist;
 use crate::errors::ParseError;
 use crate::file_reader::renlib::Command;
 use crate::file_reader::renlib::CommandVariant;
@@ -9,6 +11,11 @@ use std::fmt;
 use std::iter::FromIterator;
 use std::ops::Deref;
 
+/// Size of a standard renju board, 15x15.
+pub const DEFAULT_SIZE: u32 = 15;
+/// Largest supported board size, limited by the letters of the notation. See [`Point::notation`].
+pub const MAX_SIZE: u32 = 26;
+
 #[macro_export]
 macro_rules! p {
     [$([$($i:tt)*]),* $(,)?] => {
@@ -24,9 +31,10 @@ macro_rules! p {
 }
 
 /// Enum for `Stone`,
-#[derive(Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, PartialOrd, Ord, Hash, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stone {
+    #[default]
     Empty,
     White,
     Black,
@@ -76,12 +84,6 @@ impl Stone {
         }
     }
 }
-impl Default for Stone {
-    fn default() -> Self {
-        Self::Empty
-    }
-}
-
 impl fmt::Display for Stone {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -106,32 +108,48 @@ pub struct Point {
 }
 
 impl Point {
+    /// Returns `true` if the point is on a board of `size`x`size`.
     #[must_use]
-    pub fn is_valid(&self) -> bool {
-        let Point { x, y, .. } = *self;
-        // FIXME: Assumes grid size 15x15
-        x == 0 && y == 0 || (0..=14).contains(&x) && (0..=14).contains(&y)
+    pub fn is_valid(&self, size: u32) -> bool {
+        self.x < size && self.y < size
     }
 }
 
+/// Formats the raw coordinates, a point doesn't know the size of its board, see [`Point::notation`]
+/// for the name of the point.
 impl fmt::Debug for Point {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let (x, y) = (((self.x as u8 + 65u8) as char), 15 - self.y);
         if f.alternate() {
             return write!(
                 f,
-                "Point {{ x: {}, y: {}, is_null: {}, repr: \"[{:>1}, {:>2}]\" }}",
-                self.x, self.y, self.is_null, x, y
+                "Point {{ x: {}, y: {}, is_null: {} }}",
+                self.x, self.y, self.is_null
             );
         }
         if !self.is_null {
-            write!(f, "[{:>1}, {:>2}]", x, y)
+            write!(f, "[{:>2}, {:>2}]", self.x, self.y)
         } else {
             write!(f, "None")
         }
     }
 }
 
+/// Formats the point in standard notation on a 15x15 board, see [`Point::notation`].
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(&self.notation(DEFAULT_SIZE))
+    }
+}
+
+/// Parses a point in standard notation on a 15x15 board, see [`Point::from_notation`].
+impl std::str::FromStr for Point {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_notation(s, DEFAULT_SIZE)
+    }
+}
+
 /// Holds info about the marker at `Point` or a move.
 ///
 /// # Notes
@@ -223,9 +241,8 @@ impl fmt::Debug for BoardMarker {
             if !self.point.is_null {
                 write!(
                     f,
-                    "|[{:>1},{:>2}]{}|",
-                    ((self.point.x as u8 + 65u8) as char),
-                    15 - self.point.y,
+                    "|{:?}{}|",
+                    self.point,
                     match self.color {
                         Stone::Empty => ".",
                         Stone::White => "O",
@@ -289,6 +306,17 @@ impl Point {
             u32::from(byte >> 4),
         ))
     }
+    /// Convert back a `Point` to a byte, the inverse of [`Point::from_byte`].
+    ///
+    /// A null point becomes `0x00`, which is how RenLib stores a pass.
+    #[must_use]
+    pub fn to_byte(self) -> u8 {
+        if self.is_null {
+            0
+        } else {
+            ((self.y as u8) << 4) + self.x as u8 + 1
+        }
+    }
     /// Makes a `Point` at (`x`, `y`)
     #[must_use]
     pub const fn new(x: u32, y: u32) -> Self {
@@ -316,6 +344,81 @@ impl Point {
             y: idx / width,
         }
     }
+    /// The point in standard notation on a board of `size`x`size`, e.g `h8` for the middle of a
+    /// 15x15 board, or `pass` for a null point.
+    ///
+    /// Columns are letters from `a` on the left, rows are numbers from `1` at the bottom.
+    #[must_use]
+    pub fn notation(self, size: u32) -> String {
+        if self.is_null {
+            return "pass".to_owned();
+        }
+        format!(
+            "{}{}",
+            char::from(b'a' + self.x as u8),
+            i64::from(size) - i64::from(self.y)
+        )
+    }
+
+    /// Parse a point in standard notation on a board of `size`x`size`, see [`Point::notation`].
+    ///
+    /// The letter can be in either case, and `pass` is a null point.
+    pub fn from_notation(s: &str, size: u32) -> Result<Self, ParseError> {
+        let error = || ParseError::PointParseError(s.to_owned());
+        if s.eq_ignore_ascii_case("pass") {
+            return Ok(Self::null());
+        }
+        let mut chars = s.chars();
+        let column = chars.next().ok_or_else(error)?.to_ascii_lowercase();
+        let row = chars.as_str();
+        if !column.is_ascii_lowercase() || !row.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(error());
+        }
+        let point = match row.parse::<u32>() {
+            Ok(row @ 1..) if row <= size => Self::new(column as u32 - 'a' as u32, size - row),
+            _ => return Err(error()),
+        };
+        if point.is_valid(size) {
+            Ok(point)
+        } else {
+            Err(error())
+        }
+    }
+
+    /// Parse a sequence of points in standard notation on a 15x15 board, like `h8 i9 g7 h7`.
+    ///
+    /// See [`Point::parse_line_with_size`].
+    pub fn parse_line(s: &str) -> Result<Vec<Self>, ParseError> {
+        Self::parse_line_with_size(s, DEFAULT_SIZE)
+    }
+
+    /// Parse a sequence of points in standard notation on a board of `size`x`size`.
+    ///
+    /// The points can be separated by whitespace or commas, or not at all as in `h8i9g7h7`.
+    pub fn parse_line_with_size(s: &str, size: u32) -> Result<Vec<Self>, ParseError> {
+        let mut points = vec![];
+        for token in s
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|t| !t.is_empty())
+        {
+            if token.eq_ignore_ascii_case("pass") {
+                points.push(Self::null());
+                continue;
+            }
+            // every point starts with a letter
+            let mut rest = token;
+            while !rest.is_empty() {
+                let end = rest
+                    .char_indices()
+                    .skip(1)
+                    .find(|(_, c)| !c.is_ascii_digit())
+                    .map_or(rest.len(), |(i, _)| i);
+                points.push(Self::from_notation(&rest[..end], size)?);
+                rest = &rest[end..];
+            }
+        }
+        Ok(points)
+    }
     /// Convert back a `Point` to a 1D coord
     #[must_use]
     pub fn to_1d(self, width: u32) -> u32 {
@@ -324,13 +427,21 @@ impl Point {
 }
 
 /// Holds all `BoardMarker`'s in a `Board`.
-#[derive(Debug, Clone)]
+///
+/// The stones are kept in a [`Bitboard`] as well, with their [`Zobrist`] hash, for the rules and
+/// evaluation. The colors only change through [`BoardArr::set`], [`BoardArr::set_point`] and
+/// [`BoardArr::clear`], which keep it up to date.
+#[derive(Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct BoardArr(Vec<BoardMarker>, u32);
+pub struct BoardArr(Vec<BoardMarker>, u32, Bitboard);
 
 impl BoardArr {
     pub fn new(size: u32) -> Self {
-        let mut b = Self(vec![BoardMarker::null(); (size * size) as usize], size);
+        let mut b = Self(
+            vec![BoardMarker::null(); (size * size) as usize],
+            size,
+            Bitboard::new(size),
+        );
         for idx in 0..(size * size) {
             b.get_mut(idx as usize).unwrap().point = Point::from_1d(idx, size);
         }
@@ -341,11 +452,37 @@ impl BoardArr {
         self.1
     }
 
+    /// The hash of the stones on the board.
+    #[must_use]
+    pub fn zobrist(&self) -> &Zobrist {
+        self.2.zobrist()
+    }
+
+    /// The stones on the board.
+    #[must_use]
+    pub fn bitboard(&self) -> &Bitboard {
+        &self.2
+    }
+
     pub fn set(&mut self, marker: BoardMarker) -> Result<(), ParseError> {
+        if !marker.point.is_valid(self.1) {
+            return Err(ParseError::Other(format!(
+                "{} is outside the {}x{} board",
+                marker.point.notation(self.1),
+                self.1,
+                self.1
+            )));
+        }
         let idx = marker.point.to_1d(self.1) as usize;
         let mut_marker = self.0.get_mut(idx).ok_or_else(|| {
             ParseError::Other(format!("Couldn't get index {} in board array", idx))
         })?;
+        let color = if marker.point.is_null {
+            Stone::Empty
+        } else {
+            marker.color
+        };
+        self.2.set_point(marker.point, color);
         *mut_marker = marker;
         Ok(())
     }
@@ -360,11 +497,15 @@ impl BoardArr {
         self.0 = (0..self.1 * self.1)
             .map(|idx| BoardMarker::new(Point::from_1d(idx, self.1), Stone::Empty))
             .collect();
+        self.2 = Bitboard::new(self.1);
     }
     /// Returns a immutable reference to the `BoardMarker` at `pos`
     #[must_use]
     #[track_caller]
     pub fn get_point(&self, pos: Point) -> Option<&BoardMarker> {
+        if !pos.is_valid(self.1) {
+            return None;
+        }
         let marker = self.0.get(pos.to_1d(self.1) as usize);
         if let Some(marker) = marker {
             assert_eq!(marker.point, pos);
@@ -374,11 +515,19 @@ impl BoardArr {
     /// Returns a immutable reference to the `BoardMarker` at (`x`,`y`)
     #[must_use]
     pub fn get_xy(&self, x: u32, y: u32) -> Option<&BoardMarker> {
+        if x >= self.1 {
+            return None;
+        }
         self.0.get((x + y * self.1) as usize)
     }
     /// Returns a mutable reference to the `BoardMarker` at (`x`,`y`)
+    ///
+    /// Changing the color through it leaves the [`Bitboard`] behind, use [`Self::set_point`].
     #[must_use]
-    pub fn get_xy_mut(&mut self, x: u32, y: u32) -> Option<&mut BoardMarker> {
+    pub(crate) fn get_xy_mut(&mut self, x: u32, y: u32) -> Option<&mut BoardMarker> {
+        if x >= self.1 {
+            return None;
+        }
         self.0.get_mut((x + y * self.1) as usize)
     }
     #[must_use]
@@ -390,18 +539,34 @@ impl BoardArr {
             None
         }
     }
-    /// Returns a mutable reference to the `BoardMarker` at `pos`
-    pub fn get_point_mut(&mut self, pos: Point) -> Option<&mut BoardMarker> {
-        self.0.get_mut(pos.to_1d(self.1) as usize)
+    /// Returns a mutable reference to the `BoardMarker` at `pos`, see [`Self::get_xy_mut`]
+    pub(crate) fn get_point_mut(&mut self, pos: Point) -> Option<&mut BoardMarker> {
+        if !pos.is_valid(self.1) {
+            return None;
+        }
+        self.get_xy_mut(pos.x, pos.y)
     }
 
-    /// Returns a mutable reference to the `BoardMarker` at `pos`
-    pub fn get_mut(&mut self, pos: usize) -> Option<&mut BoardMarker> {
+    /// Returns a mutable reference to the `BoardMarker` at `pos`, see [`Self::get_xy_mut`]
+    pub(crate) fn get_mut(&mut self, pos: usize) -> Option<&mut BoardMarker> {
         self.0.get_mut(pos)
     }
     /// Sets the `BoardMarker` at `pos` to `color`
-    pub fn set_point(&mut self, pos: Point, color: Stone) {
+    ///
+    /// # Errors
+    /// If `pos` isn't on the board.
+    pub fn set_point(&mut self, pos: Point, color: Stone) -> Result<(), ParseError> {
+        if !pos.is_valid(self.1) {
+            return Err(ParseError::Other(format!(
+                "{} is outside the {}x{} board",
+                pos.notation(self.1),
+                self.1,
+                self.1
+            )));
+        }
+        self.2.set_point(pos, color);
         self.0[pos.to_1d(self.1) as usize].color = color;
+        Ok(())
     }
 }
 
@@ -426,7 +591,7 @@ impl fmt::Display for BoardArr {
         // Not sure if needed - let vec: Vec<BoardMarker> = *self;
         let mut dy: u32 = 0;
         let width: u32 = self.last().unwrap().point.y + 1;
-        write!(f, "15:")?;
+        write!(f, "{:2}:", self.1)?;
         for marker in self.iter() {
             if marker.point.y == dy {
                 if marker.point.x != width {
@@ -436,13 +601,13 @@ impl fmt::Display for BoardArr {
                 }
             } else {
                 dy += 1;
-                write!(f, "\n{:2}:{} ", 15 - dy, marker)?;
+                write!(f, "\n{:2}:{} ", self.1 - dy, marker)?;
             }
         }
         write!(
             f,
             "\n   {}",
-            (b'A'..b'A' + 15)
+            (b'A'..b'A' + self.1 as u8)
                 .map(|d| (d as char).to_string())
                 .collect::<Vec<_>>()
                 .join(" ")
@@ -472,17 +637,66 @@ impl DisplayBoard {
     }
 }
 
+/// Collects every point of a board, row by row.
+///
+/// The size is the smallest one that fits every marker, at most [`MAX_SIZE`]. If the markers
+/// don't fill a square board, the rest of it is empty, and markers that don't fit on a board of
+/// [`MAX_SIZE`] are dropped.
 impl FromIterator<BoardMarker> for BoardArr {
     fn from_iter<I: IntoIterator<Item = BoardMarker>>(iterator: I) -> Self {
-        let mut c = Self::new(15); // TODO: default size
-
-        for i in iterator {
+        let mut c = Self(vec![], 0, Bitboard::new(0));
+        for i in iterator.into_iter().take((MAX_SIZE * MAX_SIZE) as usize) {
             c.add(i);
         }
+        let size = (1..=MAX_SIZE)
+            .find(|size| (size * size) as usize >= c.len())
+            .unwrap_or(MAX_SIZE);
+        c.1 = if c.is_empty() { 0 } else { size };
+        for idx in c.len() as u32..c.1 * c.1 {
+            c.add(BoardMarker::new(Point::from_1d(idx, c.1), Stone::Empty));
+        }
+        c.2 = Bitboard::new(c.1);
+        for marker in &c.0 {
+            if !marker.point.is_null && marker.point.is_valid(c.1) {
+                c.2.set_point(marker.point, marker.color);
+            }
+        }
         c
     }
 }
 
+/// The stones by their points in standard notation, with the size of the board.
+impl fmt::Debug for BoardArr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stones = |stone| {
+            self.iter()
+                .filter(|m| m.color == stone)
+                .map(|m| m.point.notation(self.1))
+                .collect::<Vec<_>>()
+        };
+        f.debug_struct("BoardArr")
+            .field("size", &self.1)
+            .field("black", &stones(Stone::Black))
+            .field("white", &stones(Stone::White))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+impl BoardArr {
+    /// A 15x15 board with black and white stones on the `(x, y)` points given.
+    pub(crate) fn with_stones(black: &[(u32, u32)], white: &[(u32, u32)]) -> Self {
+        let mut board = Self::new(15);
+        for (x, y) in black {
+            board.set_point(Point::new(*x, *y), Stone::Black).unwrap();
+        }
+        for (x, y) in white {
+            board.set_point(Point::new(*x, *y), Stone::White).unwrap();
+        }
+        board
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -491,23 +705,133 @@ mod tests {
         let mut board = BoardArr::new(15);
         assert_eq!(board.len(), 15 * 15);
         let p = Point::new(0, 0);
-        board.set_point(p, Stone::White);
+        board.set_point(p, Stone::White).unwrap();
         assert_eq!(board.get_point(p).unwrap().color, Stone::White);
         let p = Point::new(3, 2);
-        board.set_point(p, Stone::Black);
+        board.set_point(p, Stone::Black).unwrap();
         assert_eq!(board.get_point(p).unwrap().color, Stone::Black);
         // tracing::info!("{:?}", board);
         tracing::info!("Board\n{}", board);
     }
 
+    #[test]
+    fn notation() {
+        assert_eq!("h8".parse::<Point>().unwrap(), p![H, 8]);
+        assert_eq!("A1".parse::<Point>().unwrap(), p![A, 1]);
+        assert_eq!("o15".parse::<Point>().unwrap(), p![O, 15]);
+        assert_eq!("pass".parse::<Point>().unwrap(), Point::null());
+        for invalid in ["", "h", "8", "p8", "h0", "h16", "h+8", "hh8", "h8h"] {
+            assert!(invalid.parse::<Point>().is_err(), "{invalid:?}");
+        }
+        for idx in 0..15 * 15 {
+            let point = Point::from_1d(idx, 15);
+            assert_eq!(point.to_string().parse::<Point>().unwrap(), point);
+        }
+        assert_eq!(p![H, 8].to_string(), "h8");
+        assert_eq!(p![K, 12].to_string(), "k12");
+        assert_eq!(Point::null().to_string(), "pass");
+    }
+
+    #[test]
+    fn notation_with_size() {
+        assert_eq!(Point::from_notation("a1", 19).unwrap(), Point::new(0, 18));
+        assert_eq!(Point::from_notation("t20", 20).unwrap(), Point::new(19, 0));
+        assert_eq!(Point::from_notation("j10", 19).unwrap(), Point::new(9, 9));
+        assert!(Point::from_notation("p8", 15).is_err());
+        assert!(Point::from_notation("a16", 15).is_err());
+        assert!(Point::from_notation("t1", 19).is_err());
+        for size in [5, 15, 19, 20, MAX_SIZE] {
+            for idx in 0..size * size {
+                let point = Point::from_1d(idx, size);
+                assert_eq!(
+                    Point::from_notation(&point.notation(size), size).unwrap(),
+                    point
+                );
+            }
+        }
+        assert_eq!(
+            Point::parse_line_with_size("j10 k10 s19", 19).unwrap(),
+            vec![Point::new(9, 9), Point::new(10, 9), Point::new(18, 0)]
+        );
+        // debug output doesn't depend on the size of the board
+        let marker = BoardMarker::new(Point::new(18, 0), Stone::Black);
+        assert_eq!(format!("{marker:?}"), "|[18,  0]X|");
+    }
+
+    #[test]
+    fn larger_board() {
+        let mut board = BoardArr::new(19);
+        assert_eq!(board.len(), 19 * 19);
+        board.set_point(Point::new(18, 18), Stone::Black).unwrap();
+        assert_eq!(
+            board.get_point(Point::new(18, 18)).unwrap().color,
+            Stone::Black
+        );
+        assert!(board.get_point(Point::new(19, 0)).is_none());
+        assert!(board.get_xy(19, 0).is_none());
+        assert!(board
+            .set(BoardMarker::new(Point::new(0, 19), Stone::White))
+            .is_err());
+        assert!(board.set_point(Point::new(19, 0), Stone::White).is_err());
+        assert!(board.bitboard().stones(Stone::White).is_empty());
+        let collected = board.iter().cloned().collect::<BoardArr>();
+        assert_eq!(collected.size(), 19);
+        assert!(board.to_string().starts_with("19:"));
+        assert_eq!(
+            format!("{board:?}"),
+            r#"BoardArr { size: 19, black: ["s1"], white: [] }"#
+        );
+        // the rest of the board is empty
+        let collected = board.iter().take(19 * 18).cloned().collect::<BoardArr>();
+        assert_eq!(collected.size(), 19);
+        assert_eq!(collected.len(), 19 * 19);
+        assert_eq!(
+            collected.get_point(Point::new(18, 18)).unwrap().color,
+            Stone::Empty
+        );
+    }
+
+    #[test]
+    fn notation_line() {
+        let line = p![[H, 8], [I, 9], [G, 7], [H, 7]].to_vec();
+        assert_eq!(Point::parse_line("h8 i9 g7 h7").unwrap(), line);
+        assert_eq!(Point::parse_line("h8i9g7h7").unwrap(), line);
+        assert_eq!(Point::parse_line(" H8,I9  g7h7\n").unwrap(), line);
+        assert_eq!(
+            Point::parse_line("a15o1 pass").unwrap(),
+            vec![p![A, 15], p![O, 1], Point::null()]
+        );
+        assert_eq!(Point::parse_line("").unwrap(), vec![]);
+        assert!(Point::parse_line("h8 i99").is_err());
+        assert!(Point::parse_line("h8 9i").is_err());
+    }
+
     #[test]
     fn clear_board() {
         let mut board = BoardArr::new(15);
         let p = Point::new(7, 7);
-        board.set_point(p, Stone::White);
+        board.set_point(p, Stone::White).unwrap();
         tracing::info!("Board:\n{}", board);
         board.clear();
         tracing::info!("Board - Cleared:\n{}", board);
         assert_eq!(board.get_point(p).unwrap().color, Stone::Empty);
+        assert_eq!(board.bitboard(), &Bitboard::new(15));
+    }
+
+    #[test]
+    fn bitboard_follows_the_stones() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black).unwrap();
+        board.set(BoardMarker::new(p![I, 9], Stone::White)).unwrap();
+        board.set_point(p![G, 7], Stone::Black).unwrap();
+        board.set_point(p![G, 7], Stone::Empty).unwrap();
+        let bitboard = board.bitboard();
+        assert_eq!(bitboard.get(p![H, 8]), Some(Stone::Black));
+        assert_eq!(bitboard.get(p![I, 9]), Some(Stone::White));
+        assert_eq!(bitboard.get(p![G, 7]), Some(Stone::Empty));
+        assert_eq!(bitboard.stones(Stone::Black).len(), 1);
+        assert_eq!(bitboard.zobrist(), board.zobrist());
+        let collected = board.iter().cloned().collect::<BoardArr>();
+        assert_eq!(collected.bitboard(), bitboard);
     }
 }
@@ -0,0 +1,362 @@
+//! A place in the tree of a [`Board`], held apart from it.
+//!
+//! A move can be reached from more than one move when it's linked with [`Board::add_edge`], so
+//! walking back from a move alone is ambiguous. A [`Cursor`] remembers the line it took from the
+//! root, links included, and goes back the same way.
+use super::{Board, BoardArr, EditError, IndexOutOfBoundsError, MoveIndex, Point};
+use crate::errors::ParseError;
+
+/// A line of moves from the root of a [`Board`] and the current move on it.
+///
+/// The moves after the current one are kept when going back, so [`Cursor::forward`] can follow
+/// them again. A board has a cursor of its own, see [`Board::cursor`], and any number of others
+/// can be held on the side.
+///
+/// Removing moves from the tree renumbers others, see [`Renumbered`](super::Renumbered), so only
+/// the cursor of the board itself is kept up to date by edits. A cursor held on the side is stale
+/// after that, and its methods that look at the board return [`EditError::Stale`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cursor {
+    /// The moves from the root, each one played from or linked to by the one before it.
+    line: Vec<MoveIndex>,
+    index: usize,
+    /// The [`Board::generation`] the moves are numbered for.
+    pub(super) generation: usize,
+}
+
+impl Cursor {
+    /// A cursor at the root of `board`.
+    #[must_use]
+    pub fn new(board: &Board) -> Self {
+        Self {
+            generation: board.generation(),
+            ..Self::root(board.get_root())
+        }
+    }
+
+    /// A cursor at `node`, on the line of moves it was played from.
+    pub fn to(board: &Board, node: MoveIndex) -> Result<Self, EditError> {
+        board.check_node(node)?;
+        let mut line = vec![board.move_index(node.node_index)];
+        while let Ok(parent) = board.move_parent(line[line.len() - 1]) {
+            line.push(board.move_index(parent));
+        }
+        line.reverse();
+        Ok(Self {
+            index: line.len() - 1,
+            line,
+            generation: board.generation(),
+        })
+    }
+
+    pub(super) fn root(root: MoveIndex) -> Self {
+        Self {
+            line: vec![root],
+            index: 0,
+            generation: 0,
+        }
+    }
+
+    /// A cursor at `line[index]`, the moves of `line` have to follow each other.
+    pub(super) fn from_line(line: Vec<MoveIndex>, index: usize, generation: usize) -> Self {
+        debug_assert!(index < line.len());
+        Self {
+            line,
+            index,
+            generation,
+        }
+    }
+
+    #[must_use]
+    pub fn current(&self) -> MoveIndex {
+        self.line[self.index]
+    }
+
+    #[must_use]
+    pub fn root_move(&self) -> MoveIndex {
+        self.line[0]
+    }
+
+    /// How many moves the current move is from the root.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The moves from the root to the current move.
+    #[must_use]
+    pub fn line(&self) -> &[MoveIndex] {
+        &self.line[..=self.index]
+    }
+
+    /// The whole line, with the moves after the current one.
+    #[must_use]
+    pub fn moves(&self) -> &[MoveIndex] {
+        &self.line
+    }
+
+    #[must_use]
+    pub fn prev(&self) -> Option<MoveIndex> {
+        self.line.get(self.index.checked_sub(1)?).copied()
+    }
+
+    #[must_use]
+    pub fn next(&self) -> Option<MoveIndex> {
+        self.line.get(self.index + 1).copied()
+    }
+
+    /// Go to the move before the current one, the way the cursor came. Returns `false` at the
+    /// root.
+    pub fn back(&mut self) -> bool {
+        let moved = self.index > 0;
+        self.index = self.index.saturating_sub(1);
+        moved
+    }
+
+    /// Go to the next move of the line, returns `false` at the end of it.
+    pub fn forward(&mut self) -> bool {
+        let moved = self.index + 1 < self.line.len();
+        if moved {
+            self.index += 1;
+        }
+        moved
+    }
+
+    pub fn to_root(&mut self) {
+        self.index = 0;
+    }
+
+    /// Go to `line[index]`, keeping the moves after it.
+    pub fn set_index(&mut self, index: usize) -> Result<(), IndexOutOfBoundsError> {
+        if index < self.line.len() {
+            self.index = index;
+            Ok(())
+        } else {
+            Err(IndexOutOfBoundsError)
+        }
+    }
+
+    /// Forget the moves after the current one.
+    pub fn truncate(&mut self) {
+        self.line.truncate(self.index + 1);
+    }
+
+    /// Go on to `node`, a move played from the current move or linked to from it, see
+    /// [`Board::next_moves`].
+    ///
+    /// The rest of the line is kept if `node` is the next move of it.
+    pub fn play(&mut self, board: &Board, node: MoveIndex) -> Result<(), EditError> {
+        self.check_generation(board)?;
+        board.check_node(node)?;
+        let current = self.current();
+        let (child, ..) = board
+            .next_moves(&current)
+            .find(|(child, ..)| child.node_index == node.node_index)
+            .ok_or(EditError::NotChild(node, current))?;
+        if self.next().map(|next| next.node_index) != Some(node.node_index) {
+            self.truncate();
+            self.line.push(child);
+        }
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Go on to `node` without checking that it follows the current move.
+    pub(super) fn push(&mut self, node: MoveIndex) {
+        self.truncate();
+        self.line.push(node);
+        self.index += 1;
+    }
+
+    /// Returns `true` if every move of the line is on `board` and follows the one before it.
+    #[must_use]
+    pub fn is_valid(&self, board: &Board) -> bool {
+        self.check(board).is_ok()
+    }
+
+    /// Like [`Cursor::is_valid`], with the first move that isn't.
+    ///
+    /// Returns [`EditError::Stale`] if moves were removed from `board` since the cursor was made,
+    /// its moves may be others now even if they still follow each other.
+    pub fn check(&self, board: &Board) -> Result<(), EditError> {
+        self.check_generation(board)?;
+        board.check_node(self.line[0])?;
+        if board.move_parent(self.line[0]).is_ok() {
+            return Err(EditError::NotFound(self.line[0]));
+        }
+        for pair in self.line.windows(2) {
+            board.check_node(pair[1])?;
+            let follows = board
+                .next_moves(&pair[0])
+                .any(|(child, ..)| child.node_index == pair[1].node_index);
+            if !follows {
+                return Err(EditError::NotChild(pair[1], pair[0]));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_generation(&self, board: &Board) -> Result<(), EditError> {
+        if self.generation == board.generation() {
+            Ok(())
+        } else {
+            Err(EditError::Stale)
+        }
+    }
+
+    /// The position at the current move, and the points of the moves played to get there, see
+    /// [`Board::as_board`].
+    ///
+    /// A link goes to a move with the same position, so following one plays no move.
+    pub fn as_board(&self, board: &Board) -> Result<(BoardArr, Vec<Point>), ParseError> {
+        self.check_generation(board)?;
+        let mut moves = Vec::with_capacity(self.index + 1);
+        let mut position = BoardArr::new(board.size());
+        let mut previous = None;
+        for node in self.line() {
+            let marker = board.get_move(*node).ok_or(EditError::NotFound(*node))?;
+            let linked = previous.is_some_and(|previous: MoveIndex| {
+                board.move_parent(*node) != Ok(previous.node_index)
+            });
+            previous = Some(*node);
+            if linked {
+                continue;
+            }
+            if marker.command.is_move() {
+                moves.push(marker.point);
+            }
+            if !marker.point.is_null {
+                position.set(marker.clone())?;
+            }
+        }
+        Ok((position, moves))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn walks_back_the_way_it_came() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let first = board.get_or_insert_points(root, line("h8 i9 j10"));
+        let k11 = board.get_or_insert_points(first, line("k11"));
+        let second = board.get_or_insert_points(root, line("j10 i9 h8"));
+        // the same position, linked to the first line
+        assert_eq!(board.link_transpositions(), 1);
+
+        let mut linked = Cursor::new(&board);
+        for node in board.find_line(&root, line("j10 i9 h8")).unwrap() {
+            linked.play(&board, node).unwrap();
+        }
+        linked.play(&board, first).unwrap();
+        linked.play(&board, k11).unwrap();
+        assert_eq!(linked.index(), 5);
+        assert_eq!(
+            linked.as_board(&board).unwrap().1,
+            line("j10 i9 h8 k11"),
+            "following a link plays no move"
+        );
+        let mut played = Cursor::to(&board, k11).unwrap();
+        assert_eq!(played.as_board(&board).unwrap().1, line("h8 i9 j10 k11"));
+        assert_eq!(
+            linked.as_board(&board).unwrap().0.to_string(),
+            played.as_board(&board).unwrap().0.to_string()
+        );
+        assert!(linked.is_valid(&board) && played.is_valid(&board));
+
+        assert!(linked.back() && linked.back());
+        assert_eq!(linked.current(), second);
+        assert!(played.back());
+        assert_eq!(played.current(), first);
+        assert!(linked.forward());
+        assert_eq!(linked.current().node_index, first.node_index);
+
+        linked.to_root();
+        assert!(!linked.back());
+        assert_eq!(
+            linked.play(&board, k11),
+            Err(EditError::NotChild(k11, root))
+        );
+        let h8 = board.get_move_children(&root)[0];
+        linked.play(&board, h8).unwrap();
+        assert_eq!(linked.moves(), [root, h8], "a new move forgets the rest");
+        assert!(!linked.forward());
+        assert_eq!(linked.set_index(2), Err(IndexOutOfBoundsError));
+
+        board.remove_subtree(k11).unwrap();
+        assert!(!played.is_valid(&board));
+    }
+
+    #[test]
+    fn stale_after_removing_moves() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let i9 = board.get_or_insert_points(root, line("h8 i9"));
+        let j10 = board.get_or_insert_points(root, line("j10"));
+        let mut held = Cursor::to(&board, i9).unwrap();
+        let removed = Cursor::to(&board, j10).unwrap();
+        board.set_current_move(i9).unwrap();
+
+        // adding moves keeps the others where they are
+        let k11 = board.get_or_insert_points(i9, line("k11"));
+        let g7 = board.get_or_insert_points(root, line("g7"));
+        held.play(&board, k11).unwrap();
+        assert_eq!(held.as_board(&board).unwrap().1, line("h8 i9 k11"));
+
+        // the last move, g7, takes the place of j10, and is played from the root as well
+        board.remove_subtree(j10).unwrap();
+        assert_eq!(board.get_move(j10).unwrap().point, line("g7")[0]);
+        assert!(board.get_move(g7).is_none());
+        assert_eq!(removed.check(&board), Err(EditError::Stale));
+        assert!(matches!(
+            removed.as_board(&board),
+            Err(ParseError::Edit(EditError::Stale))
+        ));
+        assert_eq!(held.check(&board), Err(EditError::Stale));
+        assert_eq!(held.play(&board, j10), Err(EditError::Stale));
+        assert_eq!(board.set_cursor(held), Err(EditError::Stale));
+
+        // the board's own cursor was kept up to date
+        assert!(board.cursor().is_valid(&board));
+        assert_eq!(board.cursor().as_board(&board).unwrap().1, line("h8 i9"));
+        let mut fresh = Cursor::to(&board, j10).unwrap();
+        assert_eq!(fresh.as_board(&board).unwrap().1, line("g7"));
+        assert!(fresh.back());
+        assert_eq!(fresh.check(&board), Ok(()));
+    }
+
+    #[test]
+    fn follows_a_removed_move() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let j10 = board.get_or_insert_points(root, line("h8 i9 j10"));
+        let i9 = board.get_parent_strong(&j10).unwrap();
+        board.get_or_insert_points(root, line("k11"));
+        board.set_current_move(j10).unwrap();
+
+        // the line stops before i9, and k11, the last move, takes its place
+        assert!(board.rm_move(i9).is_some());
+        assert!(board.cursor().is_valid(&board));
+        assert_eq!(board.cursor().as_board(&board).unwrap().1, line("h8"));
+        assert_eq!(board.get_move(i9).unwrap().point, line("k11")[0]);
+
+        // the current move itself takes the place of a removed one
+        let k11 = board.get_move_children(&root)[1];
+        board.set_current_move(k11).unwrap();
+        assert!(board.rm_move(j10).is_some());
+        assert!(board.cursor().is_valid(&board));
+        assert_eq!(board.cursor().current(), board.get_move_children(&root)[1]);
+        assert_eq!(board.cursor().as_board(&board).unwrap().1, line("k11"));
+
+        assert_eq!(board.rm_move(root), None);
+        assert!(board.cursor().is_valid(&board));
+    }
+}
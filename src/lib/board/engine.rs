@@ -0,0 +1,686 @@
+//! A general purpose engine, searching a position with iterative deepening alpha-beta.
+//!
+//! Positions are scored by a static evaluation of the patterns on the board: every row of five
+//! points that only one color has stones in is worth more the more stones it has, see
+//! [`evaluate`]. The search tries the moves that make or break the most valuable rows first and
+//! only looks at the [`Engine::with_width`] best of them, which is what keeps it fast enough on a
+//! full board.
+//!
+//! Scores are from the side to move's point of view, a won position scores [`WIN`] minus the
+//! number of moves to the win.
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use super::bitboard::{Bitboard, Bits};
+use super::evaluator::Direction;
+use super::rules::{RuleSet, DIRECTIONS};
+use super::{Board, BoardArr, MoveIndex, Point, Stone};
+
+/// The score of a five on the board.
+pub const WIN: i32 = 1_000_000;
+/// Scores this close to [`WIN`] are wins, not evaluations.
+const MAX_PLY: i32 = 1_000;
+
+/// The value of a row of five points with `n` stones of one color and none of the other.
+const WEIGHTS: [i32; 6] = [0, 1, 12, 150, 2_000, WIN];
+
+/// The result of [`Engine::analyse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// The suggested move, `None` if the game is over.
+    pub best: Option<Point>,
+    /// The score of the position for the side to move.
+    pub score: i32,
+    /// The expected line of play, starting with [`Analysis::best`].
+    pub principal: Vec<Point>,
+    /// The deepest search that was done, in moves.
+    pub depth: u32,
+    /// The positions that were searched.
+    pub nodes: u64,
+}
+
+impl Analysis {
+    /// The number of moves of the side to move until the game is decided, positive if they win and
+    /// negative if they lose. `None` if the search didn't see the end of the game.
+    #[must_use]
+    pub fn win_in(&self) -> Option<i32> {
+        let plies = WIN - self.score.abs();
+        (plies < MAX_PLY).then(|| (plies + 1) / 2 * self.score.signum())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    depth: u32,
+    score: i32,
+    bound: Bound,
+    best: Option<Point>,
+}
+
+/// Searches for the best move, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Engine {
+    rules: RuleSet,
+    /// The deepest search, in moves.
+    depth: u32,
+    /// The most moves tried in a position.
+    width: usize,
+    time_limit: Option<Duration>,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    nodes: u64,
+    stopped: bool,
+    /// Searched positions by their hash.
+    table: HashMap<u64, Entry>,
+}
+
+impl Engine {
+    /// Default depth of a search, in moves.
+    pub const DEPTH: u32 = 6;
+    /// Default number of moves tried in a position.
+    pub const WIDTH: usize = 12;
+
+    #[must_use]
+    pub fn new(rules: RuleSet) -> Self {
+        Self {
+            rules,
+            depth: Self::DEPTH,
+            width: Self::WIDTH,
+            time_limit: None,
+            node_limit: None,
+            deadline: None,
+            nodes: 0,
+            stopped: false,
+            table: HashMap::new(),
+        }
+    }
+
+    /// Search at most `depth` moves deep.
+    #[must_use]
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// Try at most `width` moves in every position.
+    #[must_use]
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    /// Stop deepening after `limit`, the last complete search is used.
+    #[must_use]
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Stop deepening after searching `limit` positions, the last complete search is used.
+    #[must_use]
+    pub fn with_node_limit(mut self, limit: u64) -> Self {
+        self.node_limit = Some(limit);
+        self
+    }
+
+    /// Search `position` for the best move of `stone`.
+    ///
+    /// Even if a limit stops the first search, a move is suggested as long as the game is going.
+    pub fn analyse(&mut self, position: &BoardArr, stone: Stone) -> Analysis {
+        assert!(!stone.is_empty());
+        self.deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        self.nodes = 0;
+        self.stopped = false;
+        self.table.clear();
+        let mut analysis = Analysis {
+            best: None,
+            score: 0,
+            principal: vec![],
+            depth: 0,
+            nodes: 0,
+        };
+        let mut position = Bitboard::from(position);
+        if position.outcome(self.rules).is_some() {
+            return analysis;
+        }
+        for depth in 1..=self.depth {
+            let mut line = vec![];
+            let score = self.search(&mut position, stone, depth, 0, -WIN, WIN, &mut line);
+            if self.stopped {
+                break;
+            }
+            analysis.best = line.first().copied();
+            analysis.score = score;
+            analysis.principal = line;
+            analysis.depth = depth;
+            if score.abs() > WIN - MAX_PLY {
+                break;
+            }
+        }
+        if analysis.best.is_none() {
+            analysis.best = self.candidates(&position, stone, None).first().copied();
+            analysis.principal.extend(analysis.best);
+        }
+        analysis.nodes = self.nodes;
+        analysis
+    }
+
+    /// Search the position after `node` for the side to move.
+    pub fn analyse_node(
+        &mut self,
+        board: &Board,
+        node: MoveIndex,
+    ) -> Result<Analysis, crate::errors::ParseError> {
+        let (position, _) = board.as_board(&node)?;
+        let stone = board.side_to_move(&node);
+        Ok(self.analyse(&position, stone))
+    }
+
+    fn stop(&mut self) -> bool {
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit)
+            || (self.nodes.is_multiple_of(256)
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline))
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    /// Negamax with alpha-beta pruning, filling `line` with the principal variation.
+    #[allow(clippy::too_many_arguments)]
+    fn search(
+        &mut self,
+        position: &mut Bitboard,
+        stone: Stone,
+        depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        mut beta: i32,
+        line: &mut Vec<Point>,
+    ) -> i32 {
+        line.clear();
+        if self.stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        if let Some(five) = self.wins(position, stone).first() {
+            line.push(*five);
+            return WIN - ply - 1;
+        }
+        if depth == 0 {
+            return evaluate(position, stone);
+        }
+        let hash = position.zobrist().hash();
+        let entry = self.table.get(&hash).copied();
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth && ply > 0) {
+            let score = from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => {
+                    self.table_line(position, stone, depth, line);
+                    return score;
+                }
+                Bound::Lower => alpha = alpha.max(score),
+                Bound::Upper => beta = beta.min(score),
+            }
+            if alpha >= beta {
+                line.extend(entry.best);
+                return score;
+            }
+        }
+
+        let opponent = stone.opposite();
+        let threats = self.wins(position, opponent);
+        let moves = if threats.is_empty() {
+            self.candidates(position, stone, entry.and_then(|entry| entry.best))
+        } else {
+            // anything else loses right away
+            threats
+        };
+        // black's forbidden points among the moves, found together
+        let forbidden = if self.rules.restricts(stone) {
+            position.renju_conditions(stone, Some(&moves)).forbidden
+        } else {
+            BTreeSet::new()
+        };
+        let original_alpha = alpha;
+        let mut best = None;
+        let mut best_score = -WIN;
+        let mut rest = vec![];
+        for point in moves {
+            if self.rules.restricts(stone) && position.forbidden_with(point, &forbidden).is_some() {
+                continue;
+            }
+            position.set_point(point, stone);
+            let score = -self.search(
+                position,
+                opponent,
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+                &mut rest,
+            );
+            position.set_point(point, Stone::Empty);
+            if self.stopped {
+                return 0;
+            }
+            if best.is_none() || score > best_score {
+                best = Some(point);
+                best_score = score;
+                line.clear();
+                line.push(point);
+                line.append(&mut rest);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        if best.is_none() {
+            // every move is forbidden or the board is full, the opponent wins a threat
+            // if there is one
+            return if self.wins(position, opponent).is_empty() {
+                0
+            } else {
+                -(WIN - ply - 2)
+            };
+        }
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.insert(
+            hash,
+            Entry {
+                depth,
+                score: to_table(best_score, ply),
+                bound,
+                best,
+            },
+        );
+        best_score
+    }
+
+    /// Follow the best moves in the table from `position`, at most `depth` moves.
+    fn table_line(&self, position: &mut Bitboard, stone: Stone, depth: u32, line: &mut Vec<Point>) {
+        let mut stone = stone;
+        for _ in 0..depth {
+            let best = self
+                .table
+                .get(&position.zobrist().hash())
+                .and_then(|entry| entry.best)
+                .or_else(|| self.wins(position, stone).first().copied());
+            match best {
+                Some(point) if position.get(point) == Some(Stone::Empty) => {
+                    position.set_point(point, stone);
+                    line.push(point);
+                    stone = stone.opposite();
+                }
+                _ => break,
+            }
+        }
+        for point in line.iter() {
+            position.set_point(*point, Stone::Empty);
+        }
+    }
+
+    /// The points where `stone` wins right away.
+    fn wins(&self, position: &Bitboard, stone: Stone) -> Vec<Point> {
+        let mut wins = position
+            .points(position.fives(stone))
+            .filter(|point| position.wins_with(*point, stone, self.rules))
+            .collect::<Vec<_>>();
+        wins.sort();
+        wins
+    }
+
+    /// The empty points near the stones on the board, best first, starting with `first`.
+    fn candidates(&self, position: &Bitboard, stone: Stone, first: Option<Point>) -> Vec<Point> {
+        // two steps from a stone
+        let stones = position.stones(Stone::Black) | position.stones(Stone::White);
+        let near = position.neighbours(position.neighbours(stones));
+        let mut moves = position
+            .points(near & position.stones(Stone::Empty))
+            .map(|point| (pattern_score(position, point, stone), point))
+            .collect::<Vec<_>>();
+        if moves.is_empty() {
+            let center = Point::new(position.size() / 2, position.size() / 2);
+            return if position.get(center) == Some(Stone::Empty) {
+                vec![center]
+            } else {
+                vec![]
+            };
+        }
+        moves.sort_by(|(a, a_point), (b, b_point)| b.cmp(a).then(a_point.cmp(b_point)));
+        let mut moves = moves
+            .into_iter()
+            .map(|(_, point)| point)
+            .take(self.width)
+            .collect::<Vec<_>>();
+        if let Some(first) = first {
+            moves.retain(|point| *point != first);
+            moves.insert(0, first);
+        }
+        moves
+    }
+}
+
+/// The static evaluation of `position` for `stone`, the value of its rows of five minus the value
+/// of the opponent's.
+///
+/// A five on the board is not scored as a win, use [`Bitboard::outcome`] for that.
+#[must_use]
+pub fn evaluate(position: &Bitboard, stone: Stone) -> i32 {
+    let mut score = 0;
+    for direction in Direction::directions() {
+        // the starts of the rows of five that fit on the board
+        let rows = (0..5).fold(position.board(), |rows, step| {
+            rows & position.view(position.board(), direction, step)
+        });
+        let black = row_counts(position, Stone::Black, direction);
+        let white = row_counts(position, Stone::White, direction);
+        let (no_black, no_white) = (rows & !black.any(), rows & !white.any());
+        for n in 1..=5 {
+            let weight = WEIGHTS[n.min(4)];
+            score += weight * (no_white & black.exactly(n)).len() as i32;
+            score -= weight * (no_black & white.exactly(n)).len() as i32;
+        }
+    }
+    if stone.is_black() {
+        score
+    } else {
+        -score
+    }
+}
+
+/// The number of stones in the row of five from every point, in binary: a point is in `ones` if
+/// the count is odd, in `twos` if it has a two and in `fours` if it has a four.
+struct RowCounts {
+    ones: Bits,
+    twos: Bits,
+    fours: Bits,
+}
+
+impl RowCounts {
+    /// The points with any stones in their row.
+    fn any(&self) -> Bits {
+        self.ones | self.twos | self.fours
+    }
+
+    /// The points with `n` stones in their row.
+    fn exactly(&self, n: usize) -> Bits {
+        [(self.ones, 1), (self.twos, 2), (self.fours, 4)]
+            .into_iter()
+            .fold(!Bits::default(), |exactly, (bits, bit)| {
+                exactly & if n & bit == 0 { !bits } else { bits }
+            })
+    }
+}
+
+/// Count the `stone`s in the row of five from every point in `direction`, adding up the shifted
+/// views a bit at a time.
+fn row_counts(position: &Bitboard, stone: Stone, direction: Direction) -> RowCounts {
+    let mut counts = RowCounts {
+        ones: Bits::default(),
+        twos: Bits::default(),
+        fours: Bits::default(),
+    };
+    for step in 0..5 {
+        let stones = position.shifted(stone, direction, step);
+        let carry = counts.ones & stones;
+        counts.ones = counts.ones ^ stones;
+        counts.fours |= counts.twos & carry;
+        counts.twos = counts.twos ^ carry;
+    }
+    counts
+}
+
+/// How much playing `stone` on `point` adds to its own rows and takes from the opponent's.
+fn pattern_score(position: &Bitboard, point: Point, stone: Stone) -> i32 {
+    let (x, y) = (i64::from(point.x), i64::from(point.y));
+    let mut score = 0;
+    for (dx, dy) in DIRECTIONS {
+        for offset in 0..5 {
+            let start = (x - dx * offset, y - dy * offset);
+            let Some((black, white)) = window(position, start, (dx, dy)) else {
+                continue;
+            };
+            let (own, other) = if stone.is_black() {
+                (black, white)
+            } else {
+                (white, black)
+            };
+            score += match (own, other) {
+                (own, 0) => WEIGHTS[own + 1] - WEIGHTS[own],
+                (0, other) => WEIGHTS[other + 1] - WEIGHTS[other],
+                _ => 0,
+            };
+        }
+    }
+    score
+}
+
+/// The black and white stones in the row of five from `start` in `direction`, `None` if it doesn't
+/// fit on the board.
+fn window(position: &Bitboard, (x, y): (i64, i64), (dx, dy): (i64, i64)) -> Option<(usize, usize)> {
+    let (mut black, mut white) = (0, 0);
+    for step in 0..5 {
+        match position.color_at(x + dx * step, y + dy * step)? {
+            Stone::Black => black += 1,
+            Stone::White => white += 1,
+            Stone::Empty => {}
+        }
+    }
+    Some((black, white))
+}
+
+/// Win scores in the table count from the position, not from the root.
+fn to_table(score: i32, ply: i32) -> i32 {
+    if score > WIN - MAX_PLY {
+        score + ply
+    } else if score < MAX_PLY - WIN {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: i32) -> i32 {
+    if score > WIN - MAX_PLY {
+        score - ply
+    } else if score < MAX_PLY - WIN {
+        score + ply
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MAX_SIZE;
+    use test_log::test;
+
+    /// Replay `line` from `position`, checking that every move is legal.
+    fn replay(position: &BoardArr, mut stone: Stone, line: &[Point]) -> BoardArr {
+        let mut position = position.clone();
+        for point in line {
+            assert_eq!(position.check_move(*point, stone, RuleSet::Renju), Ok(()));
+            position.set_point(*point, stone).unwrap();
+            stone = stone.opposite();
+        }
+        position
+    }
+
+    #[test]
+    fn evaluation() {
+        let empty = Bitboard::new(15);
+        assert_eq!(evaluate(&empty, Stone::Black), 0);
+        let position = Bitboard::from(&BoardArr::with_stones(&[(6, 7), (7, 7), (8, 7)], &[(7, 8)]));
+        let black = evaluate(&position, Stone::Black);
+        assert!(black > 0);
+        assert_eq!(evaluate(&position, Stone::White), -black);
+        // a blocked three is worth less than an open one
+        let blocked = Bitboard::from(&BoardArr::with_stones(&[(6, 7), (7, 7), (8, 7)], &[(5, 7)]));
+        assert!(evaluate(&blocked, Stone::Black) < black);
+    }
+
+    #[test]
+    fn evaluation_counts_every_row() {
+        // the same as adding up the rows one by one
+        let by_row = |position: &Bitboard| {
+            let size = i64::from(position.size());
+            let mut score = 0;
+            for (x, y) in (0..size).flat_map(|y| (0..size).map(move |x| (x, y))) {
+                for direction in DIRECTIONS {
+                    score += match window(position, (x, y), direction) {
+                        Some((black, 0)) => WEIGHTS[black.min(4)],
+                        Some((0, white)) => -WEIGHTS[white.min(4)],
+                        _ => 0,
+                    };
+                }
+            }
+            score
+        };
+        for size in [7, 15, MAX_SIZE] {
+            let mut position = Bitboard::new(size);
+            // random stones, with rows of every length and on the edges
+            let mut state = size;
+            for _ in 0..size * size / 2 {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                let point = Point::new((state >> 8) % size, (state >> 16) % size);
+                position.set_point(point, Stone::from_bool(state & 1 == 0));
+                assert_eq!(evaluate(&position, Stone::Black), by_row(&position));
+            }
+        }
+    }
+
+    #[test]
+    fn takes_a_five() {
+        let position =
+            BoardArr::with_stones(&[(0, 0), (0, 1), (0, 2)], &[(3, 7), (4, 7), (5, 7), (6, 7)]);
+        let analysis = Engine::new(RuleSet::Renju).analyse(&position, Stone::White);
+        assert!(matches!(analysis.best, Some(Point { y: 7, .. })));
+        assert_eq!(analysis.win_in(), Some(1));
+        assert_eq!(analysis.principal.len(), 1);
+    }
+
+    #[test]
+    fn blocks_a_four() {
+        let position =
+            BoardArr::with_stones(&[(3, 7), (4, 7), (5, 7), (6, 7)], &[(2, 7), (7, 8), (8, 8)]);
+        let analysis = Engine::new(RuleSet::Renju)
+            .with_depth(2)
+            .analyse(&position, Stone::White);
+        assert_eq!(analysis.best, Some(Point::new(7, 7)));
+    }
+
+    #[test]
+    fn open_three() {
+        // white wins by making the open three a straight four
+        let position = BoardArr::with_stones(&[(0, 0), (14, 14)], &[(6, 7), (7, 7), (8, 7)]);
+        let analysis = Engine::new(RuleSet::Renju)
+            .with_depth(4)
+            .analyse(&position, Stone::White);
+        assert_eq!(analysis.win_in(), Some(2));
+        let end = replay(&position, Stone::White, &analysis.principal);
+        assert_eq!(
+            end.outcome(RuleSet::Renju).and_then(|o| o.winner()),
+            Some(Stone::White)
+        );
+        // black, to move, blocks it
+        let analysis = Engine::new(RuleSet::Renju)
+            .with_depth(4)
+            .analyse(&position, Stone::Black);
+        assert!(matches!(
+            analysis.best,
+            Some(Point {
+                x: 4 | 5 | 9 | 10,
+                y: 7,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn forbidden_moves() {
+        // white's four on (6, 6) can only be blocked with a double four
+        let position = BoardArr::with_stones(
+            &[(3, 7), (4, 7), (5, 7), (7, 4), (7, 5), (7, 6), (11, 11)],
+            &[(2, 7), (7, 3), (8, 8), (9, 9), (10, 10)],
+        );
+        let analysis = Engine::new(RuleSet::Renju)
+            .with_depth(3)
+            .analyse(&position, Stone::White);
+        assert_eq!(analysis.best, Some(Point::new(6, 6)));
+        assert_eq!(analysis.win_in(), Some(2));
+        // which black may play in freestyle, and wins with
+        let analysis = Engine::new(RuleSet::Freestyle)
+            .with_depth(3)
+            .analyse(&position, Stone::White);
+        assert!(analysis.win_in().is_none_or(|moves| moves < 0));
+    }
+
+    #[test]
+    fn limits() {
+        let position = BoardArr::with_stones(&[(7, 7), (8, 8)], &[(8, 7)]);
+        let analysis = Engine::new(RuleSet::Renju)
+            .with_node_limit(50)
+            .analyse(&position, Stone::White);
+        assert!(analysis.best.is_some());
+        assert!(analysis.nodes <= 50);
+        let analysis = Engine::new(RuleSet::Renju)
+            .with_depth(20)
+            .with_time_limit(Duration::ZERO)
+            .analyse(&position, Stone::White);
+        assert!(analysis.best.is_some());
+        assert_eq!(analysis.depth, 0);
+
+        let empty = Engine::new(RuleSet::Renju)
+            .with_depth(2)
+            .analyse(&BoardArr::new(15), Stone::Black);
+        assert_eq!(empty.best, Some(Point::new(7, 7)));
+    }
+
+    #[test]
+    fn analyse_node() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let node = board.get_or_insert_points(
+            root,
+            [
+                Point::new(7, 7),
+                Point::new(8, 8),
+                Point::new(6, 7),
+                Point::new(8, 6),
+                Point::new(5, 7),
+            ],
+        );
+        let analysis = Engine::new(RuleSet::Renju)
+            .with_depth(2)
+            .analyse_node(&board, node)
+            .unwrap();
+        // white has to stop the open three
+        assert!(matches!(
+            analysis.best,
+            Some(Point {
+                x: 4 | 8 | 3 | 9,
+                y: 7,
+                ..
+            })
+        ));
+        assert!(analysis.score < 0);
+    }
+}
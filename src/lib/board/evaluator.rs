@@ -12,6 +12,8 @@
 //! # Implementation.
 //!
 
+use super::bitboard::Bitboard;
+use super::rules::RuleSet;
 use super::{BoardArr, Point, Stone};
 
 use std::collections::{BTreeMap, BTreeSet};
@@ -170,477 +172,571 @@ pub struct RenjuConditions {
     pub threes: BTreeSet<(RenjuCondition, Point)>,
 }
 
+/// A point of a line, as seen by the stone the conditions are for.
+#[derive(Debug, Clone, Copy)]
+enum Cell {
+    Same,
+    NotSame,
+    Empty,
+    /// A border point, which is not part of the board.
+    Border,
+}
+
+/// The conditions found in one line of the board, before they are checked against the other
+/// lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct LinePatterns {
+    fives: Vec<RenjuCondition>,
+    /// The points that make an overline.
+    overlines: Vec<Point>,
+    fours: Vec<RenjuCondition>,
+    /// The threes, with the point that makes them a straight four.
+    threes: Vec<(RenjuCondition, Point)>,
+}
+
+/// Keeps the [`RenjuConditions`] of a position up to date as stones are placed and removed.
+///
+/// The patterns of every line are kept for both colors, and a change only rescans the four lines
+/// through the changed point. The conditions are put together from the lines when they are asked
+/// for, and kept until the next change.
+#[derive(Debug, Clone)]
+pub struct Evaluator {
+    board: Bitboard,
+    rules: RuleSet,
+    /// The points of every line of the board.
+    lines: Vec<(Direction, Vec<Point>)>,
+    /// The lines through every point, by [`Point::to_1d`].
+    lines_through: Vec<Vec<usize>>,
+    /// The patterns of every line, for black and for white.
+    patterns: [Vec<LinePatterns>; 2],
+    conditions: [Option<RenjuConditions>; 2],
+}
+
+impl Evaluator {
+    #[must_use]
+    pub fn new(board: Bitboard, rules: RuleSet) -> Self {
+        let lines = board
+            .all_lines()
+            .map(|(direction, line)| (direction, line.collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let mut lines_through = vec![vec![]; (board.size() * board.size()) as usize];
+        for (i, (_, line)) in lines.iter().enumerate() {
+            for point in line {
+                lines_through[point.to_1d(board.size()) as usize].push(i);
+            }
+        }
+        let mut evaluator = Self {
+            board,
+            rules,
+            lines,
+            lines_through,
+            patterns: [vec![], vec![]],
+            conditions: [None, None],
+        };
+        evaluator.rescan();
+        evaluator
+    }
+
+    /// The position the conditions are for.
+    #[must_use]
+    pub fn board(&self) -> &Bitboard {
+        &self.board
+    }
+
+    #[must_use]
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        if rules != self.rules {
+            self.rules = rules;
+            self.rescan();
+        }
+    }
+
+    /// Set `point` to `stone`, which may be [`Stone::Empty`] to remove a stone.
+    pub fn set_point(&mut self, point: Point, stone: Stone) {
+        if self.board.get(point) == Some(stone) {
+            return;
+        }
+        self.board.set_point(point, stone);
+        let through = &self.lines_through[point.to_1d(self.board.size()) as usize];
+        for &i in through {
+            let (direction, line) = &self.lines[i];
+            for (patterns, stone) in self.patterns.iter_mut().zip([Stone::Black, Stone::White]) {
+                patterns[i] = self
+                    .board
+                    .line_patterns(*direction, line, stone, self.rules, None);
+            }
+        }
+        self.conditions = [None, None];
+    }
+
+    /// Change the stones to the ones of `board`, only rescanning the lines through the points that
+    /// differ.
+    pub fn update_to(&mut self, board: &BoardArr) {
+        if board.size() != self.board.size() {
+            *self = Self::new(board.bitboard().clone(), self.rules);
+            return;
+        }
+        for marker in board.iter() {
+            self.set_point(marker.point, marker.color);
+        }
+    }
+
+    /// The conditions of `stone`, the same as [`BoardArr::conditions`] of the position.
+    pub fn conditions(&mut self, stone: Stone) -> &RenjuConditions {
+        assert!(!stone.is_empty());
+        let i = usize::from(stone.is_white());
+        let (board, patterns, rules) = (&self.board, &self.patterns[i], self.rules);
+        self.conditions[i].get_or_insert_with(|| board.combine(patterns, stone, rules))
+    }
+
+    fn rescan(&mut self) {
+        for (patterns, stone) in self.patterns.iter_mut().zip([Stone::Black, Stone::White]) {
+            *patterns = self
+                .lines
+                .iter()
+                .map(|(direction, line)| {
+                    self.board
+                        .line_patterns(*direction, line, stone, self.rules, None)
+                })
+                .collect();
+        }
+        self.conditions = [None, None];
+    }
+}
+
 impl BoardArr {
     /// A condition is a place where a stone could be placed to create a certain condition.
-    #[tracing::instrument(skip(self, stone, only_including))]
+    ///
+    /// Same as [`BoardArr::conditions`] with [`RuleSet::Renju`].
     pub fn renju_conditions(
         &self,
         stone: Stone,
         only_including: Option<&[Point]>,
     ) -> RenjuConditions {
-        static NULL_POINT: Point = Point {
-            x: 0,
-            y: 0,
-            is_null: true,
-        };
-        use S::*;
-        #[derive(Debug, Clone, Copy)]
-        pub enum S {
-            Same,
-            NotSame,
-            Empty,
-            /// A border point, which is not part of the board.
-            Border,
-        }
+        self.conditions(stone, RuleSet::Renju, only_including)
+    }
+
+    /// A condition is a place where a stone could be placed to create a certain condition, under
+    /// `rules`. Points are only forbidden if `rules` restricts `stone`, see [`RuleSet::restricts`].
+    ///
+    /// Every line of the board is scanned, use an [`Evaluator`] to keep the conditions of a
+    /// position that changes one stone at a time.
+    pub fn conditions(
+        &self,
+        stone: Stone,
+        rules: RuleSet,
+        only_including: Option<&[Point]>,
+    ) -> RenjuConditions {
+        self.bitboard().conditions(stone, rules, only_including)
+    }
+}
+
+impl Bitboard {
+    /// Same as [`BoardArr::renju_conditions`].
+    pub fn renju_conditions(
+        &self,
+        stone: Stone,
+        only_including: Option<&[Point]>,
+    ) -> RenjuConditions {
+        self.conditions(stone, RuleSet::Renju, only_including)
+    }
+
+    /// Same as [`BoardArr::conditions`].
+    #[tracing::instrument(skip(self, stone, only_including))]
+    pub fn conditions(
+        &self,
+        stone: Stone,
+        rules: RuleSet,
+        only_including: Option<&[Point]>,
+    ) -> RenjuConditions {
         assert!(!stone.is_empty());
-        let lines = self
+        let patterns = self
             .all_lines()
-            .map(|(d, i)| {
-                (
-                    d,
-                    std::iter::once([(Border, &NULL_POINT); 2])
-                        .flatten()
-                        .chain(i.map(|s| {
-                            let s = self.get_xy(s.x, s.y).expect("should be populated");
-                            if s.color.is_empty() {
-                                (Empty, &s.point)
-                            } else if s.color == stone {
-                                (Same, &s.point)
-                            } else {
-                                (NotSame, &s.point)
-                            }
-                        }))
-                        .chain(std::iter::once([(Border, &NULL_POINT); 2]).flatten())
-                        .collect::<Vec<_>>(),
-                )
+            .map(|(direction, line)| {
+                let line = line.collect::<Vec<_>>();
+                self.line_patterns(direction, &line, stone, rules, only_including)
             })
-            .collect::<Vec<(Direction, Vec<_>)>>();
-        let mut conditions = BTreeSet::new();
-        let mut forbidden = BTreeSet::new();
-
-        let mut fives = BTreeSet::new();
+            .collect::<Vec<_>>();
+        self.combine(&patterns, stone, rules)
+    }
 
-        tracing::debug!("checking fives");
-        for (dir, stone_line) in &lines {
-            for line in stone_line.windows(7) {
-                // if let Some(only) = only_including {
-                //     if !line.iter().any(|(_, p)| only.contains(p)) {
-                //         continue;
-                //     }
-                // }
-                match line {
-                    // %XXXX_%
-                    [(left, _), (Same, s0), (Same, s1), (Same, s2), (Same, s3), (Empty, s4), (right, _)] =>
+    /// The patterns of `stone` in the line of `points`, before checking the other lines.
+    ///
+    /// Fours and threes are only found in windows with a point of `only_including`.
+    fn line_patterns(
+        &self,
+        direction: Direction,
+        points: &[Point],
+        stone: Stone,
+        rules: RuleSet,
+        only_including: Option<&[Point]>,
+    ) -> LinePatterns {
+        use Cell::*;
+        let border = (Border, Point::null());
+        let line = [border; 2]
+            .into_iter()
+            .chain(points.iter().map(|point| {
+                let color = self.get(*point).expect("should be on the board");
+                if color.is_empty() {
+                    (Empty, *point)
+                } else if color == stone {
+                    (Same, *point)
+                } else {
+                    (NotSame, *point)
+                }
+            }))
+            .chain([border; 2])
+            .collect::<Vec<_>>();
+        let dir = &direction;
+        let mut patterns = LinePatterns::default();
+
+        for line in line.windows(7) {
+            match line {
+                // %XXXX_%
+                [(left, _), (Same, s0), (Same, s1), (Same, s2), (Same, s3), (Empty, s4), (right, _)] =>
+                {
+                    if !rules.allows_overline(stone)
+                        && (matches!(right, Same) || matches!(left, Same))
                     {
-                        if stone.is_black() && (matches!(right, Same) || matches!(left, Same)) {
-                            continue;
-                        }
-                        let cond = RenjuCondition::Five {
-                            direction: *dir,
-                            stones: [**s0, **s1, **s2, **s3, **s4],
-                            place: [**s4],
-                        };
-                        conditions.insert(cond);
-                        fives.insert(s4);
+                        continue;
                     }
-                    // %_XXXX%
-                    [(left, _), (Empty, s0), (Same, s1), (Same, s2), (Same, s3), (Same, s4), (right, _)] =>
+                    if rules.blocks_five() && matches!((left, right), (NotSame, NotSame)) {
+                        continue;
+                    }
+                    patterns.fives.push(RenjuCondition::Five {
+                        direction: *dir,
+                        stones: [*s0, *s1, *s2, *s3, *s4],
+                        place: [*s4],
+                    });
+                }
+                // %_XXXX%
+                [(left, _), (Empty, s0), (Same, s1), (Same, s2), (Same, s3), (Same, s4), (right, _)] =>
+                {
+                    if !rules.allows_overline(stone)
+                        && (matches!(left, Same) || matches!(right, Same))
                     {
-                        if stone.is_black() && (matches!(left, Same) || matches!(right, Same)) {
-                            continue;
-                        }
-                        let cond = RenjuCondition::Five {
-                            direction: *dir,
-                            stones: [**s0, **s1, **s2, **s3, **s4],
-                            place: [**s0],
-                        };
-                        conditions.insert(cond);
-                        fives.insert(s0);
+                        continue;
                     }
-                    _ => {}
+                    if rules.blocks_five() && matches!((left, right), (NotSame, NotSame)) {
+                        continue;
+                    }
+                    patterns.fives.push(RenjuCondition::Five {
+                        direction: *dir,
+                        stones: [*s0, *s1, *s2, *s3, *s4],
+                        place: [*s0],
+                    });
                 }
+                _ => {}
             }
         }
 
-        // First check for overlines.
-        tracing::debug!("checking overlines");
-        if stone.is_black() {
-            for (_, stone_line) in &lines {
-                for line in stone_line.windows(6) {
-                    // if let Some(only) = only_including {
-                    //     if !line.iter().any(|(_, p)| only.contains(p)) {
-                    //         continue;
-                    //     }
-                    // }
-                    match line {
-                        [(Empty, f), (Same, _), (Same, _), (Same, _), (Same, _), (Same, _)] => {
-                            forbidden.insert(**f);
-                        }
-                        [(Same, _), (Empty, f), (Same, _), (Same, _), (Same, _), (Same, _)] => {
-                            forbidden.insert(**f);
-                        }
-                        [(Same, _), (Same, _), (Empty, f), (Same, _), (Same, _), (Same, _)] => {
-                            forbidden.insert(**f);
-                        }
-                        [(Same, _), (Same, _), (Same, _), (Empty, f), (Same, _), (Same, _)] => {
-                            forbidden.insert(**f);
-                        }
-                        [(Same, _), (Same, _), (Same, _), (Same, _), (Empty, f), (Same, _)] => {
-                            forbidden.insert(**f);
-                        }
-                        [(Same, _), (Same, _), (Same, _), (Same, _), (Same, _), (Empty, f)] => {
-                            forbidden.insert(**f);
-                        }
-                        _ => {}
+        if rules.restricts(stone) {
+            for line in line.windows(6) {
+                match line {
+                    [(Empty, f), (Same, _), (Same, _), (Same, _), (Same, _), (Same, _)]
+                    | [(Same, _), (Empty, f), (Same, _), (Same, _), (Same, _), (Same, _)]
+                    | [(Same, _), (Same, _), (Empty, f), (Same, _), (Same, _), (Same, _)]
+                    | [(Same, _), (Same, _), (Same, _), (Empty, f), (Same, _), (Same, _)]
+                    | [(Same, _), (Same, _), (Same, _), (Same, _), (Empty, f), (Same, _)]
+                    | [(Same, _), (Same, _), (Same, _), (Same, _), (Same, _), (Empty, f)] => {
+                        patterns.overlines.push(*f);
                     }
+                    _ => {}
                 }
             }
         }
 
-        let mut fours = BTreeMap::new();
+        let includes = |line: &[(Cell, Point)]| {
+            only_including.is_none_or(|only| line.iter().any(|(_, p)| only.contains(p)))
+        };
 
-        tracing::debug!("checking fours");
-        for (dir, stone_line) in &lines {
-            for line in stone_line.windows(7) {
-                if let Some(only) = only_including {
-                    if !line.iter().any(|(_, p)| only.contains(p)) {
-                        continue;
+        for line in line.windows(7).filter(|line| includes(line)) {
+            match line {
+                // %._XXX%
+                // %_.XXX%
+                [(left, _), (Empty, s0), (Empty, s1), (Same, s2), (Same, s3), (Same, s4), (right, _)]
+                    if matches!(right, Empty | NotSame | Border) =>
+                {
+                    patterns.fours.push(match right {
+                        Empty => RenjuCondition::StraightFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s1],
+                        },
+                        _ => RenjuCondition::ClosedFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s1],
+                        },
+                    });
+                    if matches!(left, Empty | NotSame | Border) {
+                        patterns.fours.push(RenjuCondition::BrokenFour {
+                            direction: *dir,
+                            stones: [*s0, *s1, *s2, *s3, *s4],
+                            place: [*s0],
+                        });
                     }
                 }
-                match line {
-                    // %._XXX%
-                    // %_.XXX%
-                    [(left, _), (Empty, s0), (Empty, s1), (Same, s2), (Same, s3), (Same, s4), (right, _)]
-                        if matches!(right, Empty | NotSame | Border) =>
-                    {
-                        if !forbidden.contains(s1) {
-                            let cond = match right {
-                                Empty => RenjuCondition::StraightFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s1],
-                                },
-                                NotSame | Border => RenjuCondition::ClosedFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s1],
-                                },
-                                _ => unreachable!(),
-                            };
-                            fours.entry(s1).or_insert_with(BTreeSet::new).insert(cond);
-                        }
-                        if !forbidden.contains(s0) && matches!(left, Empty | NotSame | Border) {
-                            let cond = RenjuCondition::BrokenFour {
-                                direction: *dir,
-                                stones: [**s0, **s1, **s2, **s3, **s4],
-                                place: [**s0],
-                            };
-                            fours.entry(s0).or_insert_with(BTreeSet::new).insert(cond);
-                        }
-                    }
-                    // %XXX_.%
-                    // %XXX._%
-                    [(left, _), (Same, s1), (Same, s2), (Same, s3), (Empty, s4), (Empty, s5), (right, _)]
-                        if matches!(left, Empty | NotSame | Border) =>
-                    {
-                        if !forbidden.contains(s4) {
-                            let cond = match left {
-                                Empty => RenjuCondition::StraightFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s4],
-                                },
-                                NotSame | Border => RenjuCondition::ClosedFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s4],
-                                },
-                                _ => unreachable!(),
-                            };
-                            fours.entry(s4).or_insert_with(BTreeSet::new).insert(cond);
-                        }
-                        if !forbidden.contains(s5) && matches!(right, Empty | NotSame | Border) {
-                            let cond = RenjuCondition::BrokenFour {
-                                direction: *dir,
-                                stones: [**s1, **s2, **s3, **s4, **s5],
-                                place: [**s5],
-                            };
-                            fours.entry(s5).or_insert_with(BTreeSet::new).insert(cond);
-                        }
+                // %XXX_.%
+                // %XXX._%
+                [(left, _), (Same, s1), (Same, s2), (Same, s3), (Empty, s4), (Empty, s5), (right, _)]
+                    if matches!(left, Empty | NotSame | Border) =>
+                {
+                    patterns.fours.push(match left {
+                        Empty => RenjuCondition::StraightFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s4],
+                        },
+                        _ => RenjuCondition::ClosedFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s4],
+                        },
+                    });
+                    if matches!(right, Empty | NotSame | Border) {
+                        patterns.fours.push(RenjuCondition::BrokenFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4, *s5],
+                            place: [*s5],
+                        });
                     }
-                    // %.X_XX%
-                    // %_X.XX%
-                    [(left, _), (Empty, s0), (Same, s1), (Empty, s2), (Same, s3), (Same, s4), (right, _)]
-                        if matches!(right, Empty | NotSame | Border) =>
-                    {
-                        if !forbidden.contains(s2) {
-                            let cond = match right {
-                                Empty => RenjuCondition::StraightFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s2],
-                                },
-                                _ => RenjuCondition::ClosedFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s2],
-                                },
-                            };
-                            fours.entry(s2).or_insert_with(BTreeSet::new).insert(cond);
-                        }
-                        if !forbidden.contains(s0) && matches!(left, Empty | NotSame | Border) {
-                            let cond = RenjuCondition::BrokenFour {
-                                direction: *dir,
-                                stones: [**s0, **s1, **s2, **s3, **s4],
-                                place: [**s0],
-                            };
-                            fours.entry(s0).or_insert_with(BTreeSet::new).insert(cond);
-                        }
+                }
+                // %.X_XX%
+                // %_X.XX%
+                [(left, _), (Empty, s0), (Same, s1), (Empty, s2), (Same, s3), (Same, s4), (right, _)]
+                    if matches!(right, Empty | NotSame | Border) =>
+                {
+                    patterns.fours.push(match right {
+                        Empty => RenjuCondition::StraightFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s2],
+                        },
+                        _ => RenjuCondition::ClosedFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s2],
+                        },
+                    });
+                    if matches!(left, Empty | NotSame | Border) {
+                        patterns.fours.push(RenjuCondition::BrokenFour {
+                            direction: *dir,
+                            stones: [*s0, *s1, *s2, *s3, *s4],
+                            place: [*s0],
+                        });
                     }
-                    // %XX_X.
-                    // %XX.X_
-                    [(left, _), (Same, s1), (Same, s2), (Empty, s3), (Same, s4), (Empty, s5), (right, _)]
-                        if matches!(left, Empty | NotSame | Border) =>
-                    {
-                        if !forbidden.contains(s3) {
-                            let cond = match left {
-                                Empty => RenjuCondition::StraightFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s3],
-                                },
-                                _ => RenjuCondition::ClosedFour {
-                                    direction: *dir,
-                                    stones: [**s1, **s2, **s3, **s4],
-                                    place: [**s3],
-                                },
-                            };
-                            fours.entry(s3).or_insert_with(BTreeSet::new).insert(cond);
-                        }
-                        if !forbidden.contains(s5) && matches!(right, Empty | NotSame | Border) {
-                            let cond = RenjuCondition::BrokenFour {
-                                direction: *dir,
-                                stones: [**s1, **s2, **s3, **s4, **s5],
-                                place: [**s5],
-                            };
-                            fours.entry(s5).or_insert_with(BTreeSet::new).insert(cond);
-                        }
+                }
+                // %XX_X.
+                // %XX.X_
+                [(left, _), (Same, s1), (Same, s2), (Empty, s3), (Same, s4), (Empty, s5), (right, _)]
+                    if matches!(left, Empty | NotSame | Border) =>
+                {
+                    patterns.fours.push(match left {
+                        Empty => RenjuCondition::StraightFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s3],
+                        },
+                        _ => RenjuCondition::ClosedFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4],
+                            place: [*s3],
+                        },
+                    });
+                    if matches!(right, Empty | NotSame | Border) {
+                        patterns.fours.push(RenjuCondition::BrokenFour {
+                            direction: *dir,
+                            stones: [*s1, *s2, *s3, *s4, *s5],
+                            place: [*s5],
+                        });
                     }
-                    _ => {}
                 }
+                _ => {}
             }
         }
 
-        for (k, v) in fours {
-            if stone.is_black() && v.len() > 1 {
-                forbidden.insert(**k);
-            } else {
-                conditions.extend(v);
-            }
-        }
-
-        let mut threes = BTreeMap::new();
-
         // check for open threes, threes which can become straight fours. To do this, we need to check a huge range, 8 stones to be exact.
-        tracing::debug!("checking threes");
-        for (dir, stone_line) in &lines {
-            for line in stone_line.windows(9) {
-                if let Some(only) = only_including {
-                    if !line.iter().any(|(_, p)| only.contains(p)) {
-                        continue;
-                    }
-                }
-                match line {
-                    // %.__XX.%
-                    [(left, _), (Empty, _s1), (Empty, s2), (Empty, s3), (Same, s4), (Same, s5), (Empty, _s6), (right, _), (eh_case, _)] =>
-                    {
-                        match (left, right) {
-                            (_, Same) => {
-                                continue;
-                            }
-                            // X..xXX.%
-                            (Same, Border | NotSame | Empty) => {
-                                // there is a very special case here, if x.._xx..x, then it's not a three, since that three does not given a open four
-                                if stone.is_black() && matches!(eh_case, Same) {
-                                    continue;
-                                }
-                            }
-                            (Border | NotSame | Empty, Border | NotSame | Empty) => {
-                                if !forbidden.contains(s2)
-                                    && !fives.contains(s2)
-                                    && !fives.contains(s3)
-                                {
-                                    let cond = RenjuCondition::BrokenThree {
-                                        direction: *dir,
-                                        stones: [**s2, **s3, **s4, **s5],
-                                        place: [**s2],
-                                    };
-                                    threes
-                                        .entry(s2)
-                                        .or_insert_with(BTreeSet::new)
-                                        .insert((cond, *s3));
-                                }
-                            }
-                        }
-                        if !forbidden.contains(s3) && !fives.contains(s3) && !fives.contains(s2) {
-                            let cond = RenjuCondition::UnbrokenThree {
-                                direction: *dir,
-                                stones: [**s3, **s4, **s5],
-                                place: [**s3],
-                            };
-                            threes
-                                .entry(s3)
-                                .or_insert_with(BTreeSet::new)
-                                .insert((cond, *s2));
+        for line in line.windows(9).filter(|line| includes(line)) {
+            match line {
+                // %.__XX.%
+                [(left, _), (Empty, _s1), (Empty, s2), (Empty, s3), (Same, s4), (Same, s5), (Empty, _s6), (right, _), (eh_case, _)] =>
+                {
+                    match (left, right) {
+                        (_, Same) => {
+                            continue;
                         }
-                    }
-                    // %.XX__.%
-                    [(eh_case, _), (left, _), (Empty, _s1), (Same, s2), (Same, s3), (Empty, s4), (Empty, s5), (Empty, _s6), (right, _)] =>
-                    {
-                        match (left, right) {
-                            (Same, _) => {
+                        // X..xXX.%
+                        (Same, Border | NotSame | Empty) => {
+                            // there is a very special case here, if x.._xx..x, then it's not a three, since that three does not given a open four
+                            if !rules.allows_overline(stone) && matches!(eh_case, Same) {
                                 continue;
                             }
-                            // X..xXX.%
-                            (Border | NotSame | Empty, Same) => {
-                                // there is a very special case here, if x..xx_..x, then it's not a three, since that three does not given a open four
-                                if stone.is_black() && matches!(eh_case, Same) {
-                                    continue;
-                                }
-                            }
-                            (Border | NotSame | Empty, Border | NotSame | Empty) => {
-                                if !forbidden.contains(s5)
-                                    && !fives.contains(s5)
-                                    && !fives.contains(s4)
-                                {
-                                    let cond = RenjuCondition::BrokenThree {
-                                        direction: *dir,
-                                        stones: [**s2, **s3, **s4, **s5],
-                                        place: [**s5],
-                                    };
-                                    threes
-                                        .entry(s5)
-                                        .or_insert_with(BTreeSet::new)
-                                        .insert((cond, *s4));
-                                }
-                            }
                         }
-                        if !forbidden.contains(s4) && !fives.contains(s4) && !fives.contains(s5) {
-                            let cond = RenjuCondition::UnbrokenThree {
+                        (Border | NotSame | Empty, Border | NotSame | Empty) => {
+                            let cond = RenjuCondition::BrokenThree {
                                 direction: *dir,
-                                stones: [**s2, **s3, **s4],
-                                place: [**s4],
+                                stones: [*s2, *s3, *s4, *s5],
+                                place: [*s2],
                             };
-                            threes
-                                .entry(s4)
-                                .or_insert_with(BTreeSet::new)
-                                .insert((cond, *s5));
+                            patterns.threes.push((cond, *s3));
                         }
                     }
-
-                    // %._X_X.%
-                    [(left, _s0), (Empty, _s1), (Empty, s2), (Same, s3), (Empty, s4), (Same, s5), (Empty, _s6), (right, _s7), ..] =>
-                    {
-                        match (left, right) {
-                            (_, Same) => {
+                    let cond = RenjuCondition::UnbrokenThree {
+                        direction: *dir,
+                        stones: [*s3, *s4, *s5],
+                        place: [*s3],
+                    };
+                    patterns.threes.push((cond, *s2));
+                }
+                // %.XX__.%
+                [(eh_case, _), (left, _), (Empty, _s1), (Same, s2), (Same, s3), (Empty, s4), (Empty, s5), (Empty, _s6), (right, _)] =>
+                {
+                    match (left, right) {
+                        (Same, _) => {
+                            continue;
+                        }
+                        // X..xXX.%
+                        (Border | NotSame | Empty, Same) => {
+                            // there is a very special case here, if x..xx_..x, then it's not a three, since that three does not given a open four
+                            if !rules.allows_overline(stone) && matches!(eh_case, Same) {
                                 continue;
                             }
-                            (Same, Border | NotSame | Empty) => {}
-                            (Border | NotSame | Empty, Border | NotSame | Empty) => {
-                                if !forbidden.contains(s2)
-                                    && !fives.contains(s2)
-                                    && !fives.contains(s4)
-                                {
-                                    let cond = RenjuCondition::BrokenThree {
-                                        direction: *dir,
-                                        stones: [**s2, **s3, **s4, **s5],
-                                        place: [**s2],
-                                    };
-                                    threes
-                                        .entry(s2)
-                                        .or_insert_with(BTreeSet::new)
-                                        .insert((cond, *s4));
-                                }
-                            }
                         }
-                        if !forbidden.contains(s4) && !fives.contains(s4) && !fives.contains(s2) {
-                            let cond = RenjuCondition::UnbrokenThree {
+                        (Border | NotSame | Empty, Border | NotSame | Empty) => {
+                            let cond = RenjuCondition::BrokenThree {
                                 direction: *dir,
-                                stones: [**s3, **s4, **s5],
-                                place: [**s4],
+                                stones: [*s2, *s3, *s4, *s5],
+                                place: [*s5],
                             };
-                            threes
-                                .entry(s4)
-                                .or_insert_with(BTreeSet::new)
-                                .insert((cond, *s2));
+                            patterns.threes.push((cond, *s4));
                         }
                     }
+                    let cond = RenjuCondition::UnbrokenThree {
+                        direction: *dir,
+                        stones: [*s2, *s3, *s4],
+                        place: [*s4],
+                    };
+                    patterns.threes.push((cond, *s5));
+                }
 
-                    // %.X_X_.%
-                    [(left, _s0), (Empty, _s1), (Same, s2), (Empty, s3), (Same, s4), (Empty, s5), (Empty, _s6), (right, _s7), ..] =>
-                    {
-                        match (left, right) {
-                            (Same, _) => {
-                                continue;
-                            }
-                            (Border | NotSame | Empty, Same) => {}
-                            (Border | NotSame | Empty, Border | NotSame | Empty) => {
-                                if !forbidden.contains(s5)
-                                    && !fives.contains(s5)
-                                    && !fives.contains(s4)
-                                {
-                                    let cond = RenjuCondition::BrokenThree {
-                                        direction: *dir,
-                                        stones: [**s2, **s3, **s4, **s5],
-                                        place: [**s5],
-                                    };
-                                    threes
-                                        .entry(s5)
-                                        .or_insert_with(BTreeSet::new)
-                                        .insert((cond, *s4));
-                                }
-                            }
+                // %._X_X.%
+                [(left, _s0), (Empty, _s1), (Empty, s2), (Same, s3), (Empty, s4), (Same, s5), (Empty, _s6), (right, _s7), ..] =>
+                {
+                    match (left, right) {
+                        (_, Same) => {
+                            continue;
                         }
-                        if !forbidden.contains(s3) && !fives.contains(s3) && !fives.contains(s5) {
-                            let cond = RenjuCondition::UnbrokenThree {
+                        (Same, Border | NotSame | Empty) => {}
+                        (Border | NotSame | Empty, Border | NotSame | Empty) => {
+                            let cond = RenjuCondition::BrokenThree {
                                 direction: *dir,
-                                stones: [**s2, **s3, **s4],
-                                place: [**s3],
+                                stones: [*s2, *s3, *s4, *s5],
+                                place: [*s2],
                             };
-                            threes
-                                .entry(s3)
-                                .or_insert_with(BTreeSet::new)
-                                .insert((cond, *s5));
+                            patterns.threes.push((cond, *s4));
                         }
                     }
-                    // %.X__X.%
-                    [(Border | NotSame | Empty, _s1), (Empty, _s2), (Same, s3), (Empty, s4), (Empty, s5), (Same, s6), (Empty, _s7), (Border | NotSame | Empty, _s8), ..] =>
-                    {
-                        if !forbidden.contains(s4) && !fives.contains(s4) && !fives.contains(s5) {
-                            let cond = RenjuCondition::BrokenThree {
-                                direction: *dir,
-                                stones: [**s3, **s4, **s5, **s6],
-                                place: [**s4],
-                            };
-                            threes
-                                .entry(s4)
-                                .or_insert_with(BTreeSet::new)
-                                .insert((cond, *s5));
+                    let cond = RenjuCondition::UnbrokenThree {
+                        direction: *dir,
+                        stones: [*s3, *s4, *s5],
+                        place: [*s4],
+                    };
+                    patterns.threes.push((cond, *s2));
+                }
+
+                // %.X_X_.%
+                [(left, _s0), (Empty, _s1), (Same, s2), (Empty, s3), (Same, s4), (Empty, s5), (Empty, _s6), (right, _s7), ..] =>
+                {
+                    match (left, right) {
+                        (Same, _) => {
+                            continue;
                         }
-                        if !forbidden.contains(s5) && !fives.contains(s5) && !fives.contains(s4) {
+                        (Border | NotSame | Empty, Same) => {}
+                        (Border | NotSame | Empty, Border | NotSame | Empty) => {
                             let cond = RenjuCondition::BrokenThree {
                                 direction: *dir,
-                                stones: [**s3, **s4, **s5, **s6],
-                                place: [**s5],
+                                stones: [*s2, *s3, *s4, *s5],
+                                place: [*s5],
                             };
-                            threes
-                                .entry(s5)
-                                .or_insert_with(BTreeSet::new)
-                                .insert((cond, *s4));
+                            patterns.threes.push((cond, *s4));
                         }
                     }
-                    _ => {}
+                    let cond = RenjuCondition::UnbrokenThree {
+                        direction: *dir,
+                        stones: [*s2, *s3, *s4],
+                        place: [*s3],
+                    };
+                    patterns.threes.push((cond, *s5));
                 }
+                // %.X__X.%
+                [(Border | NotSame | Empty, _s1), (Empty, _s2), (Same, s3), (Empty, s4), (Empty, s5), (Same, s6), (Empty, _s7), (Border | NotSame | Empty, _s8), ..] =>
+                {
+                    let cond = RenjuCondition::BrokenThree {
+                        direction: *dir,
+                        stones: [*s3, *s4, *s5, *s6],
+                        place: [*s4],
+                    };
+                    patterns.threes.push((cond, *s5));
+                    let cond = RenjuCondition::BrokenThree {
+                        direction: *dir,
+                        stones: [*s3, *s4, *s5, *s6],
+                        place: [*s5],
+                    };
+                    patterns.threes.push((cond, *s4));
+                }
+                _ => {}
+            }
+        }
+        patterns
+    }
+
+    /// Put together the patterns of every line, finding the points where they meet.
+    fn combine(&self, lines: &[LinePatterns], stone: Stone, rules: RuleSet) -> RenjuConditions {
+        let mut conditions = BTreeSet::new();
+        let mut forbidden = BTreeSet::new();
+        let mut fives = BTreeSet::new();
+
+        tracing::debug!("checking fives and overlines");
+        for patterns in lines {
+            for five in &patterns.fives {
+                fives.insert(*five.place());
+                conditions.insert(five.clone());
+            }
+            forbidden.extend(patterns.overlines.iter().copied());
+        }
+
+        tracing::debug!("checking fours");
+        let mut fours = BTreeMap::new();
+        for four in lines.iter().flat_map(|patterns| &patterns.fours) {
+            if !forbidden.contains(four.place()) {
+                fours
+                    .entry(*four.place())
+                    .or_insert_with(BTreeSet::new)
+                    .insert(four.clone());
+            }
+        }
+        for (k, v) in fours {
+            if rules.restricts(stone) && v.len() > 1 {
+                forbidden.insert(k);
+            } else {
+                conditions.extend(v);
+            }
+        }
+
+        tracing::debug!("checking threes");
+        let mut threes = BTreeMap::new();
+        for (three, four_point) in lines.iter().flat_map(|patterns| &patterns.threes) {
+            let place = three.place();
+            if !forbidden.contains(place) && !fives.contains(place) && !fives.contains(four_point) {
+                threes
+                    .entry(*place)
+                    .or_insert_with(BTreeSet::new)
+                    .insert((three.clone(), *four_point));
             }
         }
         let mut found_forbidden_threes = BTreeSet::new();
@@ -649,7 +745,7 @@ impl BoardArr {
             let _enter = span.enter();
             // we need to check the unique conditions, based on the row, not the point which could be a four.
             let three_row: BTreeSet<&RenjuCondition> = v.iter().map(|(c, _)| c).collect();
-            if stone.is_black() && three_row.len() > 1 {
+            if rules.restricts(stone) && three_row.len() > 1 {
                 tracing::debug!(?k, ?v, "is k forbidden?");
 
                 // 9.3 A black double-three is allowed if at least one of the following conditions  a) or b) is/are fulfilled:
@@ -687,7 +783,7 @@ impl BoardArr {
                     let mut allowed_threes = v.len();
                     // Check for double-three, case b).
                     let mut new_board = self.clone();
-                    new_board.set_point(***k, stone);
+                    new_board.set_point(*k, stone);
                     tracing::debug!("new board\n: {new_board}");
                     tracing::debug!(stones_added = ?[&k,], "adding stones to board to check for double-three.");
                     for (_c, four_point) in v.iter() {
@@ -698,7 +794,7 @@ impl BoardArr {
                         // TODO
                         tracing::debug!("checking if the four_point is forbidden double-three");
                         let new_conditions =
-                            new_board.renju_conditions(stone, Some(&[***k, **four_point]));
+                            new_board.conditions(stone, rules, Some(&[*k, *four_point]));
 
                         tracing::debug!("checked if the four_point is forbidden double-three");
                         // Now, check condition if more than one allowed straight four can be  made
@@ -707,7 +803,7 @@ impl BoardArr {
                             .iter()
                             .filter(|c| {
                                 matches!(c, RenjuCondition::StraightFour { .. })
-                                    && c.stones().contains(**k)
+                                    && c.stones().contains(k)
                             })
                             .filter(|c| !new_conditions.forbidden.contains(c.place()))
                             .count()
@@ -729,7 +825,7 @@ impl BoardArr {
                             ?allowed_threes,
                             "b) is not fulfilled, the double-three on {k:?} is forbidden."
                         );
-                        found_forbidden_threes.insert(**k);
+                        found_forbidden_threes.insert(*k);
                     } else {
                         tracing::debug!(?k, "b) is fulfilled, the double-three is allowed.");
                     }
@@ -742,21 +838,14 @@ impl BoardArr {
         }
         forbidden.extend(found_forbidden_threes.clone());
 
-        if stone.is_white() {
-            assert!(forbidden.is_empty());
-        }
-
-        if stone.is_white() {
+        if !rules.restricts(stone) {
             assert!(forbidden.is_empty());
         }
 
         RenjuConditions {
             conditions,
             forbidden,
-            threes: threes
-                .into_iter()
-                .flat_map(|(_k, v)| v.into_iter().map(|(c, p)| (c, *p)))
-                .collect(),
+            threes: threes.into_values().flatten().collect(),
         }
     }
 
@@ -861,6 +950,7 @@ impl BoardArr {
                 Point::new(point.x - steps, point.y - steps)
             }
         };
+        let size = self.size();
         let mut count = 0;
         (
             idx as usize,
@@ -876,7 +966,7 @@ impl BoardArr {
                     }
                 };
                 count += 1;
-                if next.is_valid() {
+                if next.is_valid(size) {
                     Some(next)
                 } else {
                     None
@@ -900,7 +990,7 @@ mod tests {
     fn test_condition() {
         let mut board = BoardArr::new(15);
         for pos in p![[H, 8], [G, 8], [G, 9], [H, 10]] {
-            board.set_point(pos, Stone::Black);
+            board.set_point(pos, Stone::Black).unwrap();
         }
         let conditions = board.renju_conditions(Stone::Black, None);
         for forbidden in &conditions.forbidden {
@@ -937,10 +1027,10 @@ mod tests {
             [I, 2],
             [J, 2]
         ] {
-            board.set_point(pos, Stone::Black);
+            board.set_point(pos, Stone::Black).unwrap();
         }
 
-        board.set_point(p![B, 13], Stone::White);
+        board.set_point(p![B, 13], Stone::White).unwrap();
 
         let conditions = board.renju_conditions(Stone::Black, None);
         for forbidden in &conditions.forbidden {
@@ -981,10 +1071,10 @@ mod tests {
             [L, 3],
             [D, 2]
         ] {
-            board.set_point(pos, Stone::Black);
+            board.set_point(pos, Stone::Black).unwrap();
         }
 
-        board.set_point(p![E, 10], Stone::White);
+        board.set_point(p![E, 10], Stone::White).unwrap();
 
         let conditions = board.renju_conditions(Stone::Black, None);
         for forbidden in &conditions.forbidden {
@@ -1019,11 +1109,11 @@ mod tests {
             [F, 4],
             [E, 3]
         ] {
-            board.set_point(pos, Stone::Black);
+            board.set_point(pos, Stone::Black).unwrap();
         }
 
         for pos in p![[I, 8], [G, 1], [N, 8]] {
-            board.set_point(pos, Stone::White);
+            board.set_point(pos, Stone::White).unwrap();
         }
 
         let conditions = board.renju_conditions(Stone::Black, None);
@@ -1055,10 +1145,10 @@ mod tests {
             [N, 8],
             [F, 8]
         ] {
-            board.set_point(pos, Stone::Black);
+            board.set_point(pos, Stone::Black).unwrap();
         }
         for pos in p![[J, 7], [G, 7]] {
-            board.set_point(pos, Stone::White);
+            board.set_point(pos, Stone::White).unwrap();
         }
         let conditions = board.renju_conditions(Stone::Black, None);
         for forbidden in &conditions.forbidden {
@@ -1097,7 +1187,7 @@ mod tests {
             [I, 11],
             [E, 12],
         ] {
-            board.set_point(pos, stone);
+            board.set_point(pos, stone).unwrap();
             stone = stone.opposite();
         }
 
@@ -1140,7 +1230,7 @@ mod tests {
             [L, 12],
             [M, 11]
         ] {
-            board.set_point(p, Stone::Black);
+            board.set_point(p, Stone::Black).unwrap();
         }
         tracing::info!("board \n{}", board);
 
@@ -1163,7 +1253,7 @@ mod tests {
     #[test]
     #[rustfmt::skip]
     fn line() {
-        let board = BoardArr::new(15);
+        let board = Bitboard::new(15);
 
         let p = p![H, 8];
 
@@ -1254,10 +1344,16 @@ mod tests {
 
     #[test]
     fn all_lines_is_all_lines_and_not_twice() {
-        let board = BoardArr::new(15);
+        for size in [15, 19, 20] {
+            all_lines_of_size(size);
+        }
+    }
+
+    fn all_lines_of_size(size: u32) {
+        let board = BoardArr::new(size);
         let mut all_lines = BTreeMap::new();
 
-        for (dir, iter) in board.all_lines() {
+        for (dir, iter) in Bitboard::new(size).all_lines() {
             all_lines.entry(dir).or_insert(vec![]).extend(iter);
         }
         for (dir, points) in all_lines {
@@ -1267,9 +1363,9 @@ mod tests {
                 board.retain(|i| i.point != p);
                 *found.entry(p).or_insert(0) += 1;
             }
-            let mut disp_board = BoardArr::new(15);
+            let mut disp_board = BoardArr::new(size);
             for p in &board {
-                disp_board.set_point(p.point, Stone::Black);
+                disp_board.set_point(p.point, Stone::Black).unwrap();
             }
             assert!(
                 board.is_empty(),
@@ -1284,11 +1380,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn forbidden_on_larger_board() {
+        // same shape as in `test_condition`, moved past the edge of a 15x15 board
+        let shift = |p: Point| Point::new(p.x + 9, p.y + 9);
+        let mut board = BoardArr::new(19);
+        for pos in p![[H, 8], [G, 8], [G, 9], [H, 10]] {
+            board.set_point(shift(pos), Stone::Black).unwrap();
+        }
+        let conditions = board.renju_conditions(Stone::Black, None);
+        assert_eq!(
+            conditions.forbidden,
+            [shift(p![F, 8])].into_iter().collect()
+        );
+    }
+
     #[test]
     fn check_if_illegal_move() {
         let mut board = BoardArr::new(15);
         for pos in p![[H, 8], [G, 8], [G, 9], [H, 10]] {
-            board.set_point(pos, Stone::Black);
+            board.set_point(pos, Stone::Black).unwrap();
         }
 
         let conditions = board.renju_conditions(Stone::Black, None);
@@ -1308,12 +1419,12 @@ mod tests {
         let y = 7u32;
         let p1 = BoardMarker::new(Point::new(4, y), Stone::Black);
         for x in 0..4 {
-            board.set_point(Point::new(x, y), Stone::Black);
+            board.set_point(Point::new(x, y), Stone::Black).unwrap();
         }
 
         let p2 = BoardMarker::new(Point::new(8, y + 2), Stone::White);
         for x in (7..12).filter(|x| *x != 8) {
-            board.set_point(Point::new(x, y + 2), Stone::White);
+            board.set_point(Point::new(x, y + 2), Stone::White).unwrap();
         }
         tracing::info!("\n{}\nChecks,{:?} and {:?}", board, &p1, p2);
         //assert!(is_five_dir(&board, &p1, Direction::Horizontal).unwrap());
@@ -1328,12 +1439,12 @@ mod tests {
         let x = 7u32;
         let p1 = BoardMarker::new(Point::new(x, 4), Stone::Black);
         for y in 0..4 {
-            board.set_point(Point::new(x, y), Stone::Black);
+            board.set_point(Point::new(x, y), Stone::Black).unwrap();
         }
 
         let p2 = BoardMarker::new(Point::new(x + 2, 8), Stone::White);
         for y in (7..12).filter(|y| *y != 8) {
-            board.set_point(Point::new(x + 2, y), Stone::White);
+            board.set_point(Point::new(x + 2, y), Stone::White).unwrap();
         }
         tracing::info!("\n{}\nChecks; {:?} and {:?}", board, &p1, p2);
 
@@ -1348,7 +1459,9 @@ mod tests {
         let mut board = BoardArr::new(15);
         // A diagonal is '\'
         for pos in &[2u32 + 7 * 15, 3u32 + 8 * 15, 4u32 + 9 * 15, 5u32 + 10 * 15] {
-            board.set_point(Point::from_1d(*pos, 15), Stone::Black);
+            board
+                .set_point(Point::from_1d(*pos, 15), Stone::Black)
+                .unwrap();
         }
         #[allow(clippy::identity_op)]
         for pos in &[
@@ -1357,7 +1470,9 @@ mod tests {
             11u32 + 2 * 15,
             13u32 + 4 * 15,
         ] {
-            board.set_point(Point::from_1d(*pos, 15), Stone::White);
+            board
+                .set_point(Point::from_1d(*pos, 15), Stone::White)
+                .unwrap();
         }
 
         let p1 = BoardMarker::new(Point::from_1d(11 * 15 + 6, 15), Stone::Black);
@@ -1374,7 +1489,9 @@ mod tests {
     fn is_anti_diagonal_five_in_a_row() {
         let mut board = BoardArr::new(15);
         for pos in &[6u32 + 6 * 15, 5u32 + 7 * 15, 4u32 + 8 * 15, 3u32 + 9 * 15] {
-            board.set_point(Point::from_1d(*pos, 15), Stone::Black);
+            board
+                .set_point(Point::from_1d(*pos, 15), Stone::Black)
+                .unwrap();
         }
 
         let p1 = BoardMarker::new(Point::from_1d(2u32 + 10 * 15, 15), Stone::Black);
@@ -1383,4 +1500,92 @@ mod tests {
         //assert!(is_five_dir(&board, &p1, Direction::AntiDiagonal).unwrap());
         //assert_eq!(is_line(&board, &p1), Ok(Direction::AntiDiagonal));
     }
+    mod incremental {
+        use super::*;
+        use crate::board::rules::RuleSet;
+        use proptest::prelude::*;
+        use test_log::test;
+
+        /// A board size and stones to place on it, [`Stone::Empty`] removes a stone.
+        fn changes() -> impl Strategy<Value = (u32, Vec<(Point, Stone)>)> {
+            prop::sample::select(vec![9u32, 15]).prop_flat_map(|size| {
+                let change = (0..size, 0..size, prop::option::weighted(0.8, any::<bool>()))
+                    .prop_map(|(x, y, stone)| {
+                        (
+                            Point::new(x, y),
+                            stone.map_or(Stone::Empty, Stone::from_bool),
+                        )
+                    });
+                (Just(size), prop::collection::vec(change, 1..80))
+            })
+        }
+
+        fn rules() -> impl Strategy<Value = RuleSet> {
+            prop::sample::select(RuleSet::ALL.to_vec())
+        }
+
+        fn assert_same_as_rescan(evaluator: &mut Evaluator) -> Result<(), TestCaseError> {
+            for stone in [Stone::Black, Stone::White] {
+                let expected = evaluator.board().conditions(stone, evaluator.rules(), None);
+                prop_assert_eq!(evaluator.conditions(stone), &expected);
+            }
+            Ok(())
+        }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(48))]
+
+            #[test]
+            fn same_as_rescan((size, changes) in changes(), rules in rules()) {
+                let mut evaluator = Evaluator::new(Bitboard::new(size), rules);
+                for (i, (point, stone)) in changes.iter().enumerate() {
+                    evaluator.set_point(*point, *stone);
+                    // a full rescan is slow, so only compare after some of the changes
+                    if i % 8 == 7 || i + 1 == changes.len() {
+                        assert_same_as_rescan(&mut evaluator)?;
+                    }
+                }
+            }
+
+            #[test]
+            fn update_to(
+                (size, first) in changes(),
+                (_, second) in changes(),
+                rules in rules(),
+                other_rules in rules(),
+            ) {
+                let mut board = BoardArr::new(size);
+                for (point, stone) in &first {
+                    board.set_point(*point, *stone).unwrap();
+                }
+                let mut evaluator = Evaluator::new(Bitboard::from(&board), rules);
+                for (point, stone) in &second {
+                    if point.is_valid(size) {
+                        board.set_point(*point, *stone).unwrap();
+                    }
+                }
+                evaluator.update_to(&board);
+                prop_assert_eq!(evaluator.board().zobrist(), board.zobrist());
+                assert_same_as_rescan(&mut evaluator)?;
+                evaluator.set_rules(other_rules);
+                assert_same_as_rescan(&mut evaluator)?;
+            }
+        }
+
+        #[test]
+        fn size_change() {
+            let mut evaluator = Evaluator::new(Bitboard::new(15), RuleSet::Renju);
+            let mut board = BoardArr::new(9);
+            for point in [Point::new(3, 4), Point::new(4, 4)] {
+                board.set_point(point, Stone::Black).unwrap();
+            }
+            evaluator.update_to(&board);
+            assert_eq!(evaluator.board().size(), 9);
+            assert!(!evaluator.conditions(Stone::Black).threes.is_empty());
+            assert_eq!(
+                evaluator.conditions(Stone::Black),
+                &board.conditions(Stone::Black, RuleSet::Renju, None)
+            );
+        }
+    }
 }
@@ -0,0 +1,758 @@
+//! Undo and redo for edits of a [`Board`].
+//!
+//! Every change is an [`Edit`], which gives back the edit that undoes it when it's made. Removing
+//! moves from the tree renumbers others, see [`Renumbered`](super::Renumbered), so edits find
+//! their moves by path from the root instead of by [`MoveIndex`], see [`Board::path_to`].
+//!
+//! A [`History`] keeps the edits that undo the latest changes, and the ones that redo what was
+//! undone. Edits made between [`History::begin_group`] and [`History::end_group`] are undone
+//! together.
+use super::{Board, BoardMarker, EditError, MoveIndex};
+
+/// A move and the moves played after it, as taken out of the tree by [`Edit::RemoveSubtree`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtree {
+    pub marker: BoardMarker,
+    /// The moves played after this one, in order.
+    pub children: Vec<Subtree>,
+}
+
+impl Subtree {
+    /// Copy `node` and the moves after it out of `board`.
+    #[must_use]
+    pub fn from_board(board: &Board, node: MoveIndex) -> Option<Self> {
+        Some(Self {
+            marker: board.get_move(node)?.clone(),
+            children: board
+                .get_move_children(&node)
+                .into_iter()
+                .filter_map(|child| Self::from_board(board, child))
+                .collect(),
+        })
+    }
+
+    /// Play the moves as the last move from `parent`. Returns the index of the first move.
+    pub fn insert(self, board: &mut Board, parent: MoveIndex) -> MoveIndex {
+        let node = board.insert_move(parent, self.marker);
+        for child in self.children {
+            child.insert(board, node);
+        }
+        node
+    }
+}
+
+/// A change of a [`Board`]. Moves are found by their path from the root, see [`Board::path_to`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// Play `marker` from `parent`, after the moves that are already played from it.
+    AddMove {
+        parent: Vec<usize>,
+        marker: BoardMarker,
+    },
+    /// Remove a move and every move played after it, see [`Board::remove_subtree`].
+    RemoveSubtree { node: Vec<usize> },
+    /// Put back moves taken out by [`Edit::RemoveSubtree`] at `position` among the moves played
+    /// from `parent`, with the links to and from them.
+    Restore {
+        parent: Vec<usize>,
+        position: usize,
+        subtree: Subtree,
+        links: Vec<(Vec<usize>, Vec<usize>)>,
+    },
+    /// Change the marker of a move, its comments or its point.
+    SetMarker {
+        node: Vec<usize>,
+        marker: BoardMarker,
+    },
+    /// Put the move at `order[i]` among the moves played from `parent` at `i`, see
+    /// [`Board::reorder_children`].
+    Reorder {
+        parent: Vec<usize>,
+        order: Vec<usize>,
+    },
+    /// Move a move and the moves after it to `position` among the moves played from `new_parent`,
+    /// see [`Board::move_subtree`].
+    MoveSubtree {
+        node: Vec<usize>,
+        new_parent: Vec<usize>,
+        position: usize,
+    },
+    /// Link two moves, see [`Board::add_edge`].
+    Link { from: Vec<usize>, to: Vec<usize> },
+    /// Remove a link, see [`Board::remove_link`].
+    Unlink { from: Vec<usize>, to: Vec<usize> },
+}
+
+impl Edit {
+    /// Make the change to `board`.
+    ///
+    /// Returns the edit that undoes it. Nothing is changed if an error is returned.
+    pub fn apply(self, board: &mut Board) -> Result<Edit, EditError> {
+        let find = |board: &Board, path: &[usize]| {
+            board
+                .find_path(path)
+                .ok_or_else(|| EditError::NoPath(path.to_vec()))
+        };
+        match self {
+            Edit::AddMove { parent, marker } => {
+                let node = find(board, &parent)?;
+                board.insert_move(node, marker);
+                let position = board.get_move_children(&node).len() - 1;
+                Ok(Edit::RemoveSubtree {
+                    node: child(&parent, position),
+                })
+            }
+            Edit::RemoveSubtree { node } => {
+                let index = find(board, &node)?;
+                let (&position, parent) = node.split_last().ok_or(EditError::Root(index))?;
+                let subtree = Subtree::from_board(board, index).expect("the move was found");
+                let mut links = Vec::new();
+                for (from, to) in board.links() {
+                    let (from, to) = (board.path_to(from)?, board.path_to(to)?);
+                    if from.starts_with(&node) || to.starts_with(&node) {
+                        links.push((from, to));
+                    }
+                }
+                board.remove_subtree(index)?;
+                Ok(Edit::Restore {
+                    parent: parent.to_vec(),
+                    position,
+                    subtree,
+                    links,
+                })
+            }
+            Edit::Restore {
+                parent,
+                position,
+                subtree,
+                links,
+            } => {
+                let node = find(board, &parent)?;
+                let count = board.get_move_children(&node).len();
+                let (placed, last) = (child(&parent, position.min(count)), child(&parent, count));
+                // the paths of the links are only there once the moves are back, they are added
+                // while the moves are still the last ones, which are taken off again if one fails
+                let (nodes, edges) = (board.graph.node_count(), board.graph.edge_count());
+                subtree.insert(board, node);
+                let find_placed = |board: &Board, path: &[usize]| {
+                    board
+                        .find_path(&before_placing(path, &placed, &last))
+                        .ok_or_else(|| EditError::NoPath(path.to_vec()))
+                };
+                let linked = links.into_iter().try_for_each(|(from, to)| {
+                    let (from, to) = (find_placed(board, &from)?, find_placed(board, &to)?);
+                    board
+                        .add_edge(&from, &to)
+                        .map_err(|_| EditError::WouldCycle(from, to))
+                });
+                if let Err(error) = linked {
+                    board.truncate(nodes, edges);
+                    return Err(error);
+                }
+                let position = place_last(board, node, position)?;
+                Ok(Edit::RemoveSubtree {
+                    node: child(&parent, position),
+                })
+            }
+            Edit::SetMarker { node, marker } => {
+                let index = find(board, &node)?;
+                let marker = board.set_move(index, marker)?;
+                Ok(Edit::SetMarker { node, marker })
+            }
+            Edit::Reorder { parent, order } => {
+                let node = find(board, &parent)?;
+                let children = board.get_move_children(&node);
+                let reordered = order
+                    .iter()
+                    .map(|position| children.get(*position).copied())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(EditError::NotAPermutation(node))?;
+                board.reorder_children(node, &reordered)?;
+                let mut inverse = vec![0; order.len()];
+                for (position, old) in order.into_iter().enumerate() {
+                    inverse[old] = position;
+                }
+                Ok(Edit::Reorder {
+                    parent,
+                    order: inverse,
+                })
+            }
+            Edit::MoveSubtree {
+                node,
+                new_parent,
+                position,
+            } => {
+                let index = find(board, &node)?;
+                let (&old_position, old_parent) =
+                    node.split_last().ok_or(EditError::Root(index))?;
+                let target = find(board, &new_parent)?;
+                let moved = board.move_subtree(index, target)?;
+                let new_parent = after_removal(&new_parent, &node);
+                let parent = board
+                    .get_parent_strong(&moved)
+                    .expect("the move was just moved");
+                let position = place_last(board, parent, position)?;
+                let node = child(&new_parent, position);
+                Ok(Edit::MoveSubtree {
+                    new_parent: after_insertion(old_parent, &node),
+                    node,
+                    position: old_position,
+                })
+            }
+            Edit::Link { from, to } => {
+                let (from_index, to_index) = (find(board, &from)?, find(board, &to)?);
+                if board.links().contains(&(from_index, to_index)) {
+                    return Err(EditError::AlreadyLinked(from_index, to_index));
+                }
+                board
+                    .add_edge(&from_index, &to_index)
+                    .map_err(|_| EditError::WouldCycle(from_index, to_index))?;
+                Ok(Edit::Unlink { from, to })
+            }
+            Edit::Unlink { from, to } => {
+                board.remove_link(find(board, &from)?, find(board, &to)?)?;
+                Ok(Edit::Link { from, to })
+            }
+        }
+    }
+}
+
+fn child(parent: &[usize], position: usize) -> Vec<usize> {
+    let mut path = parent.to_vec();
+    path.push(position);
+    path
+}
+
+/// Move the last move played from `parent` to `position`, or leave it last if `position` is past
+/// the end. Returns where it ends up.
+fn place_last(board: &mut Board, parent: MoveIndex, position: usize) -> Result<usize, EditError> {
+    let mut children = board.get_move_children(&parent);
+    let last = children.pop().expect("a move was just added");
+    let position = position.min(children.len());
+    if position < children.len() {
+        children.insert(position, last);
+        board.reorder_children(parent, &children)?;
+    }
+    Ok(position)
+}
+
+/// Where the move at `path` is after the move at `removed` is taken out of the tree.
+fn after_removal(path: &[usize], removed: &[usize]) -> Vec<usize> {
+    let mut path = path.to_vec();
+    if let Some((&position, parent)) = removed.split_last() {
+        if path.len() > parent.len() && path.starts_with(parent) && path[parent.len()] > position {
+            path[parent.len()] -= 1;
+        }
+    }
+    path
+}
+
+/// Where the move at `path` is while the move that goes at `placed` is still at `last`, the last
+/// move played from the same parent.
+fn before_placing(path: &[usize], placed: &[usize], last: &[usize]) -> Vec<usize> {
+    match path.strip_prefix(placed) {
+        Some(rest) => [last, rest].concat(),
+        None => after_removal(path, placed),
+    }
+}
+
+/// Where the move at `path` is after a move is put in at `inserted`.
+fn after_insertion(path: &[usize], inserted: &[usize]) -> Vec<usize> {
+    let mut path = path.to_vec();
+    if let Some((&position, parent)) = inserted.split_last() {
+        if path.len() > parent.len() && path.starts_with(parent) && path[parent.len()] >= position {
+            path[parent.len()] += 1;
+        }
+    }
+    path
+}
+
+/// Edits undone or redone at once.
+#[derive(Clone, Debug)]
+struct Step {
+    /// The edits that undo or redo the step, in the order they were made, so they are made in
+    /// reverse.
+    edits: Vec<Edit>,
+    /// The current move before the step was made.
+    current: Vec<usize>,
+}
+
+impl Step {
+    /// Make the edits, returning the step that goes back.
+    ///
+    /// If one of them fails, the ones made before it are undone by the edits they gave back, so
+    /// `board` is left as it was.
+    fn replay(self, board: &mut Board) -> Result<Step, EditError> {
+        let current = current_path(board);
+        let mut edits = Vec::with_capacity(self.edits.len());
+        for edit in self.edits.into_iter().rev() {
+            match edit.apply(board) {
+                Ok(inverse) => edits.push(inverse),
+                Err(error) => {
+                    for inverse in edits.into_iter().rev() {
+                        inverse
+                            .apply(board)
+                            .expect("an edit that was just made can be undone");
+                    }
+                    if let Some(node) = board.find_path(&current) {
+                        board.set_current_move(node)?;
+                    }
+                    return Err(error);
+                }
+            }
+        }
+        if let Some(node) = board.find_path(&self.current) {
+            board.set_current_move(node)?;
+        }
+        Ok(Step { edits, current })
+    }
+}
+
+fn current_path(board: &Board) -> Vec<usize> {
+    board.path_to(board.current_move()).unwrap_or_default()
+}
+
+/// The edits that undo and redo the latest changes of a [`Board`].
+///
+/// Every change of the board has to be made through the history, changes made around it make the
+/// paths of the edits wrong. [`History::clear`] it when the board is changed another way.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    /// The step edits are added to until the outermost group ends.
+    group: Option<Step>,
+    depth: usize,
+    /// The last step can take more edits, see [`History::apply_merged`].
+    mergeable: bool,
+}
+
+impl History {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `edit` to `board` and remember how to undo it.
+    ///
+    /// Nothing is remembered if an error is returned. Anything that was undone can't be redone
+    /// any more.
+    pub fn apply(&mut self, board: &mut Board, edit: Edit) -> Result<(), EditError> {
+        let current = current_path(board);
+        let inverse = edit.apply(board)?;
+        self.redo.clear();
+        self.mergeable = false;
+        match &mut self.group {
+            Some(group) => group.edits.push(inverse),
+            None => self.undo.push(Step {
+                edits: vec![inverse],
+                current,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Like [`History::apply`], but a [`Edit::SetMarker`] of the same move as the last change,
+    /// which was also made with this, is undone together with it. Typing a comment is undone at
+    /// once this way instead of a letter at a time.
+    pub fn apply_merged(&mut self, board: &mut Board, edit: Edit) -> Result<(), EditError> {
+        if let (Edit::SetMarker { node, .. }, None, true) = (&edit, &self.group, self.mergeable) {
+            let last = self.undo.last().map(|step| step.edits.as_slice());
+            if let Some([Edit::SetMarker { node: last, .. }]) = last {
+                if last == node {
+                    edit.apply(board)?;
+                    self.redo.clear();
+                    return Ok(());
+                }
+            }
+        }
+        self.apply(board, edit)?;
+        self.mergeable = self.group.is_none();
+        Ok(())
+    }
+
+    /// Undo the edits made until the matching [`History::end_group`] together. Groups can be
+    /// nested, only the outermost one counts.
+    pub fn begin_group(&mut self, board: &Board) {
+        if self.depth == 0 {
+            self.group = Some(Step {
+                edits: vec![],
+                current: current_path(board),
+            });
+        }
+        self.depth += 1;
+    }
+
+    /// End the group started by [`History::begin_group`].
+    pub fn end_group(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 {
+            if let Some(group) = self.group.take().filter(|group| !group.edits.is_empty()) {
+                self.undo.push(group);
+                self.mergeable = false;
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[must_use]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Undo the last change, and go back to the move that was current before it.
+    ///
+    /// Returns `false` if there is nothing to undo. The change is forgotten if it can't be undone,
+    /// and the board is left as it was.
+    pub fn undo(&mut self, board: &mut Board) -> Result<bool, EditError> {
+        self.end_open_group();
+        let Some(step) = self.undo.pop() else {
+            return Ok(false);
+        };
+        self.redo.push(step.replay(board)?);
+        Ok(true)
+    }
+
+    /// Make the last undone change again, see [`History::undo`].
+    pub fn redo(&mut self, board: &mut Board) -> Result<bool, EditError> {
+        self.end_open_group();
+        let Some(step) = self.redo.pop() else {
+            return Ok(false);
+        };
+        self.undo.push(step.replay(board)?);
+        Ok(true)
+    }
+
+    /// Forget every change.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn end_open_group(&mut self) {
+        self.mergeable = false;
+        if self.depth > 0 {
+            self.depth = 1;
+            self.end_group();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Point, Stone};
+    use test_log::test;
+
+    /// Every move in the tree and the links, in tree order.
+    #[allow(clippy::type_complexity)]
+    fn snapshot(
+        board: &Board,
+    ) -> (
+        Vec<(Vec<usize>, BoardMarker)>,
+        Vec<(Vec<usize>, Vec<usize>)>,
+    ) {
+        let mut moves = Vec::new();
+        let mut stack = vec![board.get_root()];
+        while let Some(node) = stack.pop() {
+            moves.push((
+                board.path_to(node).unwrap(),
+                board.get_move(node).unwrap().clone(),
+            ));
+            stack.extend(board.get_move_children(&node).into_iter().rev());
+        }
+        let links = board
+            .links()
+            .into_iter()
+            .map(|(from, to)| (board.path_to(from).unwrap(), board.path_to(to).unwrap()))
+            .collect();
+        (moves, links)
+    }
+
+    fn marker(x: u32, y: u32) -> BoardMarker {
+        BoardMarker::new(Point::new(x, y), Stone::Black)
+    }
+
+    /// Root with a line 0-0 and 1-1, a second move 2-2, and 3-3 after it.
+    fn tree() -> Board {
+        let mut board = Board::with_size(15).unwrap();
+        let root = board.get_root();
+        let first = board.insert_move(root, marker(0, 0));
+        board.insert_move(first, marker(1, 1));
+        let second = board.insert_move(root, marker(2, 2));
+        let third = board.insert_move(second, marker(3, 3));
+        board.add_edge(&third, &first).unwrap();
+        board
+    }
+
+    #[test]
+    fn every_edit_is_undone() {
+        let mut board = tree();
+        let original = snapshot(&board);
+        let mut comment = marker(0, 0);
+        comment.set_oneline_comment("first".to_string());
+        for edit in [
+            Edit::AddMove {
+                parent: vec![0],
+                marker: marker(4, 4),
+            },
+            Edit::RemoveSubtree { node: vec![0] },
+            Edit::RemoveSubtree { node: vec![1, 0] },
+            Edit::SetMarker {
+                node: vec![0],
+                marker: comment,
+            },
+            Edit::Reorder {
+                parent: vec![],
+                order: vec![1, 0],
+            },
+            Edit::MoveSubtree {
+                node: vec![0],
+                new_parent: vec![1, 0],
+                position: 0,
+            },
+            Edit::MoveSubtree {
+                node: vec![0, 0],
+                new_parent: vec![1],
+                position: 5,
+            },
+            Edit::MoveSubtree {
+                node: vec![0, 0],
+                new_parent: vec![],
+                position: 1,
+            },
+            Edit::Unlink {
+                from: vec![1, 0],
+                to: vec![0],
+            },
+            Edit::Link {
+                from: vec![1],
+                to: vec![0, 0],
+            },
+        ] {
+            let inverse = edit.clone().apply(&mut board).unwrap();
+            let edited = snapshot(&board);
+            assert_ne!(edited, original, "{edit:?}");
+            let redo = inverse.apply(&mut board).unwrap();
+            assert_eq!(snapshot(&board), original, "{edit:?}");
+            let undo = redo.apply(&mut board).unwrap();
+            assert_eq!(snapshot(&board), edited, "{edit:?}");
+            undo.apply(&mut board).unwrap();
+        }
+        assert_eq!(
+            board.get_move(board.find_path(&[0]).unwrap()),
+            Some(&marker(0, 0))
+        );
+    }
+
+    #[test]
+    fn failed_edits() {
+        let mut board = tree();
+        let original = snapshot(&board);
+        let positions = board.position_index().len();
+        for (edit, error) in [
+            (
+                Edit::RemoveSubtree { node: vec![2] },
+                EditError::NoPath(vec![2]),
+            ),
+            (
+                Edit::RemoveSubtree { node: vec![] },
+                EditError::Root(board.get_root()),
+            ),
+            (
+                Edit::Reorder {
+                    parent: vec![],
+                    order: vec![0, 0],
+                },
+                EditError::NotAPermutation(board.get_root()),
+            ),
+            (
+                Edit::Link {
+                    from: vec![1, 0],
+                    to: vec![0],
+                },
+                EditError::AlreadyLinked(
+                    board.find_path(&[1, 0]).unwrap(),
+                    board.find_path(&[0]).unwrap(),
+                ),
+            ),
+            (
+                Edit::MoveSubtree {
+                    node: vec![1],
+                    new_parent: vec![0, 0],
+                    position: 0,
+                },
+                EditError::WouldCycle(
+                    board.find_path(&[1]).unwrap(),
+                    board.find_path(&[0, 0]).unwrap(),
+                ),
+            ),
+            (
+                Edit::Restore {
+                    parent: vec![],
+                    position: 0,
+                    subtree: Subtree {
+                        marker: marker(4, 4),
+                        children: vec![],
+                    },
+                    links: vec![(vec![0], vec![1, 0]), (vec![0], vec![5])],
+                },
+                EditError::NoPath(vec![5]),
+            ),
+        ] {
+            assert_eq!(edit.apply(&mut board), Err(error));
+            assert_eq!(snapshot(&board), original);
+            assert_eq!(board.position_index().len(), positions);
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut board = tree();
+        let mut history = History::new();
+        let original = snapshot(&board);
+        assert_eq!(history.undo(&mut board), Ok(false));
+
+        history.begin_group(&board);
+        history
+            .apply(
+                &mut board,
+                Edit::AddMove {
+                    parent: vec![1],
+                    marker: marker(5, 5),
+                },
+            )
+            .unwrap();
+        history.begin_group(&board);
+        history
+            .apply(&mut board, Edit::RemoveSubtree { node: vec![0] })
+            .unwrap();
+        history.end_group();
+        assert!(!history.can_undo(), "the outer group is still open");
+        history.end_group();
+        history
+            .apply(
+                &mut board,
+                Edit::Reorder {
+                    parent: vec![0],
+                    order: vec![1, 0],
+                },
+            )
+            .unwrap();
+        let edited = snapshot(&board);
+
+        assert_eq!(history.undo(&mut board), Ok(true));
+        assert_eq!(history.undo(&mut board), Ok(true));
+        assert_eq!(snapshot(&board), original);
+        assert!(!history.can_undo());
+        assert_eq!(history.redo(&mut board), Ok(true));
+        assert_eq!(history.redo(&mut board), Ok(true));
+        assert_eq!(snapshot(&board), edited);
+        assert!(!history.can_redo());
+
+        history.undo(&mut board).unwrap();
+        history
+            .apply(&mut board, Edit::RemoveSubtree { node: vec![0] })
+            .unwrap();
+        assert!(!history.can_redo(), "a new change forgets the undone ones");
+    }
+
+    #[test]
+    fn failed_undo_changes_nothing() {
+        let mut board = tree();
+        let mut history = History::new();
+        history.begin_group(&board);
+        history
+            .apply(
+                &mut board,
+                Edit::AddMove {
+                    parent: vec![1],
+                    marker: marker(5, 5),
+                },
+            )
+            .unwrap();
+        history
+            .apply(&mut board, Edit::RemoveSubtree { node: vec![0] })
+            .unwrap();
+        history.end_group();
+        // changed around the history, the removed move comes back but the added one is gone
+        board
+            .remove_subtree(board.find_path(&[0, 1]).unwrap())
+            .unwrap();
+        let edited = snapshot(&board);
+        let current = board.current_move();
+
+        assert_eq!(history.undo(&mut board), Err(EditError::NoPath(vec![1, 1])));
+        assert_eq!(snapshot(&board), edited);
+        assert_eq!(board.current_move(), current);
+        assert!(!history.can_undo() && !history.can_redo());
+    }
+
+    #[test]
+    fn current_move_is_restored() {
+        let mut board = tree();
+        let mut history = History::new();
+        let deep = board.find_path(&[0, 0]).unwrap();
+        board.set_current_move(deep).unwrap();
+        history
+            .apply(&mut board, Edit::RemoveSubtree { node: vec![0] })
+            .unwrap();
+        assert_eq!(board.path_to(board.current_move()), Ok(vec![]));
+        board
+            .set_current_move(board.find_path(&[0]).unwrap())
+            .unwrap();
+
+        history.undo(&mut board).unwrap();
+        assert_eq!(board.path_to(board.current_move()), Ok(vec![0, 0]));
+        assert_eq!(board.move_list().len(), 3);
+        history.redo(&mut board).unwrap();
+        assert_eq!(board.path_to(board.current_move()), Ok(vec![0]));
+    }
+
+    #[test]
+    fn typing_is_undone_at_once() {
+        let mut board = tree();
+        let mut history = History::new();
+        let comment = |history: &mut History, board: &mut Board, node: &[usize], text: &str| {
+            let mut marker = board
+                .get_move(board.find_path(node).unwrap())
+                .unwrap()
+                .clone();
+            marker.set_oneline_comment(text.to_string());
+            history
+                .apply_merged(
+                    board,
+                    Edit::SetMarker {
+                        node: node.to_vec(),
+                        marker,
+                    },
+                )
+                .unwrap();
+        };
+        let text = |board: &Board, node: &[usize]| {
+            board
+                .get_move(board.find_path(node).unwrap())
+                .unwrap()
+                .oneline_comment
+                .clone()
+        };
+        comment(&mut history, &mut board, &[0], "a");
+        comment(&mut history, &mut board, &[0], "ab");
+        comment(&mut history, &mut board, &[1], "c");
+        comment(&mut history, &mut board, &[1], "cd");
+
+        history.undo(&mut board).unwrap();
+        assert_eq!(text(&board, &[1]), None);
+        assert_eq!(text(&board, &[0]), Some("ab".to_string()));
+        history.redo(&mut board).unwrap();
+        comment(&mut history, &mut board, &[1], "cde");
+        history.undo(&mut board).unwrap();
+        assert_eq!(text(&board, &[1]), Some("cd".to_string()));
+        history.undo(&mut board).unwrap();
+        history.undo(&mut board).unwrap();
+        assert_eq!(text(&board, &[0]), None);
+    }
+}
@@ -0,0 +1,419 @@
+//! Merging the moves of one [`Board`] into another, like two libraries of the same openings.
+//!
+//! Moves on the same point from the same position are shared, everything else is added. With
+//! symmetry, a line of the other board is also shared with a line of this one that is the same up
+//! to rotation and mirroring, and added in the orientation of this board.
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use super::{Board, BoardMarker, EditError, MoveIndex, NodeIndex, Point, Stone, Transformation};
+
+/// Put between two different texts of a move by [`CommentMerge::MarkConflicts`].
+pub const CONFLICT_MARKER: &str = "<<<merge conflict>>>";
+
+/// What [`Board::merge_with`] does with the texts of a move that both boards have.
+///
+/// A text that only one of the moves has is always kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommentMerge {
+    /// Keep both texts, the one of this board first.
+    #[default]
+    Concatenate,
+    /// Keep both texts with [`CONFLICT_MARKER`] between them, so they can be found and resolved
+    /// by hand.
+    MarkConflicts,
+}
+
+/// How [`Board::merge_with`] merges another board.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    symmetry: bool,
+    comments: CommentMerge,
+}
+
+impl MergeOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also share lines that are the same up to rotation and mirroring.
+    #[must_use]
+    pub fn with_symmetry(mut self, symmetry: bool) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    #[must_use]
+    pub fn with_comments(mut self, comments: CommentMerge) -> Self {
+        self.comments = comments;
+        self
+    }
+}
+
+/// What [`Board::merge_with`] did, counted in moves of the merged board.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    /// Moves that weren't on this board.
+    pub added: usize,
+    /// Moves that were already on this board, not counting the root.
+    pub shared: usize,
+    /// Shared moves that were only on this board in another orientation.
+    pub transformed: usize,
+    /// Texts of shared moves that only the merged board had.
+    pub comments_added: usize,
+    /// Texts of shared moves that were different on both boards.
+    pub conflicts: usize,
+    /// Links added with [`Board::add_edge`] for the links of the merged board, and by
+    /// [`Board::link_transpositions`] between the moves that lead to the same position after the
+    /// merge.
+    pub links: usize,
+}
+
+impl fmt::Display for MergeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} moves added, {} shared ({} in another orientation), ",
+            self.added, self.shared, self.transformed
+        )?;
+        write!(
+            f,
+            "{} comments added, {} conflicts, {} links",
+            self.comments_added, self.conflicts, self.links
+        )
+    }
+}
+
+impl Board {
+    /// Merge the moves of `other` into this board, see [`Board::merge_with`].
+    pub fn merge(&mut self, other: &Board) -> Result<MergeStats, EditError> {
+        self.merge_with(other, MergeOptions::default())
+    }
+
+    /// Merge the moves of `other` into this board.
+    ///
+    /// Moves of `other` on the same point from the same position as a move of this board are
+    /// shared with it, and their texts are merged as `options` says. Other moves are added after
+    /// the moves that are already played from the same move. Links of `other` are added between
+    /// the moves they end up as, and the transpositions between the moves of both are linked.
+    ///
+    /// The moves of this board aren't renumbered, but both boards have to be the same size.
+    pub fn merge_with(
+        &mut self,
+        other: &Board,
+        options: MergeOptions,
+    ) -> Result<MergeStats, EditError> {
+        if self.size != other.size {
+            return Err(EditError::DifferentSizes(self.size, other.size));
+        }
+        let mut stats = MergeStats::default();
+        let mut merged = HashMap::from([(other.get_root().node_index, self.get_root())]);
+        self.merge_texts(self.get_root(), other.root_marker(), options, &mut stats);
+
+        let mut stack = vec![(
+            self.get_root(),
+            other.get_root(),
+            Transformation::identity(),
+            vec![],
+            vec![],
+        )];
+        while let Some((ours, theirs, transform, our_stones, their_stones)) = stack.pop() {
+            // the orientations of `other` that give the position of this board, the current one
+            // first
+            let transforms = if options.symmetry {
+                let mut transforms = vec![transform];
+                transforms.extend(Transformation::types().into_iter().filter(|other| {
+                    *other != transform
+                        && same_stones(&their_stones, *other, &our_stones, self.size)
+                }));
+                transforms
+            } else {
+                vec![transform]
+            };
+            let mut next = vec![];
+            for child in other.get_move_children(&theirs) {
+                let marker = other.get_move(child).expect("children are in the tree");
+                let shared = transforms.iter().find_map(|transform| {
+                    let marker = transformed(marker, *transform, self.size);
+                    self.get_move_children(&ours)
+                        .into_iter()
+                        .find(|ours| self.get_move(*ours).is_some_and(|m| same(m, &marker)))
+                        .map(|ours| (ours, *transform))
+                });
+                let Some((node, transform)) = shared else {
+                    stats.added +=
+                        self.insert_transformed(other, child, ours, transform, &mut merged);
+                    continue;
+                };
+                stats.shared += 1;
+                if transform != Transformation::identity() {
+                    stats.transformed += 1;
+                }
+                merged.insert(child.node_index, node);
+                self.merge_texts(node, marker, options, &mut stats);
+                let (mut our_stones, mut their_stones) = (our_stones.clone(), their_stones.clone());
+                if let Some(stone) = stone(self.get_move(node).expect("the move was found")) {
+                    our_stones.push(stone);
+                }
+                if let Some(stone) = stone(marker) {
+                    their_stones.push(stone);
+                }
+                next.push((node, child, transform, our_stones, their_stones));
+            }
+            stack.extend(next.into_iter().rev());
+        }
+
+        let mut links = self
+            .links()
+            .into_iter()
+            .map(|(from, to)| (from.node_index, to.node_index))
+            .collect::<HashSet<_>>();
+        for (from, to) in other.links() {
+            let (Some(from), Some(to)) = (merged.get(&from.node_index), merged.get(&to.node_index))
+            else {
+                continue;
+            };
+            let link = (from.node_index, to.node_index);
+            if link.0 != link.1 && links.insert(link) && self.add_edge(from, to).is_ok() {
+                stats.links += 1;
+            }
+        }
+        stats.links += self.link_transpositions();
+        Ok(stats)
+    }
+
+    fn root_marker(&self) -> &BoardMarker {
+        self.get_move(self.get_root())
+            .expect("the root is in the tree")
+    }
+
+    /// Insert `node` of `other` and the moves after it from `parent`, in the orientation of
+    /// `transform`. Returns the number of inserted moves.
+    fn insert_transformed(
+        &mut self,
+        other: &Board,
+        node: MoveIndex,
+        parent: MoveIndex,
+        transform: Transformation,
+        merged: &mut HashMap<NodeIndex, MoveIndex>,
+    ) -> usize {
+        let mut inserted = 0;
+        let mut stack = vec![(node, parent)];
+        while let Some((theirs, parent)) = stack.pop() {
+            let marker = other.get_move(theirs).expect("children are in the tree");
+            let ours = self.insert_move(parent, transformed(marker, transform, self.size));
+            merged.insert(theirs.node_index, ours);
+            inserted += 1;
+            for child in other.get_move_children(&theirs).into_iter().rev() {
+                stack.push((child, ours));
+            }
+        }
+        inserted
+    }
+
+    fn merge_texts(
+        &mut self,
+        node: MoveIndex,
+        theirs: &BoardMarker,
+        options: MergeOptions,
+        stats: &mut MergeStats,
+    ) {
+        let ours = self.get_move_mut(node).expect("the move was found");
+        for (ours, theirs, separator) in [
+            (&mut ours.oneline_comment, &theirs.oneline_comment, "; "),
+            (
+                &mut ours.multiline_comment,
+                &theirs.multiline_comment,
+                "\n\n",
+            ),
+            (&mut ours.board_text, &theirs.board_text, "; "),
+        ] {
+            match (ours.as_mut(), theirs) {
+                (_, None) => (),
+                (None, Some(theirs)) => {
+                    *ours = Some(theirs.clone());
+                    stats.comments_added += 1;
+                }
+                (Some(ours), Some(theirs)) if ours == theirs => (),
+                (Some(ours), Some(theirs)) => {
+                    *ours = match options.comments {
+                        CommentMerge::Concatenate => format!("{ours}{separator}{theirs}"),
+                        CommentMerge::MarkConflicts => {
+                            format!("{ours} {CONFLICT_MARKER} {theirs}")
+                        }
+                    };
+                    stats.conflicts += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Returns `true` if `left` and `right` are moves on the same point, or the same mark on the same
+/// point.
+fn same(left: &BoardMarker, right: &BoardMarker) -> bool {
+    match (left.command.is_move(), right.command.is_move()) {
+        (true, true) => left.point == right.point,
+        (false, false) => left.point == right.point && left.command == right.command,
+        _ => false,
+    }
+}
+
+/// The stone a move puts on the board, like [`Board::as_board`].
+fn stone(marker: &BoardMarker) -> Option<(Point, Stone)> {
+    (marker.command.is_move() && !marker.point.is_null).then_some((marker.point, marker.color))
+}
+
+fn transformed(marker: &BoardMarker, transform: Transformation, size: u32) -> BoardMarker {
+    let mut marker = marker.clone();
+    if !marker.point.is_null {
+        marker.point = transform.apply(marker.point, size);
+    }
+    marker
+}
+
+/// Returns `true` if `theirs` are the stones of `ours` after `transform`.
+fn same_stones(
+    theirs: &[(Point, Stone)],
+    transform: Transformation,
+    ours: &[(Point, Stone)],
+    size: u32,
+) -> bool {
+    let ours = ours.iter().copied().collect::<BTreeSet<_>>();
+    theirs.len() == ours.len()
+        && theirs
+            .iter()
+            .all(|(point, stone)| ours.contains(&(transform.apply(*point, size), *stone)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use test_log::test;
+
+    fn board(lines: &[&[Point]]) -> Board {
+        let mut board = Board::new();
+        for line in lines {
+            board.get_or_insert_points(board.get_root(), line.iter().copied());
+        }
+        board
+    }
+
+    fn find(board: &Board, points: &[Point]) -> MoveIndex {
+        let root = board.get_root();
+        board
+            .find_line(&root, points.iter().copied())
+            .unwrap()
+            .last()
+            .copied()
+            .unwrap_or(root)
+    }
+
+    fn comment(board: &mut Board, points: &[Point], text: &str) {
+        let node = find(board, points);
+        board
+            .get_move_mut(node)
+            .unwrap()
+            .set_oneline_comment(text.to_string());
+    }
+
+    #[test]
+    fn shared_and_added_moves() {
+        let mut ours = board(&[&p![[H, 8], [I, 9], [J, 10]], &p![[H, 8], [G, 9]]]);
+        let mut theirs = board(&[&p![[H, 8], [I, 9], [G, 7]], &p![[H, 8], [G, 9]]]);
+        comment(&mut ours, &p![[H, 8]], "center");
+        comment(&mut theirs, &p![[H, 8]], "tengen");
+        comment(&mut theirs, &p![[H, 8], [G, 9]], "direct");
+        let node = find(&theirs, &p![[H, 8], [I, 9], [G, 7]]);
+        let target = find(&theirs, &p![[H, 8], [G, 9]]);
+        theirs.add_edge(&node, &target).unwrap();
+
+        let stats = ours.merge(&theirs).unwrap();
+        assert_eq!(
+            stats,
+            MergeStats {
+                added: 1,
+                shared: 3,
+                transformed: 0,
+                comments_added: 1,
+                conflicts: 1,
+                links: 1,
+            }
+        );
+        let h8 = ours.get_move(find(&ours, &p![[H, 8]])).unwrap();
+        assert_eq!(h8.oneline_comment.as_deref(), Some("center; tengen"));
+        let g7 = find(&ours, &p![[H, 8], [I, 9], [G, 7]]);
+        assert_eq!(
+            ours.get_move_children(&find(&ours, &p![[H, 8], [I, 9]])),
+            vec![find(&ours, &p![[H, 8], [I, 9], [J, 10]]), g7]
+        );
+        assert_eq!(ours.links(), vec![(g7, find(&ours, &p![[H, 8], [G, 9]]))]);
+
+        // merging again adds nothing
+        let again = ours.merge(&theirs).unwrap();
+        assert_eq!((again.added, again.shared, again.links), (0, 4, 0));
+        assert_eq!(again.conflicts, 1);
+    }
+
+    #[test]
+    fn transpositions_are_linked() {
+        let mut ours = board(&[&p![[H, 8], [I, 9], [G, 7], [J, 10]]]);
+        let theirs = board(&[&p![[G, 7], [I, 9], [H, 8]]]);
+        let stats = ours.merge(&theirs).unwrap();
+        assert_eq!((stats.added, stats.links), (3, 1));
+        let transposed = find(&ours, &p![[G, 7], [I, 9], [H, 8]]);
+        let j10 = find(&ours, &p![[H, 8], [I, 9], [G, 7], [J, 10]]);
+        assert_eq!(
+            ours.lines(&transposed).count(),
+            1,
+            "the link is not a move played from it"
+        );
+        assert!(ours
+            .next_moves(&transposed)
+            .any(|(node, ..)| ours.get_move_children(&node).contains(&j10)));
+    }
+
+    #[test]
+    fn conflicts_are_marked() {
+        let mut ours = board(&[&p![[H, 8]]]);
+        let mut theirs = board(&[&p![[H, 8]]]);
+        comment(&mut ours, &p![[H, 8]], "a");
+        comment(&mut theirs, &p![[H, 8]], "b");
+        ours.merge_with(
+            &theirs,
+            MergeOptions::new().with_comments(CommentMerge::MarkConflicts),
+        )
+        .unwrap();
+        let h8 = ours.get_move(find(&ours, &p![[H, 8]])).unwrap();
+        assert_eq!(h8.oneline_comment, Some(format!("a {CONFLICT_MARKER} b")));
+    }
+
+    #[test]
+    fn symmetric_lines_are_shared() {
+        let mut ours = board(&[&p![[H, 8], [I, 9], [J, 9]]]);
+        // the same line mirrored, then a move of its own
+        let theirs = board(&[&p![[H, 8], [G, 9], [F, 9], [E, 9]]]);
+
+        let mut plain = board(&[&p![[H, 8], [I, 9], [J, 9]]]);
+        let stats = plain.merge(&theirs).unwrap();
+        assert_eq!((stats.added, stats.shared), (3, 1));
+
+        let stats = ours
+            .merge_with(&theirs, MergeOptions::new().with_symmetry(true))
+            .unwrap();
+        assert_eq!((stats.added, stats.shared, stats.transformed), (1, 3, 2));
+        // the new move is added in the orientation of this board
+        find(&ours, &p![[H, 8], [I, 9], [J, 9], [K, 9]]);
+    }
+
+    #[test]
+    fn sizes_have_to_match() {
+        let mut ours = Board::with_size(15).unwrap();
+        assert_eq!(
+            ours.merge(&Board::with_size(19).unwrap()),
+            Err(EditError::DifferentSizes(15, 19))
+        );
+    }
+}
@@ -0,0 +1,905 @@
+//! Opening protocols used in renju tournaments, which decide how the first moves are placed and
+//! who ends up with which color.
+//!
+//! An [`Opening`] drives a game through the [`Phase`]s of a [`Protocol`] and checks every action
+//! against the current phase, e.g the 3rd move has to be in the central 5x5. The moves are recorded
+//! in a [`Board`]. The 5th move alternatives black offers are kept as children of the 4th move,
+//! marked with `NOMOVE` and `MARK`, next to the one white chose.
+//!
+//! The first three moves of a game are one of the 26 [`CanonicalOpening`]s, up to symmetry.
+use std::collections::VecDeque;
+use std::fmt;
+
+use super::rules::{IllegalMove, RuleSet};
+use super::{Board, BoardArr, BoardMarker, MoveIndex, Point, Stone, Transformation};
+use crate::file_reader::renlib::CommandVariant;
+
+/// How the opening of a game is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Protocol {
+    /// The tentative black places one of the 26 openings, white may swap, plays the 4th move and
+    /// chooses between two 5th moves offered by black.
+    Rif,
+    /// Like [`Protocol::Rif`], but the tentative black declares how many 5th moves black offers
+    /// before the swap.
+    Yamaguchi,
+    /// The first five moves are placed one at a time in growing squares around the center, with a
+    /// swap after each. Black may offer ten 5th moves anywhere instead of playing one.
+    Taraguchi,
+    /// Like [`Protocol::Yamaguchi`], but white declares the number of 5th moves, at most
+    /// [`Protocol::SOOSORV_MAX`], after the 4th move and black may swap again.
+    Soosorv,
+}
+
+impl Protocol {
+    pub const ALL: [Self; 4] = [Self::Rif, Self::Yamaguchi, Self::Taraguchi, Self::Soosorv];
+    /// The most 5th moves that can be declared in [`Protocol::Soosorv`].
+    pub const SOOSORV_MAX: u32 = 8;
+    /// The number of 5th moves black offers instead of playing one in [`Protocol::Taraguchi`].
+    pub const TARAGUCHI_ALTERNATIVES: u32 = 10;
+
+    /// The phases of the protocol in order, before any choices are made.
+    fn phases(self) -> VecDeque<Phase> {
+        use Phase::*;
+        let move_in = |number, area| Move {
+            number,
+            area: Some(area),
+        };
+        let mut phases = VecDeque::new();
+        if self != Self::Taraguchi {
+            phases.extend([move_in(1, 1), move_in(2, 3), move_in(3, 5)]);
+        }
+        phases.extend(match self {
+            Self::Rif => vec![
+                Swap,
+                Move {
+                    number: 4,
+                    area: None,
+                },
+                Offer { count: 2 },
+                Choose,
+            ],
+            Self::Yamaguchi => vec![
+                Declare { max: None },
+                Swap,
+                Move {
+                    number: 4,
+                    area: None,
+                },
+                Offer { count: 0 },
+                Choose,
+            ],
+            Self::Taraguchi => vec![
+                move_in(1, 1),
+                Swap,
+                move_in(2, 3),
+                Swap,
+                move_in(3, 5),
+                Swap,
+                move_in(4, 7),
+                Swap,
+                Fifth {
+                    area: 9,
+                    alternatives: Self::TARAGUCHI_ALTERNATIVES,
+                },
+                Swap,
+            ],
+            Self::Soosorv => vec![
+                Swap,
+                Move {
+                    number: 4,
+                    area: None,
+                },
+                Declare {
+                    max: Some(Self::SOOSORV_MAX),
+                },
+                Swap,
+                Offer { count: 0 },
+                Choose,
+            ],
+        });
+        phases
+    }
+
+    /// Returns `true` if the first three moves are placed together by the tentative black.
+    fn places_opening(self) -> bool {
+        self != Self::Taraguchi
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rif => "RIF",
+            Self::Yamaguchi => "Yamaguchi",
+            Self::Taraguchi => "Taraguchi",
+            Self::Soosorv => "Soosõrv",
+        })
+    }
+}
+
+/// The 26 openings the first three moves of a game can make, up to symmetry.
+///
+/// The first move is always in the center. In the 13 direct openings the second move is next to it,
+/// in the 13 indirect openings it's diagonal to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CanonicalOpening {
+    Kansei,
+    Keigetsu,
+    Sosei,
+    Kagetsu,
+    Zangetsu,
+    Ugetsu,
+    Kinsei,
+    Shogetsu,
+    Kyugetsu,
+    Shingetsu,
+    Zuisei,
+    Sangetsu,
+    Yusei,
+    Chosei,
+    Kyogetsu,
+    Kosei,
+    Suigetsu,
+    Ryusei,
+    Ungetsu,
+    Hogetsu,
+    Rangetsu,
+    Gingetsu,
+    Myojo,
+    Shagetsu,
+    Meigetsu,
+    Suisei,
+}
+
+impl CanonicalOpening {
+    /// Every opening, the direct ones first, in their usual order.
+    pub const ALL: [Self; 26] = {
+        use CanonicalOpening::*;
+        [
+            Kansei, Keigetsu, Sosei, Kagetsu, Zangetsu, Ugetsu, Kinsei, Shogetsu, Kyugetsu,
+            Shingetsu, Zuisei, Sangetsu, Yusei, Chosei, Kyogetsu, Kosei, Suigetsu, Ryusei, Ungetsu,
+            Hogetsu, Rangetsu, Gingetsu, Myojo, Shagetsu, Meigetsu, Suisei,
+        ]
+    };
+
+    /// The third move in the standard orientation, as steps right and up from the center. The
+    /// second move is one step up for direct openings and one step up and right for indirect ones.
+    const THIRD: [(i64, i64); 26] = [
+        // direct
+        (0, 2),
+        (1, 2),
+        (2, 2),
+        (1, 1),
+        (2, 1),
+        (1, 0),
+        (2, 0),
+        (0, -1),
+        (1, -1),
+        (2, -1),
+        (0, -2),
+        (1, -2),
+        (2, -2),
+        // indirect
+        (2, 2),
+        (1, 2),
+        (0, 2),
+        (-1, 2),
+        (-2, 2),
+        (0, 1),
+        (-1, 1),
+        (-2, 1),
+        (-1, 0),
+        (-2, 0),
+        (-1, -1),
+        (-2, -1),
+        (-2, -2),
+    ];
+
+    /// Returns `true` if the second move is next to the first, `false` if it's diagonal to it.
+    #[must_use]
+    pub fn is_direct(self) -> bool {
+        (self as usize) < 13
+    }
+
+    /// The short code of the opening, `D1` to `D13` for direct and `I1` to `I13` for indirect.
+    #[must_use]
+    pub fn code(self) -> String {
+        let index = self as usize;
+        if self.is_direct() {
+            format!("D{}", index + 1)
+        } else {
+            format!("I{}", index - 12)
+        }
+    }
+
+    /// The three moves of the opening in the standard orientation on a board of `size`x`size`,
+    /// where `size` is odd.
+    #[must_use]
+    pub fn moves(self, size: u32) -> [Point; 3] {
+        let center = i64::from(size / 2);
+        let at = |(x, y): (i64, i64)| Point::new((center + x) as u32, (center - y) as u32);
+        let second = if self.is_direct() { (0, 1) } else { (1, 1) };
+        [at((0, 0)), at(second), at(Self::THIRD[self as usize])]
+    }
+
+    /// Name the opening the first three of `moves` make on a board of `size`x`size`.
+    ///
+    /// Returns the opening and the transformation that maps `moves` to [`CanonicalOpening::moves`],
+    /// or `None` if there are fewer than three moves or they aren't one of the openings.
+    #[must_use]
+    pub fn recognize(moves: &[Point], size: u32) -> Option<(Self, Transformation)> {
+        let [first, second, third] = *moves.get(..3)? else {
+            return None;
+        };
+        if size.is_multiple_of(2) || [first, second, third].iter().any(|p| p.is_null) {
+            return None;
+        }
+        Transformation::types().into_iter().find_map(|transform| {
+            let moves = [first, second, third].map(|p| transform.apply(p, size));
+            Self::ALL
+                .into_iter()
+                .find(|opening| opening.moves(size) == moves)
+                .map(|opening| (opening, transform))
+        })
+    }
+}
+
+impl fmt::Display for CanonicalOpening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// What has to happen next in an [`Opening`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Phase {
+    /// Move `number` is played, within the central `area`x`area` square if there is one.
+    Move { number: u32, area: Option<u32> },
+    /// The player without the last stone may swap colors.
+    Swap,
+    /// The number of 5th moves black will offer is declared, at most `max` if there is one.
+    Declare { max: Option<u32> },
+    /// Black offers `count` different 5th moves.
+    Offer { count: u32 },
+    /// Black either plays the 5th move within the central `area`x`area` square, or offers
+    /// `alternatives` different 5th moves anywhere.
+    Fifth { area: u32, alternatives: u32 },
+    /// White chooses one of the offered 5th moves.
+    Choose,
+    /// The opening is over, the game goes on by the renju rules.
+    Free,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Move {
+                number,
+                area: Some(area),
+            } => write!(f, "move {number} within the central {area}x{area}"),
+            Self::Move { number, area: None } => write!(f, "move {number}"),
+            Self::Swap => write!(f, "swap"),
+            Self::Declare { max: Some(max) } => write!(f, "declaring up to {max} 5th moves"),
+            Self::Declare { max: None } => write!(f, "declaring the number of 5th moves"),
+            Self::Offer { count } => write!(f, "offering {count} 5th moves"),
+            Self::Fifth { area, alternatives } => write!(
+                f,
+                "5th move within the central {area}x{area} or offering {alternatives}"
+            ),
+            Self::Choose => write!(f, "choosing a 5th move"),
+            Self::Free => write!(f, "free play"),
+        }
+    }
+}
+
+/// One of the two players, which color they play can change with a swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Player {
+    /// The player who starts as (tentative) black.
+    First,
+    Second,
+}
+
+impl Player {
+    #[must_use]
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::First => Self::Second,
+            Self::Second => Self::First,
+        }
+    }
+}
+
+/// Why an action isn't allowed in an [`Opening`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OpeningError {
+    #[error("can't {action} during {phase}")]
+    WrongPhase { phase: Phase, action: &'static str },
+    #[error("{point} is outside the central {area}x{area}")]
+    OutsideArea { point: Point, area: u32 },
+    #[error(transparent)]
+    Illegal(#[from] IllegalMove),
+    #[error("can't declare {declared} 5th moves, it has to be between 1 and {max}")]
+    Declared { declared: u32, max: u32 },
+    #[error("{offered} 5th moves were offered, expected {expected}")]
+    OfferCount { offered: usize, expected: u32 },
+    #[error("the 5th moves {0} and {1} are the same by symmetry")]
+    Symmetric(Point, Point),
+    #[error("{0} wasn't offered")]
+    NotOffered(Point),
+}
+
+/// A game going through the opening phases of a [`Protocol`], see the [module docs](self).
+#[derive(Debug)]
+pub struct Opening {
+    protocol: Protocol,
+    board: Board,
+    node: MoveIndex,
+    position: BoardArr,
+    phases: VecDeque<Phase>,
+    /// The player who has black right now.
+    black: Player,
+    offered: Vec<Point>,
+}
+
+impl Opening {
+    /// Start a game by `protocol` on a standard board.
+    #[must_use]
+    pub fn new(protocol: Protocol) -> Self {
+        let board = Board::new();
+        Self {
+            protocol,
+            node: board.get_root(),
+            position: BoardArr::new(board.size()),
+            board,
+            phases: protocol.phases(),
+            black: Player::First,
+            offered: vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// The tree the game is recorded in.
+    #[must_use]
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    #[must_use]
+    pub fn into_board(self) -> Board {
+        self.board
+    }
+
+    /// The last move played.
+    #[must_use]
+    pub fn current_move(&self) -> MoveIndex {
+        self.node
+    }
+
+    /// The stones on the board after the last move.
+    #[must_use]
+    pub fn position(&self) -> &BoardArr {
+        &self.position
+    }
+
+    #[must_use]
+    pub fn phase(&self) -> Phase {
+        self.phases.front().copied().unwrap_or(Phase::Free)
+    }
+
+    /// The canonical opening of the first three moves, once they're played.
+    #[must_use]
+    pub fn canonical(&self) -> Option<CanonicalOpening> {
+        self.board
+            .canonical_opening(&self.node)
+            .map(|(opening, _)| opening)
+    }
+
+    /// The 5th moves black offered, empty until then.
+    #[must_use]
+    pub fn offered(&self) -> &[Point] {
+        &self.offered
+    }
+
+    /// The color `player` has right now.
+    #[must_use]
+    pub fn color(&self, player: Player) -> Stone {
+        Stone::from_bool(player == self.black)
+    }
+
+    /// The player to act in the current phase.
+    #[must_use]
+    pub fn player(&self) -> Player {
+        let holder = |stone: Stone| {
+            if stone.is_black() {
+                self.black
+            } else {
+                self.black.opposite()
+            }
+        };
+        let last = self.position.to_move().opposite();
+        match self.phase() {
+            Phase::Move { number, .. } if number <= 3 && self.protocol.places_opening() => {
+                Player::First
+            }
+            Phase::Move { .. } | Phase::Free => holder(self.position.to_move()),
+            Phase::Swap => holder(last.opposite()),
+            Phase::Declare { .. } => holder(last),
+            Phase::Offer { .. } | Phase::Fifth { .. } => holder(Stone::Black),
+            Phase::Choose => holder(Stone::White),
+        }
+    }
+
+    /// Play the next stone on `point`.
+    pub fn play(&mut self, point: Point) -> Result<MoveIndex, OpeningError> {
+        let area = match self.phase() {
+            Phase::Move { area, .. } => area,
+            Phase::Fifth { area, .. } => Some(area),
+            Phase::Free => None,
+            phase => {
+                return Err(OpeningError::WrongPhase {
+                    phase,
+                    action: "play a move",
+                })
+            }
+        };
+        if let Some(area) = area {
+            if !self.in_area(point, area) {
+                return Err(OpeningError::OutsideArea { point, area });
+            }
+        }
+        let stone = self.position.to_move();
+        self.position.check_move(point, stone, RuleSet::Renju)?;
+        self.phases.pop_front();
+        Ok(self.push(point, stone))
+    }
+
+    /// Decide whether to swap colors.
+    pub fn swap(&mut self, swap: bool) -> Result<(), OpeningError> {
+        self.expect(|phase| matches!(phase, Phase::Swap), "swap")?;
+        self.phases.pop_front();
+        if swap {
+            self.black = self.black.opposite();
+        }
+        Ok(())
+    }
+
+    /// Declare how many 5th moves black will offer.
+    pub fn declare(&mut self, count: u32) -> Result<(), OpeningError> {
+        let Phase::Declare { max } = self.phase() else {
+            return Err(OpeningError::WrongPhase {
+                phase: self.phase(),
+                action: "declare 5th moves",
+            });
+        };
+        let max = max.unwrap_or(u32::MAX);
+        if !(1..=max).contains(&count) {
+            return Err(OpeningError::Declared {
+                declared: count,
+                max,
+            });
+        }
+        self.phases.pop_front();
+        for phase in &mut self.phases {
+            if let Phase::Offer { count: offer } = phase {
+                *offer = count;
+            }
+        }
+        Ok(())
+    }
+
+    /// Offer `points` as the 5th move for white to choose from. No two of them may be the same by
+    /// symmetry of the position.
+    ///
+    /// Returns the markers added to the tree.
+    pub fn offer(&mut self, points: &[Point]) -> Result<Vec<MoveIndex>, OpeningError> {
+        let expected = match self.phase() {
+            Phase::Offer { count } => count,
+            Phase::Fifth { alternatives, .. } => alternatives,
+            phase => {
+                return Err(OpeningError::WrongPhase {
+                    phase,
+                    action: "offer 5th moves",
+                })
+            }
+        };
+        if points.len() != expected as usize {
+            return Err(OpeningError::OfferCount {
+                offered: points.len(),
+                expected,
+            });
+        }
+        for point in points {
+            self.position
+                .check_move(*point, Stone::Black, RuleSet::Renju)?;
+        }
+        let symmetries = self.symmetries();
+        for (i, first) in points.iter().enumerate() {
+            for second in &points[i + 1..] {
+                if symmetries
+                    .iter()
+                    .any(|t| t.apply(*first, self.position.size()) == *second)
+                {
+                    return Err(OpeningError::Symmetric(*first, *second));
+                }
+            }
+        }
+
+        self.phases.clear();
+        self.phases.push_back(Phase::Choose);
+        self.offered = points.to_vec();
+        Ok(points
+            .iter()
+            .map(|point| {
+                let mut marker = BoardMarker::new(*point, Stone::Black);
+                marker
+                    .command
+                    .insert(CommandVariant::NOMOVE | CommandVariant::MARK);
+                self.board.insert_move(self.node, marker)
+            })
+            .collect())
+    }
+
+    /// Choose which of the offered 5th moves is played.
+    pub fn choose(&mut self, point: Point) -> Result<MoveIndex, OpeningError> {
+        self.expect(|phase| matches!(phase, Phase::Choose), "choose a 5th move")?;
+        if !self.offered.contains(&point) {
+            return Err(OpeningError::NotOffered(point));
+        }
+        self.phases.pop_front();
+        Ok(self.push(point, Stone::Black))
+    }
+
+    fn expect(
+        &self,
+        is_phase: impl FnOnce(Phase) -> bool,
+        action: &'static str,
+    ) -> Result<(), OpeningError> {
+        if is_phase(self.phase()) {
+            Ok(())
+        } else {
+            Err(OpeningError::WrongPhase {
+                phase: self.phase(),
+                action,
+            })
+        }
+    }
+
+    fn push(&mut self, point: Point, stone: Stone) -> MoveIndex {
+        if !point.is_null {
+            self.position
+                .set_point(point, stone)
+                .expect("the move was checked");
+        }
+        self.node = self
+            .board
+            .get_or_insert_move(self.node, BoardMarker::new(point, stone));
+        self.node
+    }
+
+    /// Returns `true` if `point` is in the `area`x`area` square around the center.
+    fn in_area(&self, point: Point, area: u32) -> bool {
+        let center = self.position.size() / 2;
+        !point.is_null
+            && point.is_valid(self.position.size())
+            && point.x.abs_diff(center) <= area / 2
+            && point.y.abs_diff(center) <= area / 2
+    }
+
+    /// The transformations that leave the position as it is.
+    fn symmetries(&self) -> Vec<Transformation> {
+        let size = self.position.size();
+        Transformation::types()
+            .into_iter()
+            .filter(|t| {
+                self.position
+                    .iter()
+                    .filter(|m| !m.color.is_empty())
+                    .all(|m| {
+                        self.position
+                            .get_point(t.apply(m.point, size))
+                            .is_some_and(|other| other.color == m.color)
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use test_log::test;
+
+    /// Play the first four moves of `opening`, h8 h9 h10 and h7, which is symmetric left to right.
+    fn four_moves(opening: &mut Opening) {
+        for point in p![[H, 8], [H, 9], [H, 10]] {
+            opening.play(point).unwrap();
+        }
+        if opening.phase() == (Phase::Declare { max: None }) {
+            opening.declare(2).unwrap();
+        }
+        opening.swap(false).unwrap();
+        opening.play(p![H, 7]).unwrap();
+    }
+
+    #[test]
+    fn rif() {
+        let mut opening = Opening::new(Protocol::Rif);
+        assert_eq!(
+            opening.phase(),
+            Phase::Move {
+                number: 1,
+                area: Some(1)
+            }
+        );
+        assert_eq!(
+            opening.play(p![H, 9]),
+            Err(OpeningError::OutsideArea {
+                point: p![H, 9],
+                area: 1
+            })
+        );
+        opening.play(p![H, 8]).unwrap();
+        assert_eq!(
+            opening.play(p![H, 10]),
+            Err(OpeningError::OutsideArea {
+                point: p![H, 10],
+                area: 3
+            })
+        );
+        opening.play(p![I, 9]).unwrap();
+        assert_eq!(opening.player(), Player::First);
+        assert!(matches!(
+            opening.play(p![M, 8]),
+            Err(OpeningError::OutsideArea { area: 5, .. })
+        ));
+        opening.play(p![J, 10]).unwrap();
+        assert_eq!(opening.canonical(), Some(CanonicalOpening::Chosei));
+        assert_eq!(opening.phase(), Phase::Swap);
+        assert_eq!(opening.player(), Player::Second);
+        assert!(matches!(
+            opening.play(p![A, 1]),
+            Err(OpeningError::WrongPhase { .. })
+        ));
+        opening.swap(true).unwrap();
+        assert_eq!(opening.color(Player::Second), Stone::Black);
+        assert_eq!(opening.player(), Player::First);
+        // the 4th move can go anywhere
+        opening.play(p![A, 1]).unwrap();
+        assert_eq!(opening.phase(), Phase::Offer { count: 2 });
+        assert_eq!(opening.player(), Player::Second);
+        assert_eq!(
+            opening.offer(&p![[K, 11]]),
+            Err(OpeningError::OfferCount {
+                offered: 1,
+                expected: 2
+            })
+        );
+        opening.offer(&p![[K, 11], [G, 7]]).unwrap();
+        assert_eq!(opening.player(), Player::First);
+        assert_eq!(
+            opening.choose(p![K, 12]),
+            Err(OpeningError::NotOffered(p![K, 12]))
+        );
+        opening.choose(p![G, 7]).unwrap();
+        assert_eq!(opening.phase(), Phase::Free);
+        opening.play(p![O, 15]).unwrap();
+        assert_eq!(opening.position().to_move(), Stone::Black);
+    }
+
+    #[test]
+    fn offers_are_recorded_as_marks() {
+        let mut opening = Opening::new(Protocol::Rif);
+        four_moves(&mut opening);
+        let fourth = opening.current_move();
+        let marks = opening.offer(&p![[I, 8], [H, 6]]).unwrap();
+        let chosen = opening.choose(p![H, 6]).unwrap();
+
+        let board = opening.into_board();
+        for mark in marks {
+            let marker = board.get_move(mark).unwrap();
+            assert!(marker.command.is_no_move() && marker.command.is_mark());
+            assert_eq!(marker.color, Stone::Black);
+        }
+        let children = board.get_move_children(&fourth);
+        assert_eq!(children.len(), 3);
+        assert_eq!(board.find_move_child(&fourth, &p![H, 6]), Some(chosen));
+        let (_, moves) = board.as_board(&chosen).unwrap();
+        assert_eq!(moves, p![[H, 8], [H, 9], [H, 10], [H, 7], [H, 6]]);
+    }
+
+    #[test]
+    fn symmetric_offers() {
+        let mut opening = Opening::new(Protocol::Rif);
+        four_moves(&mut opening);
+        assert_eq!(
+            opening.offer(&p![[I, 8], [G, 8]]),
+            Err(OpeningError::Symmetric(p![I, 8], p![G, 8]))
+        );
+        assert_eq!(
+            opening.offer(&p![[I, 8], [I, 8]]),
+            Err(OpeningError::Symmetric(p![I, 8], p![I, 8]))
+        );
+        assert!(matches!(
+            opening.offer(&p![[I, 8], [H, 9]]),
+            Err(OpeningError::Illegal(IllegalMove::Occupied(_)))
+        ));
+        opening.offer(&p![[I, 8], [G, 9]]).unwrap();
+    }
+
+    #[test]
+    fn canonical_openings() {
+        // every opening in every orientation is recognized, with the transformation back to the
+        // standard orientation
+        for opening in CanonicalOpening::ALL {
+            let moves = opening.moves(15);
+            assert!(moves.iter().all(|p| p.is_valid(15)));
+            for transform in Transformation::types() {
+                let transformed = moves.map(|p| transform.inverse_apply(p, 15));
+                let (found, back) = CanonicalOpening::recognize(&transformed, 15).unwrap();
+                assert_eq!(found, opening, "{transform:?}");
+                assert_eq!(transformed.map(|p| back.apply(p, 15)), moves);
+            }
+        }
+        // and no two openings are the same
+        for (i, first) in CanonicalOpening::ALL.iter().enumerate() {
+            for second in &CanonicalOpening::ALL[i + 1..] {
+                let found = CanonicalOpening::recognize(&second.moves(15), 15).unwrap();
+                assert_ne!(found.0, *first);
+            }
+        }
+
+        let (kagetsu, _) = CanonicalOpening::recognize(&p![[H, 8], [H, 9], [I, 9]], 15).unwrap();
+        assert_eq!(kagetsu, CanonicalOpening::Kagetsu);
+        assert_eq!(kagetsu.code(), "D4");
+        let (hogetsu, transform) =
+            CanonicalOpening::recognize(&p![[H, 8], [G, 7], [I, 7], [A, 1]], 15).unwrap();
+        assert_eq!(hogetsu, CanonicalOpening::Hogetsu);
+        assert_eq!(hogetsu.code(), "I7");
+        assert_eq!(hogetsu.to_string(), "Hogetsu");
+        assert_eq!(transform.apply(p![G, 7], 15), p![I, 9]);
+        let (ryusei, _) = CanonicalOpening::recognize(&p![[H, 8], [I, 9], [F, 10]], 15).unwrap();
+        assert_eq!(ryusei, CanonicalOpening::Ryusei);
+        // 19x19 boards have them too, around the center
+        let moves = Point::parse_line_with_size("j10 k11 h12", 19).unwrap();
+        assert_eq!(
+            CanonicalOpening::recognize(&moves, 19).unwrap().0,
+            CanonicalOpening::Ryusei
+        );
+        assert_eq!(moves, ryusei.moves(19));
+        assert_eq!(
+            CanonicalOpening::recognize(&ryusei.moves(19), 19)
+                .unwrap()
+                .0,
+            CanonicalOpening::Ryusei
+        );
+
+        assert_eq!(CanonicalOpening::recognize(&p![[H, 8], [H, 9]], 15), None);
+        assert_eq!(
+            CanonicalOpening::recognize(&p![[A, 1], [H, 9], [H, 10]], 15),
+            None
+        );
+        assert_eq!(
+            CanonicalOpening::recognize(&p![[H, 8], [H, 10], [H, 9]], 15),
+            None
+        );
+        assert_eq!(
+            CanonicalOpening::recognize(&p![[H, 8], [H, 9], [H, 11]], 15),
+            None
+        );
+        assert_eq!(
+            CanonicalOpening::recognize(&p![[H, 8], [H, 9], [I, 9]], 16),
+            None
+        );
+    }
+
+    #[test]
+    fn yamaguchi() {
+        let mut opening = Opening::new(Protocol::Yamaguchi);
+        for point in p![[H, 8], [H, 9], [H, 10]] {
+            opening.play(point).unwrap();
+        }
+        assert_eq!(opening.phase(), Phase::Declare { max: None });
+        assert_eq!(opening.player(), Player::First);
+        assert_eq!(
+            opening.declare(0),
+            Err(OpeningError::Declared {
+                declared: 0,
+                max: u32::MAX
+            })
+        );
+        opening.declare(3).unwrap();
+        opening.swap(false).unwrap();
+        opening.play(p![H, 7]).unwrap();
+        assert_eq!(opening.phase(), Phase::Offer { count: 3 });
+        opening.offer(&p![[I, 8], [G, 9], [H, 6]]).unwrap();
+        opening.choose(p![I, 8]).unwrap();
+        assert_eq!(opening.phase(), Phase::Free);
+    }
+
+    #[test]
+    fn soosorv() {
+        let mut opening = Opening::new(Protocol::Soosorv);
+        for point in p![[H, 8], [H, 9], [H, 10]] {
+            opening.play(point).unwrap();
+        }
+        opening.swap(true).unwrap();
+        opening.play(p![H, 7]).unwrap();
+        assert_eq!(opening.player(), Player::First);
+        assert_eq!(
+            opening.declare(9),
+            Err(OpeningError::Declared {
+                declared: 9,
+                max: Protocol::SOOSORV_MAX
+            })
+        );
+        opening.declare(1).unwrap();
+        // the player with black decides the second swap
+        assert_eq!(opening.player(), Player::Second);
+        opening.swap(true).unwrap();
+        assert_eq!(opening.player(), Player::First);
+        opening.offer(&p![[A, 1]]).unwrap();
+        opening.choose(p![A, 1]).unwrap();
+        assert_eq!(opening.color(Player::First), Stone::Black);
+    }
+
+    /// Play the first four moves of a taraguchi opening, each in a bigger square.
+    fn taraguchi_four_moves() -> Opening {
+        let mut opening = Opening::new(Protocol::Taraguchi);
+        let moves = [(p![H, 8], 1), (p![I, 9], 3), (p![J, 10], 5), (p![E, 11], 7)];
+        for (number, (point, area)) in (1..).zip(moves) {
+            assert_eq!(
+                opening.phase(),
+                Phase::Move {
+                    number,
+                    area: Some(area)
+                }
+            );
+            opening.play(point).unwrap();
+            opening.swap(false).unwrap();
+        }
+        opening
+    }
+
+    #[test]
+    fn taraguchi() {
+        let mut opening = taraguchi_four_moves();
+        assert!(matches!(
+            opening.play(p![A, 1]),
+            Err(OpeningError::OutsideArea { area: 9, .. })
+        ));
+        opening.play(p![L, 12]).unwrap();
+        assert_eq!(opening.phase(), Phase::Swap);
+        opening.swap(true).unwrap();
+        assert_eq!(opening.phase(), Phase::Free);
+
+        // or offer ten anywhere
+        let mut opening = taraguchi_four_moves();
+        let offers = p![
+            [A, 1],
+            [A, 2],
+            [A, 3],
+            [A, 4],
+            [A, 5],
+            [A, 6],
+            [A, 7],
+            [A, 8],
+            [A, 9],
+            [A, 10]
+        ];
+        opening.offer(&offers).unwrap();
+        assert_eq!(opening.phase(), Phase::Choose);
+        opening.choose(p![A, 10]).unwrap();
+        assert_eq!(opening.phase(), Phase::Free);
+    }
+}
@@ -0,0 +1,725 @@
+//! Rules of the game on top of [`BoardArr::conditions`]: which moves are legal, which rule a
+//! forbidden move breaks and when the game is over.
+//!
+//! Which rules apply is decided by a [`RuleSet`]. In renju, black moves first and may not make a
+//! double-three, a double-four or an overline, unless the same move makes a five. White has no
+//! restrictions and also wins with an overline.
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use super::bitboard::Bitboard;
+use super::{BoardArr, Point, Stone};
+use crate::errors::ParseError;
+
+/// The four directions a row can go in, the opposite directions are covered by walking both ways.
+pub(super) const DIRECTIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// The rules a game is played by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RuleSet {
+    /// Black may not make a double-three, double-four or overline. White wins with five or more.
+    #[default]
+    Renju,
+    /// Five or more in a row wins, for both.
+    Freestyle,
+    /// Exactly five in a row wins, for both. An overline doesn't win.
+    Standard,
+    /// Five or more in a row wins, unless both ends of the row are blocked by the other color.
+    Caro,
+}
+
+impl RuleSet {
+    pub const ALL: [Self; 4] = [Self::Renju, Self::Freestyle, Self::Standard, Self::Caro];
+
+    /// Returns `true` if `stone` has forbidden points, i.e black in renju.
+    #[must_use]
+    pub fn restricts(self, stone: Stone) -> bool {
+        self == Self::Renju && stone.is_black()
+    }
+
+    /// Returns `true` if six or more in a row wins for `stone`.
+    #[must_use]
+    pub fn allows_overline(self, stone: Stone) -> bool {
+        match self {
+            Self::Renju => stone.is_white(),
+            Self::Freestyle | Self::Caro => true,
+            Self::Standard => false,
+        }
+    }
+
+    /// Returns `true` if a five blocked on both ends by the other color doesn't win.
+    #[must_use]
+    pub fn blocks_five(self) -> bool {
+        self == Self::Caro
+    }
+
+    /// Returns `true` if a row of `length` stones of `stone` wins, `blocked` is if both ends of the
+    /// row are taken by the other color.
+    #[must_use]
+    pub fn wins(self, stone: Stone, length: u32, blocked: bool) -> bool {
+        match length {
+            5 => !(blocked && self.blocks_five()),
+            6.. => self.allows_overline(stone) && !(blocked && self.blocks_five()),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Renju => "renju",
+            Self::Freestyle => "freestyle",
+            Self::Standard => "standard",
+            Self::Caro => "caro",
+        })
+    }
+}
+
+impl FromStr for RuleSet {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|rules| rules.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                ParseError::Other(format!(
+                    "unknown rules {s:?}, expected one of {}",
+                    Self::ALL.map(|r| r.to_string()).join(", ")
+                ))
+            })
+    }
+}
+
+/// A rule that a forbidden black move breaks in renju.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Forbidden {
+    /// Two open threes at once, 3x3.
+    DoubleThree,
+    /// Two fours at once, 4x4.
+    DoubleFour,
+    /// Six or more stones in a row.
+    Overline,
+}
+
+impl fmt::Display for Forbidden {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::DoubleThree => "3x3",
+            Self::DoubleFour => "4x4",
+            Self::Overline => "overline",
+        })
+    }
+}
+
+/// How a game has ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    /// `winner` made a winning row of `length` stones, a five or an overline where it's allowed.
+    Win { winner: Stone, length: u32 },
+    /// Black made an overline, which is forbidden in renju, so white won.
+    Overline,
+    /// The board is full without a winner.
+    Draw,
+}
+
+impl Outcome {
+    /// The stone that won, `None` on a draw.
+    #[must_use]
+    pub fn winner(&self) -> Option<Stone> {
+        match self {
+            Self::Win { winner, .. } => Some(*winner),
+            Self::Overline => Some(Stone::White),
+            Self::Draw => None,
+        }
+    }
+}
+
+/// Why a move can't be played, see [`BoardArr::check_move`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IllegalMove {
+    #[error("there is no stone to play, the color is empty")]
+    NoStone,
+    #[error("{0} is outside the board")]
+    OutsideBoard(Point),
+    #[error("{0} is already taken")]
+    Occupied(Point),
+    #[error("{0} is forbidden for black, it makes a {1}")]
+    Forbidden(Point, Forbidden),
+    #[error("the game is already over, {0:?}")]
+    GameOver(Outcome),
+}
+
+impl BoardArr {
+    /// The stone to play next. Black starts, so it's black if both have as many stones.
+    #[must_use]
+    pub fn to_move(&self) -> Stone {
+        self.bitboard().to_move()
+    }
+
+    /// The outcome of the game under `rules`, `None` if it's still going.
+    #[must_use]
+    pub fn outcome(&self, rules: RuleSet) -> Option<Outcome> {
+        self.bitboard().outcome(rules)
+    }
+
+    /// The rule black breaks by playing on `point` in renju, `None` if the move is allowed.
+    ///
+    /// A move that makes a five is always allowed.
+    #[must_use]
+    pub fn forbidden(&self, point: Point) -> Option<Forbidden> {
+        self.bitboard().forbidden(point)
+    }
+
+    /// Check that `stone` can be played on `point` under `rules`. A null point is a pass, which is
+    /// always allowed while the game is going. [`Stone::Empty`] can't be played.
+    pub fn check_move(
+        &self,
+        point: Point,
+        stone: Stone,
+        rules: RuleSet,
+    ) -> Result<(), IllegalMove> {
+        self.bitboard().check_move(point, stone, rules)
+    }
+
+    /// Every point `stone` can be played on under `rules`, none if the game is over.
+    ///
+    /// Returns [`IllegalMove::NoStone`] if `stone` is [`Stone::Empty`].
+    pub fn legal_moves(&self, stone: Stone, rules: RuleSet) -> Result<Vec<Point>, IllegalMove> {
+        self.bitboard().legal_moves(stone, rules)
+    }
+
+    /// Returns `true` if `stone` wins under `rules` by playing on the empty `point`.
+    #[must_use]
+    pub fn wins_with(&self, point: Point, stone: Stone, rules: RuleSet) -> bool {
+        self.bitboard().wins_with(point, stone, rules)
+    }
+}
+
+impl Bitboard {
+    /// Same as [`BoardArr::to_move`].
+    #[must_use]
+    pub fn to_move(&self) -> Stone {
+        Stone::from_bool(self.stones(Stone::Black).len() <= self.stones(Stone::White).len())
+    }
+
+    /// Same as [`BoardArr::outcome`].
+    #[must_use]
+    pub fn outcome(&self, rules: RuleSet) -> Option<Outcome> {
+        let stones = self.stones(Stone::Black) | self.stones(Stone::White);
+        for point in self.points(stones) {
+            let color = self.get(point).expect("stones are on the board");
+            let (x, y) = (i64::from(point.x), i64::from(point.y));
+            for direction @ (dx, dy) in DIRECTIONS {
+                let (start, end) = self.row(point, direction, color, None);
+                // only count every row once, from its first stone
+                if start != 0 {
+                    continue;
+                }
+                let length = end as u32 + 1;
+                let opponent = Some(color.opposite());
+                let blocked = self.color_at(x - dx, y - dy) == opponent
+                    && self.color_at(x + dx * (end + 1), y + dy * (end + 1)) == opponent;
+                if rules.wins(color, length, blocked) {
+                    return Some(Outcome::Win {
+                        winner: color,
+                        length,
+                    });
+                }
+                if rules.restricts(color) && length > 5 {
+                    return Some(Outcome::Overline);
+                }
+            }
+        }
+        if self.stones(Stone::Empty).is_empty() {
+            Some(Outcome::Draw)
+        } else {
+            None
+        }
+    }
+
+    /// Same as [`BoardArr::forbidden`].
+    #[must_use]
+    pub fn forbidden(&self, point: Point) -> Option<Forbidden> {
+        let threes = self
+            .renju_conditions(Stone::Black, Some(&[point]))
+            .forbidden;
+        self.forbidden_with(point, &threes)
+    }
+
+    /// Like [`Bitboard::forbidden`], with the points [`Bitboard::renju_conditions`] found forbidden.
+    pub(super) fn forbidden_with(
+        &self,
+        point: Point,
+        forbidden: &BTreeSet<Point>,
+    ) -> Option<Forbidden> {
+        if self.get(point) != Some(Stone::Empty) {
+            return None;
+        }
+        let lengths = DIRECTIONS.map(|direction| {
+            let (start, end) = self.row(point, direction, Stone::Black, None);
+            end - start + 1
+        });
+        if lengths.contains(&5) {
+            None
+        } else if lengths.iter().any(|length| *length > 5) {
+            Some(Forbidden::Overline)
+        } else if self.fours(point, Stone::Black) > 1 {
+            Some(Forbidden::DoubleFour)
+        } else if forbidden.contains(&point) {
+            Some(Forbidden::DoubleThree)
+        } else {
+            None
+        }
+    }
+
+    /// Same as [`BoardArr::check_move`].
+    pub fn check_move(
+        &self,
+        point: Point,
+        stone: Stone,
+        rules: RuleSet,
+    ) -> Result<(), IllegalMove> {
+        if stone.is_empty() {
+            return Err(IllegalMove::NoStone);
+        }
+        if let Some(outcome) = self.outcome(rules) {
+            return Err(IllegalMove::GameOver(outcome));
+        }
+        if point.is_null {
+            return Ok(());
+        }
+        match self.get(point) {
+            None => return Err(IllegalMove::OutsideBoard(point)),
+            Some(color) if !color.is_empty() => return Err(IllegalMove::Occupied(point)),
+            Some(_) => {}
+        }
+        if !rules.restricts(stone) {
+            return Ok(());
+        }
+        match self.forbidden(point) {
+            Some(rule) => Err(IllegalMove::Forbidden(point, rule)),
+            None => Ok(()),
+        }
+    }
+
+    /// Same as [`BoardArr::legal_moves`].
+    pub fn legal_moves(&self, stone: Stone, rules: RuleSet) -> Result<Vec<Point>, IllegalMove> {
+        if stone.is_empty() {
+            return Err(IllegalMove::NoStone);
+        }
+        if self.outcome(rules).is_some() {
+            return Ok(vec![]);
+        }
+        let empty = self.points(self.stones(Stone::Empty));
+        if !rules.restricts(stone) {
+            return Ok(empty.collect());
+        }
+        let forbidden = self.renju_conditions(stone, None).forbidden;
+        Ok(empty
+            .filter(|point| self.forbidden_with(*point, &forbidden).is_none())
+            .collect())
+    }
+
+    /// Same as [`BoardArr::wins_with`].
+    #[must_use]
+    pub fn wins_with(&self, point: Point, stone: Stone, rules: RuleSet) -> bool {
+        if self.get(point) != Some(Stone::Empty) {
+            return false;
+        }
+        let (x, y) = (i64::from(point.x), i64::from(point.y));
+        let opponent = Some(stone.opposite());
+        DIRECTIONS.into_iter().any(|direction @ (dx, dy)| {
+            let (start, end) = self.row(point, direction, stone, None);
+            let blocked = self.color_at(x + dx * (start - 1), y + dy * (start - 1)) == opponent
+                && self.color_at(x + dx * (end + 1), y + dy * (end + 1)) == opponent;
+            rules.wins(stone, (end - start + 1) as u32, blocked)
+        })
+    }
+
+    /// The color on (`x`, `y`), `None` if it's outside the board.
+    pub(super) fn color_at(&self, x: i64, y: i64) -> Option<Stone> {
+        let (x, y) = (u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+        self.get(Point::new(x, y))
+    }
+
+    /// The row of `stone` through `point` in `direction`, as if `point` and `extra` were `stone`.
+    ///
+    /// Returns how many steps the row goes back and forward from `point`, as `(-back, forward)`.
+    fn row(
+        &self,
+        point: Point,
+        (dx, dy): (i64, i64),
+        stone: Stone,
+        extra: Option<(i64, i64)>,
+    ) -> (i64, i64) {
+        let (x, y) = (i64::from(point.x), i64::from(point.y));
+        let is_stone = |step: i64| {
+            let (x, y) = (x + dx * step, y + dy * step);
+            extra == Some((x, y)) || self.color_at(x, y) == Some(stone)
+        };
+        let back = (1i64..).take_while(|step| is_stone(-step)).count() as i64;
+        let forward = (1i64..).take_while(|step| is_stone(*step)).count() as i64;
+        (-back, forward)
+    }
+
+    /// Count the fours `stone` would make by playing on `point`, i.e the different rows of four that
+    /// can become a five with one more stone.
+    fn fours(&self, point: Point, stone: Stone) -> usize {
+        let (x, y) = (i64::from(point.x), i64::from(point.y));
+        let mut fours = BTreeSet::new();
+        for direction @ (dx, dy) in DIRECTIONS {
+            for step in (-4..=4).filter(|step| *step != 0) {
+                let empty = (x + dx * step, y + dy * step);
+                if self.color_at(empty.0, empty.1) != Some(Stone::Empty) {
+                    continue;
+                }
+                let (start, end) = self.row(point, direction, stone, Some(empty));
+                let five = if stone.is_black() {
+                    end - start + 1 == 5
+                } else {
+                    end - start + 1 >= 5
+                };
+                if five {
+                    // a straight four can become five on both ends, but it's the same four
+                    let stones = (start..=end).filter(|s| *s != step).collect::<Vec<_>>();
+                    fours.insert((direction, stones));
+                }
+            }
+        }
+        fours.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use test_log::test;
+
+    fn board(black: &[Point], white: &[Point]) -> BoardArr {
+        let mut board = BoardArr::new(15);
+        for point in black {
+            board.set_point(*point, Stone::Black).unwrap();
+        }
+        for point in white {
+            board.set_point(*point, Stone::White).unwrap();
+        }
+        board
+    }
+
+    fn row(y: u32, xs: &[u32]) -> Vec<Point> {
+        xs.iter().map(|x| Point::new(*x, y)).collect()
+    }
+
+    #[test]
+    fn rule_set_from_str() {
+        for rules in RuleSet::ALL {
+            assert_eq!(rules.to_string().parse::<RuleSet>().unwrap(), rules);
+        }
+        assert_eq!("Caro".parse::<RuleSet>().unwrap(), RuleSet::Caro);
+        assert!("go".parse::<RuleSet>().is_err());
+        assert_eq!(RuleSet::default(), RuleSet::Renju);
+    }
+
+    mod renju {
+        use super::*;
+        use test_log::test;
+
+        #[test]
+        fn outcome() {
+            assert_eq!(BoardArr::new(15).outcome(RuleSet::Renju), None);
+            let five = board(&row(7, &[2, 3, 4, 5, 6]), &row(8, &[2, 3, 4, 5]));
+            assert_eq!(
+                five.outcome(RuleSet::Renju),
+                Some(Outcome::Win {
+                    winner: Stone::Black,
+                    length: 5
+                })
+            );
+            let five = board(
+                &row(7, &[2, 3, 4, 5]),
+                &p![[A, 1], [B, 2], [C, 3], [D, 4], [E, 5]],
+            );
+            assert_eq!(
+                five.outcome(RuleSet::Renju),
+                Some(Outcome::Win {
+                    winner: Stone::White,
+                    length: 5
+                })
+            );
+            let overline = board(&row(7, &[2, 3, 4, 5]), &row(8, &[0, 1, 2, 3, 4, 5]));
+            assert_eq!(
+                overline.outcome(RuleSet::Renju),
+                Some(Outcome::Win {
+                    winner: Stone::White,
+                    length: 6
+                })
+            );
+            assert_eq!(
+                overline.outcome(RuleSet::Renju).unwrap().winner(),
+                Some(Stone::White)
+            );
+            let overline = board(&row(7, &[2, 3, 4, 5, 6, 7]), &[]);
+            assert_eq!(overline.outcome(RuleSet::Renju), Some(Outcome::Overline));
+            assert_eq!(
+                overline.outcome(RuleSet::Renju).unwrap().winner(),
+                Some(Stone::White)
+            );
+
+            let mut full = BoardArr::new(4);
+            for idx in 0..16 {
+                full.set_point(Point::from_1d(idx, 4), Stone::from_bool(idx % 3 == 0))
+                    .unwrap();
+            }
+            assert_eq!(full.outcome(RuleSet::Renju), Some(Outcome::Draw));
+            assert_eq!(full.outcome(RuleSet::Renju).unwrap().winner(), None);
+        }
+
+        #[test]
+        fn forbidden() {
+            // from `evaluator::tests::test_condition`
+            let double_three = board(&p![[H, 8], [G, 8], [G, 9], [H, 10]], &[]);
+            assert_eq!(
+                double_three.forbidden(p![F, 8]),
+                Some(Forbidden::DoubleThree)
+            );
+            assert_eq!(double_three.forbidden(p![J, 8]), None);
+
+            let mut stones = row(7, &[4, 5, 6]);
+            stones.extend([Point::new(7, 4), Point::new(7, 5), Point::new(7, 6)]);
+            let double_four = board(&stones, &[]);
+            assert_eq!(
+                double_four.forbidden(Point::new(7, 7)),
+                Some(Forbidden::DoubleFour)
+            );
+            // two fours in the same row
+            let double_four = board(&row(7, &[2, 3, 4, 8, 9, 10]), &[]);
+            assert_eq!(
+                double_four.forbidden(Point::new(6, 7)),
+                Some(Forbidden::DoubleFour)
+            );
+            // a straight four is only one four
+            let straight_four = board(&row(7, &[4, 5, 6]), &[]);
+            assert_eq!(straight_four.forbidden(Point::new(7, 7)), None);
+
+            let overline = board(&row(7, &[2, 3, 4, 6, 7]), &[]);
+            assert_eq!(
+                overline.forbidden(Point::new(5, 7)),
+                Some(Forbidden::Overline)
+            );
+            // a five wins even if it also makes a four elsewhere
+            let mut stones = row(7, &[2, 3, 4, 6]);
+            stones.extend([Point::new(5, 4), Point::new(5, 5), Point::new(5, 6)]);
+            let five = board(&stones, &[]);
+            assert_eq!(five.forbidden(Point::new(5, 7)), None);
+        }
+
+        #[test]
+        fn check_move() {
+            let board = board(&p![[H, 8], [G, 8], [G, 9], [H, 10]], &p![[A, 1], [O, 15]]);
+            assert_eq!(board.to_move(), Stone::White);
+            assert_eq!(
+                board.check_move(p![F, 8], Stone::White, RuleSet::Renju),
+                Ok(())
+            );
+            assert_eq!(
+                board.check_move(p![F, 8], Stone::Black, RuleSet::Renju),
+                Err(IllegalMove::Forbidden(p![F, 8], Forbidden::DoubleThree))
+            );
+            assert_eq!(
+                board.check_move(p![H, 8], Stone::White, RuleSet::Renju),
+                Err(IllegalMove::Occupied(p![H, 8]))
+            );
+            assert_eq!(
+                board.check_move(Point::new(15, 0), Stone::White, RuleSet::Renju),
+                Err(IllegalMove::OutsideBoard(Point::new(15, 0)))
+            );
+            assert_eq!(
+                board.check_move(Point::null(), Stone::White, RuleSet::Renju),
+                Ok(())
+            );
+
+            let five = self::board(&row(7, &[2, 3, 4, 5, 6]), &row(8, &[2, 3, 4, 5]));
+            assert_eq!(
+                five.check_move(p![H, 1], Stone::White, RuleSet::Renju),
+                Err(IllegalMove::GameOver(Outcome::Win {
+                    winner: Stone::Black,
+                    length: 5
+                }))
+            );
+            assert!(five
+                .legal_moves(Stone::White, RuleSet::Renju)
+                .unwrap()
+                .is_empty());
+            assert_eq!(
+                five.check_move(p![H, 1], Stone::Empty, RuleSet::Renju),
+                Err(IllegalMove::NoStone)
+            );
+            assert_eq!(
+                five.legal_moves(Stone::Empty, RuleSet::Renju),
+                Err(IllegalMove::NoStone)
+            );
+        }
+
+        #[test]
+        fn legal_moves() {
+            let empty = BoardArr::new(15);
+            assert_eq!(empty.to_move(), Stone::Black);
+            assert_eq!(
+                empty
+                    .legal_moves(Stone::Black, RuleSet::Renju)
+                    .unwrap()
+                    .len(),
+                15 * 15
+            );
+
+            let board = board(&p![[H, 8], [G, 8], [G, 9], [H, 10]], &p![[A, 1], [O, 15]]);
+            let black = board.legal_moves(Stone::Black, RuleSet::Renju).unwrap();
+            assert_eq!(black.len(), 15 * 15 - 6 - 1);
+            assert!(!black.contains(&p![F, 8]));
+            let white = board.legal_moves(Stone::White, RuleSet::Renju).unwrap();
+            assert_eq!(white.len(), 15 * 15 - 6);
+            assert!(white.contains(&p![F, 8]));
+        }
+    }
+
+    #[test]
+    fn wins_with() {
+        let board = board(&row(7, &[2, 3, 4, 5]), &row(8, &[2, 3, 4, 5, 7, 8]));
+        assert!(board.wins_with(Point::new(6, 7), Stone::Black, RuleSet::Renju));
+        assert!(board.wins_with(Point::new(1, 7), Stone::Black, RuleSet::Renju));
+        assert!(!board.wins_with(Point::new(7, 7), Stone::Black, RuleSet::Renju));
+        // an overline, which only wins for white in renju
+        assert!(board.wins_with(Point::new(6, 8), Stone::White, RuleSet::Renju));
+        assert!(!board.wins_with(Point::new(6, 8), Stone::White, RuleSet::Standard));
+        assert!(!board.wins_with(Point::new(2, 7), Stone::Black, RuleSet::Renju));
+    }
+
+    mod freestyle {
+        use super::*;
+        use test_log::test;
+
+        #[test]
+        fn outcome() {
+            let five = board(&row(7, &[2, 3, 4, 5, 6]), &row(8, &[2, 3, 4, 5]));
+            assert_eq!(
+                five.outcome(RuleSet::Freestyle).unwrap().winner(),
+                Some(Stone::Black)
+            );
+            let overline = board(&row(7, &[2, 3, 4, 5, 6, 7]), &row(8, &[2, 3, 4, 5]));
+            assert_eq!(
+                overline.outcome(RuleSet::Freestyle),
+                Some(Outcome::Win {
+                    winner: Stone::Black,
+                    length: 6
+                })
+            );
+        }
+
+        #[test]
+        fn nothing_is_forbidden() {
+            let board = board(&p![[H, 8], [G, 8], [G, 9], [H, 10]], &p![[A, 1], [O, 15]]);
+            assert_eq!(
+                board.check_move(p![F, 8], Stone::Black, RuleSet::Freestyle),
+                Ok(())
+            );
+            let overline = self::board(&row(7, &[2, 3, 4, 6, 7]), &row(8, &[2, 3, 4, 6, 7]));
+            assert_eq!(
+                overline.check_move(Point::new(5, 7), Stone::Black, RuleSet::Freestyle),
+                Ok(())
+            );
+            assert_eq!(
+                board
+                    .legal_moves(Stone::Black, RuleSet::Freestyle)
+                    .unwrap()
+                    .len(),
+                15 * 15 - 6
+            );
+        }
+    }
+
+    mod standard {
+        use super::*;
+        use test_log::test;
+
+        #[test]
+        fn outcome() {
+            let five = board(&row(7, &[2, 3, 4, 5]), &row(8, &[2, 3, 4, 5, 6]));
+            assert_eq!(
+                five.outcome(RuleSet::Standard).unwrap().winner(),
+                Some(Stone::White)
+            );
+            // an overline doesn't win for either color
+            let overline = board(&row(7, &[2, 3, 4, 5, 6, 7]), &row(8, &[2, 3, 4, 5]));
+            assert_eq!(overline.outcome(RuleSet::Standard), None);
+            let overline = board(&row(7, &[2, 3, 4, 5]), &row(8, &[0, 1, 2, 3, 4, 5]));
+            assert_eq!(overline.outcome(RuleSet::Standard), None);
+        }
+
+        #[test]
+        fn overline_is_allowed_but_not_a_win() {
+            let board = board(&row(7, &[2, 3, 4, 6, 7]), &row(8, &[2, 3, 4, 6, 7]));
+            assert_eq!(
+                board.check_move(Point::new(5, 7), Stone::Black, RuleSet::Standard),
+                Ok(())
+            );
+            let mut after = board.clone();
+            after.set_point(Point::new(5, 7), Stone::Black).unwrap();
+            assert_eq!(after.outcome(RuleSet::Standard), None);
+            assert!(!after
+                .legal_moves(Stone::White, RuleSet::Standard)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    mod caro {
+        use super::*;
+        use test_log::test;
+
+        #[test]
+        fn outcome() {
+            let open = board(&row(7, &[2, 3, 4, 5, 6]), &row(8, &[2, 3, 4, 5]));
+            assert_eq!(
+                open.outcome(RuleSet::Caro).unwrap().winner(),
+                Some(Stone::Black)
+            );
+            // blocked on one end still wins
+            let one_end = board(&row(7, &[2, 3, 4, 5, 6]), &row(7, &[1]));
+            assert_eq!(
+                one_end.outcome(RuleSet::Caro).unwrap().winner(),
+                Some(Stone::Black)
+            );
+            // blocked on both ends doesn't
+            let blocked = board(&row(7, &[2, 3, 4, 5, 6]), &row(7, &[1, 7]));
+            assert_eq!(blocked.outcome(RuleSet::Caro), None);
+            assert!(blocked.outcome(RuleSet::Freestyle).is_some());
+            // the edge of the board doesn't block
+            let edge = board(&row(7, &[0, 1, 2, 3, 4]), &row(7, &[5]));
+            assert_eq!(
+                edge.outcome(RuleSet::Caro).unwrap().winner(),
+                Some(Stone::Black)
+            );
+            let overline = board(&row(7, &[2, 3, 4, 5, 6, 7]), &[]);
+            assert_eq!(
+                overline.outcome(RuleSet::Caro).unwrap().winner(),
+                Some(Stone::Black)
+            );
+        }
+
+        #[test]
+        fn nothing_is_forbidden() {
+            let board = board(&p![[H, 8], [G, 8], [G, 9], [H, 10]], &p![[A, 1], [O, 15]]);
+            assert_eq!(
+                board.check_move(p![F, 8], Stone::Black, RuleSet::Caro),
+                Ok(())
+            );
+        }
+    }
+}
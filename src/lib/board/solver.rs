@@ -0,0 +1,646 @@
+//! Threat space search, finding a victory by continuous fours (VCF) or by continuous fours and
+//! threes (VCT).
+//!
+//! The attacker only plays moves that threaten to win, so the defender's answers are limited. After
+//! a four the defender has to block the five, or lose. After an open three the defender can block
+//! the straight four it threatens, or play a four of their own. A line is a win if the attacker wins
+//! against every answer.
+//!
+//! Black's forbidden points are respected for both sides when the rules restrict black. A four
+//! that black can only block on a forbidden point wins for white, which shows up as a pass in the
+//! line.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::bitboard::Bitboard;
+use super::evaluator::RenjuCondition;
+use super::rules::RuleSet;
+use super::{Board, BoardArr, MoveIndex, Point, Stone};
+
+/// Which threats the attacker may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Threats {
+    /// Victory by continuous fours, every attacking move is a four.
+    Vcf,
+    /// Victory by continuous threats, every attacking move is a four or an open three.
+    Vct,
+}
+
+impl std::fmt::Display for Threats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Vcf => "VCF",
+            Self::Vct => "VCT",
+        })
+    }
+}
+
+/// What a search found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    /// The attacker wins with this line, the attacker's moves alternating with the defender's and
+    /// ending with the winning move. Against a VCT the defender may have other answers, the line
+    /// follows the first one.
+    Win(Vec<Point>),
+    /// There is no win within the depth.
+    NoWin,
+    /// The time ran out before the search was done.
+    Unknown,
+}
+
+impl Solution {
+    /// The winning line, if there is one.
+    #[must_use]
+    pub fn line(&self) -> Option<&[Point]> {
+        match self {
+            Self::Win(line) => Some(line),
+            Self::NoWin | Self::Unknown => None,
+        }
+    }
+}
+
+/// Searches for a [`Threats`] win, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Solver {
+    threats: Threats,
+    rules: RuleSet,
+    /// The most attacking moves in a line.
+    depth: u32,
+    time_limit: Option<Duration>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+    /// Positions with no win, by their hash, the attacker and the threats, and the depth that was
+    /// searched.
+    no_win: HashMap<(u64, Stone, Threats), u32>,
+}
+
+impl Solver {
+    /// Default depth of a VCF search, in attacking moves.
+    pub const VCF_DEPTH: u32 = 20;
+    /// Default depth of a VCT search, in attacking moves.
+    pub const VCT_DEPTH: u32 = 4;
+
+    #[must_use]
+    pub fn new(threats: Threats, rules: RuleSet) -> Self {
+        Self {
+            threats,
+            rules,
+            depth: match threats {
+                Threats::Vcf => Self::VCF_DEPTH,
+                Threats::Vct => Self::VCT_DEPTH,
+            },
+            time_limit: None,
+            deadline: None,
+            timed_out: false,
+            no_win: HashMap::new(),
+        }
+    }
+
+    /// Search at most `depth` attacking moves deep.
+    #[must_use]
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Give up with [`Solution::Unknown`] after `limit`.
+    #[must_use]
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Search for a win for `attacker` in `position`, with `attacker` to move.
+    pub fn solve(&mut self, position: &BoardArr, attacker: Stone) -> Solution {
+        assert!(!attacker.is_empty());
+        self.deadline = self.time_limit.map(|limit| Instant::now() + limit);
+        self.timed_out = false;
+        self.no_win.clear();
+        if position.outcome(self.rules).is_some() {
+            return Solution::NoWin;
+        }
+        let mut position = Bitboard::from(position);
+        let found = match self.threats {
+            Threats::Vcf => self.vcf(&mut position, attacker, self.depth),
+            Threats::Vct => self.vct(&mut position, attacker, self.depth),
+        };
+        match found {
+            Some(line) => Solution::Win(line),
+            None if self.timed_out => Solution::Unknown,
+            None => Solution::NoWin,
+        }
+    }
+
+    /// Search for a win for the side to move in the position after `from` and insert it as a branch.
+    ///
+    /// The first move of the line is commented with the kind of win and the last with `win`, unless
+    /// they were in the tree with a comment already.
+    /// Returns the last move of the line, `None` if there was no win.
+    pub fn solve_and_insert(
+        &mut self,
+        board: &mut Board,
+        from: MoveIndex,
+    ) -> Result<Option<MoveIndex>, crate::errors::ParseError> {
+        let (position, _) = board.as_board(&from)?;
+        let attacker = board.side_to_move(&from);
+        let Solution::Win(line) = self.solve(&position, attacker) else {
+            return Ok(None);
+        };
+        let first = board.get_or_insert_points(from, line.first().copied());
+        let last = board.get_or_insert_points(first, line.iter().skip(1).copied());
+        let comment = format!("{} in {}", self.threats, line.len().div_ceil(2));
+        for (node, comment) in [(first, comment), (last, "win".to_owned())] {
+            if let Some(marker) = board
+                .get_move_mut(node)
+                .filter(|marker| marker.oneline_comment.is_none())
+            {
+                marker.set_oneline_comment(comment);
+            }
+        }
+        Ok(Some(last))
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.timed_out = true;
+        }
+        self.timed_out
+    }
+
+    fn vcf(&mut self, position: &mut Bitboard, attacker: Stone, depth: u32) -> Option<Vec<Point>> {
+        if let Some(five) = self.wins(position, attacker).first() {
+            return Some(vec![*five]);
+        }
+        if depth == 0
+            || self.out_of_time()
+            || self.searched(position, attacker, Threats::Vcf, depth)
+        {
+            return None;
+        }
+        let candidates = self.must_block(position, attacker)?;
+        for point in candidates.unwrap_or_else(|| self.fours(position, attacker)) {
+            if let Some(line) = self.after_four(position, attacker, point, depth, Self::vcf) {
+                return Some(line);
+            }
+        }
+        self.no_win(position, attacker, Threats::Vcf, depth);
+        None
+    }
+
+    fn vct(&mut self, position: &mut Bitboard, attacker: Stone, depth: u32) -> Option<Vec<Point>> {
+        if let Some(five) = self.wins(position, attacker).first() {
+            return Some(vec![*five]);
+        }
+        if depth == 0
+            || self.out_of_time()
+            || self.searched(position, attacker, Threats::Vct, depth)
+        {
+            return None;
+        }
+        if let Some(line) = self.vcf(position, attacker, Self::VCF_DEPTH) {
+            return Some(line);
+        }
+        let blocks = self.must_block(position, attacker)?;
+        let fours = self.fours(position, attacker);
+        let threes = self.threes(position, attacker);
+        let candidates = fours
+            .iter()
+            .chain(&threes)
+            .copied()
+            .filter(|point| blocks.as_ref().is_none_or(|blocks| blocks.contains(point)));
+        for point in candidates.collect::<Vec<_>>() {
+            let line = if fours.contains(&point) {
+                self.after_four(position, attacker, point, depth, Self::vct)
+            } else {
+                self.after_three(position, attacker, point, depth)
+            };
+            if line.is_some() {
+                return line;
+            }
+        }
+        self.no_win(position, attacker, Threats::Vct, depth);
+        None
+    }
+
+    /// Play the four on `point` and search on with `search` after the defender blocks it.
+    fn after_four(
+        &mut self,
+        position: &mut Bitboard,
+        attacker: Stone,
+        point: Point,
+        depth: u32,
+        search: fn(&mut Self, &mut Bitboard, Stone, u32) -> Option<Vec<Point>>,
+    ) -> Option<Vec<Point>> {
+        let defender = attacker.opposite();
+        position.set_point(point, attacker);
+        let fives = self.wins(position, attacker);
+        let blocks = fives
+            .iter()
+            .copied()
+            .filter(|five| self.can_play(position, *five, defender))
+            .collect::<Vec<_>>();
+        let line = match (fives.as_slice(), blocks.as_slice()) {
+            // not a four after all
+            ([], _) => None,
+            // the defender can't block every five
+            ([first, ..], []) => Some(vec![point, Point::null(), *first]),
+            (_, [block]) if fives.len() > 1 => {
+                let win = fives.iter().find(|five| *five != block)?;
+                Some(vec![point, *block, *win])
+            }
+            (_, [block]) => {
+                position.set_point(*block, defender);
+                let line = search(self, position, attacker, depth - 1).map(|rest| {
+                    let mut line = vec![point, *block];
+                    line.extend(rest);
+                    line
+                });
+                position.set_point(*block, Stone::Empty);
+                line
+            }
+            (_, [block, ..]) => {
+                let win = fives.iter().find(|five| *five != block)?;
+                Some(vec![point, *block, *win])
+            }
+        };
+        position.set_point(point, Stone::Empty);
+        line
+    }
+
+    /// Play the three on `point` and search on after every answer of the defender.
+    fn after_three(
+        &mut self,
+        position: &mut Bitboard,
+        attacker: Stone,
+        point: Point,
+        depth: u32,
+    ) -> Option<Vec<Point>> {
+        let defender = attacker.opposite();
+        position.set_point(point, attacker);
+        let mut answers = self.defences(position, attacker);
+        for four in self.fours(position, defender) {
+            if !answers.contains(&four) {
+                answers.push(four);
+            }
+        }
+        let mut principal = None;
+        for answer in answers {
+            position.set_point(answer, defender);
+            let line = self.vct(position, attacker, depth - 1);
+            position.set_point(answer, Stone::Empty);
+            match line {
+                Some(rest) => {
+                    principal.get_or_insert_with(|| {
+                        let mut line = vec![point, answer];
+                        line.extend(rest);
+                        line
+                    });
+                }
+                None => {
+                    principal = None;
+                    break;
+                }
+            }
+        }
+        position.set_point(point, Stone::Empty);
+        principal
+    }
+
+    /// The points where the defender has a five. Returns `None` if the attacker can't stop them,
+    /// or the points to block them on, which the attacker has to play.
+    #[allow(clippy::option_option)]
+    fn must_block(&self, position: &Bitboard, attacker: Stone) -> Option<Option<Vec<Point>>> {
+        match self.wins(position, attacker.opposite()).as_slice() {
+            [] => Some(None),
+            [five] => Some(Some(vec![*five])),
+            _ => None,
+        }
+    }
+
+    /// The points where `stone` wins right away.
+    fn wins(&self, position: &Bitboard, stone: Stone) -> Vec<Point> {
+        position
+            .points(position.fives(stone))
+            .filter(|point| position.wins_with(*point, stone, self.rules))
+            .collect()
+    }
+
+    /// The points where `stone` makes a four.
+    fn fours(&self, position: &Bitboard, stone: Stone) -> Vec<Point> {
+        let conditions = position.conditions(stone, self.rules, None);
+        let mut fours = conditions
+            .conditions
+            .iter()
+            .filter(|c| {
+                matches!(
+                    c,
+                    RenjuCondition::StraightFour { .. }
+                        | RenjuCondition::ClosedFour { .. }
+                        | RenjuCondition::BrokenFour { .. }
+                )
+            })
+            .map(|c| *c.place())
+            .filter(|point| !conditions.forbidden.contains(point))
+            .collect::<Vec<_>>();
+        fours.sort();
+        fours.dedup();
+        fours
+    }
+
+    /// The points where `stone` makes an open three, but not a four.
+    fn threes(&self, position: &Bitboard, stone: Stone) -> Vec<Point> {
+        let conditions = position.conditions(stone, self.rules, None);
+        let mut threes = conditions
+            .threes
+            .iter()
+            .map(|(c, _)| *c.place())
+            .filter(|point| !conditions.forbidden.contains(point))
+            .collect::<Vec<_>>();
+        threes.sort();
+        threes.dedup();
+        threes
+    }
+
+    /// The points where the defender stops every straight four the attacker could make.
+    ///
+    /// If no point stops them all, a point that stops some of them, the attacker makes one of the
+    /// others next.
+    fn defences(&self, position: &mut Bitboard, attacker: Stone) -> Vec<Point> {
+        let defender = attacker.opposite();
+        let straight_fours = |position: &Bitboard| {
+            position
+                .conditions(attacker, self.rules, None)
+                .conditions
+                .into_iter()
+                .filter(|c| matches!(c, RenjuCondition::StraightFour { .. }))
+                .collect::<Vec<_>>()
+        };
+        // a defence has to be on one of the rows the straight fours are made in
+        let mut candidates = straight_fours(position)
+            .iter()
+            .flat_map(|four| {
+                let stones = four.stones();
+                let (first, last) = (stones[0], stones[stones.len() - 1]);
+                let (dx, dy) = (
+                    i64::from(last.x) - i64::from(first.x),
+                    i64::from(last.y) - i64::from(first.y),
+                );
+                let step = (dx.signum(), dy.signum());
+                (-2..stones.len() as i64 + 2).filter_map(move |i| {
+                    let x = u32::try_from(i64::from(first.x) + step.0 * i).ok()?;
+                    let y = u32::try_from(i64::from(first.y) + step.1 * i).ok()?;
+                    Some(Point::new(x, y))
+                })
+            })
+            .filter(|point| self.can_play(position, *point, defender))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+        let partial = candidates.first().copied();
+        candidates.retain(|point| {
+            position.set_point(*point, defender);
+            let stops = straight_fours(position).is_empty();
+            position.set_point(*point, Stone::Empty);
+            stops
+        });
+        if candidates.is_empty() {
+            candidates.extend(partial);
+        }
+        candidates
+    }
+
+    /// Returns `true` if `stone` may play on `point`.
+    fn can_play(&self, position: &Bitboard, point: Point, stone: Stone) -> bool {
+        position.get(point) == Some(Stone::Empty)
+            && !(self.rules.restricts(stone) && position.forbidden(point).is_some())
+    }
+
+    /// Returns `true` if the position is known to have no win at `depth`.
+    fn searched(&self, position: &Bitboard, attacker: Stone, threats: Threats, depth: u32) -> bool {
+        self.no_win
+            .get(&(position.zobrist().hash(), attacker, threats))
+            .is_some_and(|searched| *searched >= depth)
+    }
+
+    fn no_win(&mut self, position: &Bitboard, attacker: Stone, threats: Threats, depth: u32) {
+        // a search cut short by the time limit didn't prove anything
+        if !self.timed_out {
+            let searched = self
+                .no_win
+                .entry((position.zobrist().hash(), attacker, threats))
+                .or_default();
+            *searched = (*searched).max(depth);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::rules::Forbidden;
+    use test_log::test;
+
+    /// Replay `line` and check that it's a win for `attacker` made of fours.
+    fn check_vcf(position: &BoardArr, attacker: Stone, line: &[Point]) {
+        let mut position = position.clone();
+        let mut stone = attacker;
+        for (i, point) in line.iter().enumerate() {
+            if !point.is_null {
+                assert_eq!(position.check_move(*point, stone, RuleSet::Renju), Ok(()));
+                position.set_point(*point, stone).unwrap();
+            }
+            if stone == attacker && i + 1 < line.len() {
+                let fives = Solver::new(Threats::Vcf, RuleSet::Renju)
+                    .wins(&Bitboard::from(&position), attacker);
+                assert!(!fives.is_empty(), "{point} is not a four");
+            }
+            stone = stone.opposite();
+        }
+        assert_eq!(
+            position.outcome(RuleSet::Renju).and_then(|o| o.winner()),
+            Some(attacker)
+        );
+    }
+
+    /// White has a closed three across, a closed three down and a diagonal that makes the second
+    /// three a four.
+    fn vcf_position() -> BoardArr {
+        BoardArr::with_stones(
+            &[(2, 7), (6, 3), (2, 10)],
+            &[(3, 7), (4, 7), (5, 7), (6, 4), (6, 5), (3, 9), (4, 8)],
+        )
+    }
+
+    #[test]
+    fn five() {
+        let position = BoardArr::with_stones(&[(0, 0)], &[(3, 7), (4, 7), (5, 7), (6, 7)]);
+        let solution = Solver::new(Threats::Vcf, RuleSet::Renju).solve(&position, Stone::White);
+        assert!(matches!(solution.line(), Some([_])));
+    }
+
+    #[test]
+    fn vcf() {
+        let position = vcf_position();
+        let mut solver = Solver::new(Threats::Vcf, RuleSet::Renju);
+        let Solution::Win(line) = solver.solve(&position, Stone::White) else {
+            panic!("no vcf found");
+        };
+        check_vcf(&position, Stone::White, &line);
+        assert!(line.len() >= 5);
+        assert_eq!(
+            Solver::new(Threats::Vcf, RuleSet::Renju)
+                .with_depth(1)
+                .solve(&position, Stone::White),
+            Solution::NoWin
+        );
+        // black has nothing
+        assert_eq!(solver.solve(&position, Stone::Black), Solution::NoWin);
+    }
+
+    #[test]
+    fn forbidden_moves() {
+        let stones = [(3, 7), (4, 7), (5, 7), (6, 4), (6, 5), (6, 6)];
+        let blocks = [(2, 7), (6, 3)];
+        // the double four on (6, 7) wins for white
+        let white = BoardArr::with_stones(&blocks, &stones);
+        let line = Solver::new(Threats::Vcf, RuleSet::Renju)
+            .solve(&white, Stone::White)
+            .line()
+            .map(<[_]>::to_vec)
+            .unwrap();
+        check_vcf(&white, Stone::White, &line);
+        // but is forbidden for black
+        let black = BoardArr::with_stones(&stones, &blocks);
+        let mut solver = Solver::new(Threats::Vcf, RuleSet::Renju);
+        assert!(solver
+            .solve(&black, Stone::Black)
+            .line()
+            .is_none_or(|line| line[0] != Point::new(6, 7)));
+        // unless the rules allow it
+        let mut freestyle = Solver::new(Threats::Vcf, RuleSet::Freestyle);
+        assert!(freestyle.solve(&black, Stone::Black).line().is_some());
+
+        // a four black can only block with a double four wins for white
+        let position = BoardArr::with_stones(
+            &[(3, 7), (4, 7), (5, 7), (7, 4), (7, 5), (7, 6), (11, 11)],
+            &[(2, 7), (7, 3), (8, 8), (9, 9), (10, 10)],
+        );
+        assert_eq!(
+            position.forbidden(Point::new(7, 7)),
+            Some(Forbidden::DoubleFour)
+        );
+        let line = Solver::new(Threats::Vcf, RuleSet::Renju)
+            .solve(&position, Stone::White)
+            .line()
+            .map(<[_]>::to_vec)
+            .unwrap();
+        check_vcf(&position, Stone::White, &line);
+        assert_eq!(line, [Point::new(6, 6), Point::null(), Point::new(7, 7)]);
+    }
+
+    #[test]
+    fn vct() {
+        // white makes a double three on (7, 7)
+        let position =
+            BoardArr::with_stones(&[(0, 0), (14, 14)], &[(5, 7), (6, 7), (7, 5), (7, 6)]);
+        assert_eq!(
+            Solver::new(Threats::Vcf, RuleSet::Renju).solve(&position, Stone::White),
+            Solution::NoWin
+        );
+        let mut solver = Solver::new(Threats::Vct, RuleSet::Renju).with_depth(3);
+        let Solution::Win(line) = solver.solve(&position, Stone::White) else {
+            panic!("no vct found");
+        };
+        let mut replay = position.clone();
+        let mut stone = Stone::White;
+        for point in &line {
+            if !point.is_null {
+                assert_eq!(replay.check_move(*point, stone, RuleSet::Renju), Ok(()));
+                replay.set_point(*point, stone).unwrap();
+            }
+            stone = stone.opposite();
+        }
+        assert_eq!(
+            replay.outcome(RuleSet::Renju).and_then(|o| o.winner()),
+            Some(Stone::White)
+        );
+        // a single two is not enough
+        let position = BoardArr::with_stones(&[(0, 0)], &[(5, 7), (6, 7)]);
+        assert_eq!(
+            Solver::new(Threats::Vct, RuleSet::Renju)
+                .with_depth(2)
+                .solve(&position, Stone::White),
+            Solution::NoWin
+        );
+    }
+
+    #[test]
+    fn time_limit() {
+        let position = BoardArr::with_stones(&[(0, 0)], &[(5, 7), (6, 7)]);
+        let mut solver = Solver::new(Threats::Vct, RuleSet::Renju).with_time_limit(Duration::ZERO);
+        assert_eq!(solver.solve(&position, Stone::White), Solution::Unknown);
+    }
+
+    #[test]
+    fn insert() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        // white to move with the position from `vcf`
+        let position = vcf_position();
+        let (black, white) = position
+            .iter()
+            .filter(|m| !m.color.is_empty())
+            .partition::<Vec<_>, _>(|m| m.color.is_black());
+        let mut points = vec![];
+        for (black, white) in black.iter().zip(&white) {
+            points.extend([black.point, white.point]);
+        }
+        points.extend(
+            white[black.len()..]
+                .iter()
+                .flat_map(|m| [Point::null(), m.point]),
+        );
+        points.push(Point::null());
+        let from = board.get_or_insert_points(root, points);
+        // black passed last
+        assert_eq!(board.get_move(from).unwrap().color, Stone::Black);
+
+        let mut solver = Solver::new(Threats::Vcf, RuleSet::Renju);
+        let last = solver.solve_and_insert(&mut board, from).unwrap().unwrap();
+        let first = board.get_move_children(&from)[0];
+        let first = board.get_move(first).unwrap();
+        assert_eq!(first.color, Stone::White);
+        assert!(first
+            .oneline_comment
+            .as_deref()
+            .unwrap()
+            .starts_with("VCF in "));
+        let last_marker = board.get_move(last).unwrap();
+        assert_eq!(last_marker.oneline_comment.as_deref(), Some("win"));
+        let (position, _) = board.as_board(&last).unwrap();
+        assert_eq!(
+            position.outcome(RuleSet::Renju).and_then(|o| o.winner()),
+            Some(Stone::White)
+        );
+
+        // comments already in the tree are kept
+        let first = board.get_move_children(&from)[0];
+        board
+            .get_move_mut(first)
+            .unwrap()
+            .set_oneline_comment("the only move".to_owned());
+        assert_eq!(
+            solver.solve_and_insert(&mut board, from).unwrap(),
+            Some(last)
+        );
+        assert_eq!(
+            board.get_move(first).unwrap().oneline_comment.as_deref(),
+            Some("the only move")
+        );
+        assert_eq!(board.get_move_children(&from).len(), 1);
+    }
+}
@@ -0,0 +1,365 @@
+//! Lazy walks over the moves of a [`Board`].
+//!
+//! Every walk follows the moves played from each other, not the links added with
+//! [`Board::add_edge`], so every move after the start is visited once. Only
+//! [`Board::next_moves`] steps over the links as well. Children come in the order
+//! they were inserted, like [`Board::get_move_children`]. A walk keeps one buffer that grows with
+//! the width or depth of the tree, and allocates nothing for the moves it steps over.
+use std::iter::FusedIterator;
+
+use daggy::Walker;
+
+use super::{Board, BoardMarker, MoveIndex, MOVE_EDGE};
+
+/// A move, its marker and how many moves it is from the start of the walk.
+pub type Visit<'a> = (MoveIndex, &'a BoardMarker, usize);
+
+impl Board {
+    /// Every move from `node` on, `node` first, each move before the moves played from it.
+    #[must_use]
+    pub fn depth_first(&self, node: &MoveIndex) -> DepthFirst<'_> {
+        DepthFirst {
+            board: self,
+            stack: self
+                .get_move(*node)
+                .map(|_| (*node, 0))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Every move from `node` on, the moves one move after it first, then the ones two moves
+    /// after it, and so on.
+    #[must_use]
+    pub fn breadth_first(&self, node: &MoveIndex) -> BreadthFirst<'_> {
+        BreadthFirst {
+            board: self,
+            level: self
+                .get_move(*node)
+                .map(|_| (*node, 0))
+                .into_iter()
+                .collect(),
+            next: Vec::new(),
+            at: 0,
+        }
+    }
+
+    /// The moves from `node` on that have no moves played from them, in the order of
+    /// [`Board::depth_first`].
+    #[must_use]
+    pub fn leaves(&self, node: &MoveIndex) -> Leaves<'_> {
+        Leaves {
+            walk: self.depth_first(node),
+        }
+    }
+
+    /// Every line from `node` to one of the [`Board::leaves`] after it, `node` included.
+    #[must_use]
+    pub fn lines(&self, node: &MoveIndex) -> Lines<'_> {
+        Lines {
+            walk: self.depth_first(node),
+            line: Vec::new(),
+        }
+    }
+
+    /// `node` and the moves it was played from, back to the root.
+    ///
+    /// The depth is the number of moves from the root, as in [`Board::depth_first`] from the root,
+    /// so it counts down to 0.
+    #[must_use]
+    pub fn ancestors(&self, node: &MoveIndex) -> Ancestors<'_> {
+        let next = self.check_node(*node).ok().map(|()| {
+            let parents = std::iter::successors(self.move_parent(*node).ok(), |parent| {
+                self.move_parent(MoveIndex::new_node(*parent)).ok()
+            })
+            .count();
+            (*node, parents)
+        });
+        Ancestors { board: self, next }
+    }
+
+    /// The moves played from `node` and the ones it is linked to, the moves a [`Cursor`] can go
+    /// on to, in the order they were added. They are one move from `node`.
+    ///
+    /// [`Cursor`]: super::cursor::Cursor
+    #[must_use]
+    pub fn next_moves(&self, node: &MoveIndex) -> NextMoves<'_> {
+        let mut moves = self
+            .graph
+            .children(node.node_index)
+            .iter(&self.graph)
+            .map(MoveIndex::new)
+            .collect::<Vec<_>>();
+        // the first one comes off the end first
+        moves.sort_unstable_by_key(|child| std::cmp::Reverse(child.edge_index));
+        NextMoves { board: self, moves }
+    }
+
+    /// Push the moves played from `node` to `buffer` in the order they were inserted, returns how
+    /// many there were.
+    fn push_move_children(
+        &self,
+        node: MoveIndex,
+        depth: usize,
+        buffer: &mut Vec<(MoveIndex, usize)>,
+    ) -> usize {
+        let start = buffer.len();
+        buffer.extend(
+            self.graph
+                .children(node.node_index)
+                .iter(&self.graph)
+                .filter(|(edge, _)| self.graph.edge_weight(*edge) == Some(&MOVE_EDGE))
+                .map(|child| (MoveIndex::new(child), depth)),
+        );
+        buffer[start..].sort_unstable_by_key(|(child, _)| child.edge_index);
+        buffer.len() - start
+    }
+}
+
+/// See [`Board::depth_first`].
+#[derive(Clone, Debug)]
+pub struct DepthFirst<'a> {
+    board: &'a Board,
+    stack: Vec<(MoveIndex, usize)>,
+}
+
+impl<'a> DepthFirst<'a> {
+    /// The next move, and whether no moves are played from it.
+    fn step(&mut self) -> Option<(Visit<'a>, bool)> {
+        let (node, depth) = self.stack.pop()?;
+        let marker = self.board.get_move(node)?;
+        let start = self.stack.len();
+        let children = self
+            .board
+            .push_move_children(node, depth + 1, &mut self.stack);
+        // the first child comes off the stack first
+        self.stack[start..].reverse();
+        Some(((node, marker, depth), children == 0))
+    }
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step().map(|(visit, _)| visit)
+    }
+}
+
+impl FusedIterator for DepthFirst<'_> {}
+
+/// See [`Board::breadth_first`].
+#[derive(Clone, Debug)]
+pub struct BreadthFirst<'a> {
+    board: &'a Board,
+    /// The moves at the current depth, visited up to `at`.
+    level: Vec<(MoveIndex, usize)>,
+    /// The moves played from the visited moves of `level`.
+    next: Vec<(MoveIndex, usize)>,
+    at: usize,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.at == self.level.len() {
+            std::mem::swap(&mut self.level, &mut self.next);
+            self.next.clear();
+            self.at = 0;
+        }
+        let (node, depth) = *self.level.get(self.at)?;
+        self.at += 1;
+        let marker = self.board.get_move(node)?;
+        self.board
+            .push_move_children(node, depth + 1, &mut self.next);
+        Some((node, marker, depth))
+    }
+}
+
+impl FusedIterator for BreadthFirst<'_> {}
+
+/// See [`Board::leaves`].
+#[derive(Clone, Debug)]
+pub struct Leaves<'a> {
+    walk: DepthFirst<'a>,
+}
+
+impl<'a> Iterator for Leaves<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let (visit, true) = self.walk.step()? {
+                return Some(visit);
+            }
+        }
+    }
+}
+
+impl FusedIterator for Leaves<'_> {}
+
+/// See [`Board::lines`].
+///
+/// Every line is a new `Vec`, the moves between them are walked without one.
+#[derive(Clone, Debug)]
+pub struct Lines<'a> {
+    walk: DepthFirst<'a>,
+    /// The moves from the start to the last visited move.
+    line: Vec<Visit<'a>>,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Vec<Visit<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (visit, leaf) = self.walk.step()?;
+            self.line.truncate(visit.2);
+            self.line.push(visit);
+            if leaf {
+                return Some(self.line.clone());
+            }
+        }
+    }
+}
+
+impl FusedIterator for Lines<'_> {}
+
+/// See [`Board::ancestors`].
+#[derive(Clone, Debug)]
+pub struct Ancestors<'a> {
+    board: &'a Board,
+    next: Option<(MoveIndex, usize)>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, depth) = self.next.take()?;
+        let marker = self.board.get_move(node)?;
+        self.next = self
+            .board
+            .move_parent(node)
+            .ok()
+            .map(|parent| (self.board.move_index(parent), depth.saturating_sub(1)));
+        Some((node, marker, depth))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.next.map_or(0, |(_, depth)| depth + 1);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Ancestors<'_> {}
+
+impl FusedIterator for Ancestors<'_> {}
+
+/// See [`Board::next_moves`].
+#[derive(Clone, Debug)]
+pub struct NextMoves<'a> {
+    board: &'a Board,
+    moves: Vec<MoveIndex>,
+}
+
+impl<'a> Iterator for NextMoves<'a> {
+    type Item = Visit<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.moves.pop()?;
+        Some((node, self.board.get_move(node)?, 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.moves.len(), Some(self.moves.len()))
+    }
+}
+
+impl ExactSizeIterator for NextMoves<'_> {}
+
+impl FusedIterator for NextMoves<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{cursor::Cursor, Point};
+    use test_log::test;
+
+    /// The visits as `point:depth`.
+    fn notation<'a>(board: &Board, visits: impl IntoIterator<Item = Visit<'a>>) -> Vec<String> {
+        visits
+            .into_iter()
+            .map(|(_, marker, depth)| format!("{}:{depth}", marker.point.notation(board.size())))
+            .collect()
+    }
+
+    #[test]
+    fn walks_the_tree_in_order() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |s| Point::parse_line(s).unwrap();
+        let i9 = board.get_or_insert_points(root, line("h8 i9"));
+        let h8 = board.get_move_children(&root)[0];
+        let k11 = board.get_or_insert_points(i9, line("j10 k11"));
+        board.get_or_insert_points(i9, line("g7"));
+        let h9 = board.get_or_insert_points(h8, line("h9"));
+        let other = board.get_or_insert_points(root, line("j10"));
+        // links are not followed
+        board.add_edge(&other, &h9).unwrap();
+
+        assert_eq!(
+            notation(&board, board.depth_first(&h8)),
+            ["h8:0", "i9:1", "j10:2", "k11:3", "g7:2", "h9:1"]
+        );
+        assert_eq!(
+            notation(&board, board.breadth_first(&h8)),
+            ["h8:0", "i9:1", "h9:1", "j10:2", "g7:2", "k11:3"]
+        );
+        assert_eq!(board.depth_first(&root).count(), 8);
+        assert_eq!(board.breadth_first(&root).count(), 8);
+        assert_eq!(
+            notation(&board, board.leaves(&h8)),
+            ["k11:3", "g7:2", "h9:1"]
+        );
+        let lines = board
+            .lines(&root)
+            .map(|line| notation(&board, line.into_iter().skip(1)).join(" "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "h8:1 i9:2 j10:3 k11:4",
+                "h8:1 i9:2 g7:3",
+                "h8:1 h9:2",
+                "j10:1"
+            ]
+        );
+
+        assert_eq!(board.ancestors(&k11).len(), 5);
+        assert_eq!(
+            notation(&board, board.ancestors(&k11).take(4)),
+            ["k11:4", "j10:3", "i9:2", "h8:1"]
+        );
+        assert!(board
+            .ancestors(&k11)
+            .map(|(node, _, _)| node.node_index)
+            .eq(Cursor::to(&board, k11)
+                .unwrap()
+                .line()
+                .iter()
+                .rev()
+                .map(|node| node.node_index)));
+        assert_eq!(board.ancestors(&h9).len(), 3, "the move it was played from");
+        assert_eq!(board.ancestors(&root).count(), 1);
+
+        assert_eq!(notation(&board, board.next_moves(&h8)), ["i9:1", "h9:1"]);
+        assert_eq!(
+            notation(&board, board.next_moves(&other)),
+            ["h9:1"],
+            "links are followed one move"
+        );
+        assert_eq!(board.next_moves(&k11).len(), 0);
+    }
+}
@@ -0,0 +1,309 @@
+//! Zobrist hashing of positions.
+//!
+//! Every point and color has a random key, and the hash of a position is the xor of the keys of its
+//! stones, so placing or removing a stone updates it in constant time. A [`Zobrist`] keeps the hash
+//! of the position under each of the eight [`Transformation::types`] as well, which gives a
+//! canonical hash that is the same for all symmetric positions.
+//!
+//! The side to move isn't hashed. It usually follows from the number of stones, but a pass changes
+//! it without a stone, so positions that only differ by a pass have the same hash. Compare
+//! [`Board::side_to_move`](super::Board::side_to_move) where that matters, as
+//! [`Board::link_transpositions`](super::Board::link_transpositions) does.
+use std::collections::HashMap;
+
+use super::{NodeIndex, Point, Stone, Transformation, MAX_SIZE};
+
+const POINTS: usize = (MAX_SIZE * MAX_SIZE) as usize;
+
+/// Keys for black and white on every point of the largest board, generated with splitmix64 so they
+/// are the same on every run.
+const KEYS: [[u64; 2]; POINTS] = {
+    let mut keys = [[0; 2]; POINTS];
+    let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+    let mut i = 0;
+    while i < POINTS * 2 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i / 2][i % 2] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+};
+
+/// The key of `stone` on `point`, `0` for an empty point.
+#[must_use]
+pub fn key(point: Point, stone: Stone) -> u64 {
+    let index = (point.x + point.y * MAX_SIZE) as usize;
+    match stone {
+        Stone::Empty => 0,
+        Stone::Black => KEYS[index][0],
+        Stone::White => KEYS[index][1],
+    }
+}
+
+/// The hash of a position on a board of `size`x`size`, and of its seven symmetric positions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Zobrist {
+    size: u32,
+    /// The hash of the position transformed by each of [`Transformation::types`], in that order.
+    hashes: [u64; 8],
+}
+
+impl Zobrist {
+    /// The hash of an empty board.
+    #[must_use]
+    pub fn new(size: u32) -> Self {
+        Self {
+            size,
+            hashes: [0; 8],
+        }
+    }
+
+    /// Hash the stones in `stones`.
+    #[must_use]
+    pub fn from_stones(size: u32, stones: impl IntoIterator<Item = (Point, Stone)>) -> Self {
+        let mut zobrist = Self::new(size);
+        for (point, stone) in stones {
+            zobrist.toggle(point, stone);
+        }
+        zobrist
+    }
+
+    /// Place `stone` on `point`, or remove it if it is already there.
+    pub fn toggle(&mut self, point: Point, stone: Stone) {
+        if stone.is_empty() || point.is_null {
+            return;
+        }
+        for (hash, transform) in self.hashes.iter_mut().zip(Transformation::types()) {
+            *hash ^= key(transform.apply(point, self.size), stone);
+        }
+    }
+
+    /// The hash of the position as it is.
+    #[must_use]
+    pub fn hash(&self) -> u64 {
+        self.hashes[0]
+    }
+
+    /// The hash of the position that is the same for all of its symmetric positions.
+    #[must_use]
+    pub fn canonical(&self) -> u64 {
+        self.canonical_with_transformation().0
+    }
+
+    /// Like [`Zobrist::canonical`], with the transformation that takes the position to the one
+    /// the canonical hash is the hash of.
+    pub fn canonical_with_transformation(&self) -> (u64, Transformation) {
+        self.hashes
+            .into_iter()
+            .zip(Transformation::types())
+            .min_by_key(|(hash, _)| *hash)
+            .expect("there are always eight transformations")
+    }
+}
+
+/// The moves of a [`Board`](super::Board) by the position they lead to, kept up to date as the
+/// tree changes, see [`Board::position_index`](super::Board::position_index).
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionIndex {
+    /// The moves by their canonical hash, in the order they were indexed.
+    nodes: HashMap<u64, Vec<NodeIndex>>,
+    /// The position after every node of the tree, by its index, and whether it's in `nodes`.
+    positions: Vec<(Zobrist, bool)>,
+}
+
+impl PositionIndex {
+    /// Add the position after the next node of the tree, `indexed` if it can be looked up.
+    pub(super) fn push(&mut self, zobrist: Zobrist, indexed: bool) -> NodeIndex {
+        let node = NodeIndex::new(self.positions.len());
+        self.positions.push((zobrist, indexed));
+        if indexed {
+            self.nodes
+                .entry(zobrist.canonical())
+                .or_default()
+                .push(node);
+        }
+        node
+    }
+
+    /// Change the position after `node`.
+    pub(super) fn set(&mut self, node: NodeIndex, zobrist: Zobrist, indexed: bool) {
+        if self.positions[node.index()] == (zobrist, indexed) {
+            return;
+        }
+        self.unindex(node);
+        self.positions[node.index()] = (zobrist, indexed);
+        if indexed {
+            self.nodes
+                .entry(zobrist.canonical())
+                .or_default()
+                .push(node);
+        }
+    }
+
+    /// Remove `node`, the last node takes its place, as in the graph of the board.
+    pub(super) fn swap_remove(&mut self, node: NodeIndex) {
+        self.unindex(node);
+        let last = NodeIndex::new(self.positions.len() - 1);
+        if last != node {
+            let (zobrist, indexed) = self.positions[last.index()];
+            if indexed {
+                let moved = self
+                    .nodes
+                    .get_mut(&zobrist.canonical())
+                    .and_then(|nodes| nodes.iter_mut().find(|other| **other == last))
+                    .expect("indexed nodes are in the map");
+                *moved = node;
+            }
+        }
+        self.positions.swap_remove(node.index());
+    }
+
+    fn unindex(&mut self, node: NodeIndex) {
+        let (zobrist, indexed) = self.positions[node.index()];
+        if !indexed {
+            return;
+        }
+        let canonical = zobrist.canonical();
+        if let Some(nodes) = self.nodes.get_mut(&canonical) {
+            nodes.retain(|other| *other != node);
+            if nodes.is_empty() {
+                self.nodes.remove(&canonical);
+            }
+        }
+    }
+
+    /// The position after `node`, whether it's indexed or not.
+    #[must_use]
+    pub fn position(&self, node: NodeIndex) -> Option<&Zobrist> {
+        self.positions.get(node.index()).map(|(zobrist, _)| zobrist)
+    }
+
+    /// The moves with exactly the position of `zobrist`.
+    pub fn transpositions<'a>(
+        &'a self,
+        zobrist: &'a Zobrist,
+    ) -> impl Iterator<Item = NodeIndex> + 'a {
+        self.symmetric(zobrist)
+            .filter(|(_, transform)| *transform == Transformation::identity())
+            .map(|(node, _)| node)
+    }
+
+    /// The moves with the position of `zobrist` up to symmetry, with the transformation that takes
+    /// their position to the one of `zobrist`.
+    pub fn symmetric<'a>(
+        &'a self,
+        zobrist: &'a Zobrist,
+    ) -> impl Iterator<Item = (NodeIndex, Transformation)> + 'a {
+        self.nodes
+            .get(&zobrist.canonical())
+            .into_iter()
+            .flatten()
+            .filter_map(|node| {
+                let (other, _) = self.positions[node.index()];
+                let transform = other
+                    .hashes
+                    .iter()
+                    .zip(Transformation::types())
+                    .find(|(hash, _)| **hash == zobrist.hash())?
+                    .1;
+                Some((*node, transform))
+            })
+    }
+
+    /// Every group of two or more moves with exactly the same position.
+    pub fn groups(&self) -> impl Iterator<Item = Vec<NodeIndex>> + '_ {
+        self.nodes.values().flat_map(|nodes| {
+            let mut groups = HashMap::<_, Vec<_>>::new();
+            for node in nodes {
+                let (zobrist, _) = self.positions[node.index()];
+                groups.entry(zobrist.hash()).or_default().push(*node);
+            }
+            groups.into_values().filter(|group| group.len() > 1)
+        })
+    }
+
+    /// The number of moves in the index.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.values().map(Vec::len).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use test_log::test;
+
+    fn hash(stones: &[(Point, Stone)]) -> Zobrist {
+        Zobrist::from_stones(15, stones.iter().copied())
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        let mut keys = KEYS.iter().flatten().collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), POINTS * 2);
+    }
+
+    #[test]
+    fn transpositions() {
+        let [h8, i9, g7, h7] = p![[H, 8], [I, 9], [G, 7], [H, 7]];
+        let first = hash(&[(h8, Stone::Black), (i9, Stone::White), (g7, Stone::Black)]);
+        let second = hash(&[(g7, Stone::Black), (i9, Stone::White), (h8, Stone::Black)]);
+        assert_eq!(first, second);
+        let swapped = hash(&[(h8, Stone::White), (i9, Stone::Black), (g7, Stone::Black)]);
+        assert_ne!(first.hash(), swapped.hash());
+
+        let mut incremental = first;
+        incremental.toggle(h7, Stone::White);
+        assert_ne!(incremental, first);
+        incremental.toggle(h7, Stone::White);
+        assert_eq!(incremental, first);
+        incremental.toggle(Point::null(), Stone::Black);
+        incremental.toggle(h7, Stone::Empty);
+        assert_eq!(incremental, first);
+        assert_eq!(Zobrist::new(15).hash(), 0);
+    }
+
+    #[test]
+    fn symmetric_positions() {
+        let stones = [
+            (p![H, 8], Stone::Black),
+            (p![I, 9], Stone::White),
+            (p![K, 9], Stone::Black),
+        ];
+        let position = hash(&stones);
+        for size in [15, 19, 20] {
+            let position = Zobrist::from_stones(size, stones);
+            for transform in Transformation::types() {
+                let transformed = Zobrist::from_stones(
+                    size,
+                    stones.map(|(point, stone)| (transform.apply(point, size), stone)),
+                );
+                assert_eq!(transformed.canonical(), position.canonical());
+            }
+        }
+        let (canonical, transform) = position.canonical_with_transformation();
+        let transformed = hash(&stones.map(|(point, stone)| (transform.apply(point, 15), stone)));
+        assert_eq!(transformed.hash(), canonical);
+
+        let other = hash(&[
+            (p![H, 8], Stone::Black),
+            (p![I, 9], Stone::White),
+            (p![K, 10], Stone::Black),
+        ]);
+        assert_ne!(other.canonical(), position.canonical());
+    }
+}
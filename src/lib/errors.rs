@@ -1,15 +1,51 @@
+use crate::file_reader::FileType;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
     #[error("File is not currently supported")]
     NotSupported,
+    #[error("File type is ambiguous, it could be any of {0:?}")]
+    AmbiguousFileType(Vec<FileType>),
     #[error("Couldn't parse MoveIndex string")]
     MoveIndexParseError,
+    #[error("{0:?} is not a point, expected a letter and a number like h8")]
+    PointParseError(String),
+    /// A move that should be in the board wasn't found.
+    #[error("there is no move {0} from the current position")]
+    MoveNotFound(crate::board::Point),
     #[error("Version {majv}.{minv} is not supported")]
     VersionNotSupported { majv: u8, minv: u8 },
-    #[error("unsuccessful parsing of file in pos format")]
-    PosParseError,
+    /// A board can't be this large, see [`MAX_SIZE`](crate::board::MAX_SIZE).
+    #[error("board size must be between 1 and {}, got {0}", crate::board::MAX_SIZE)]
+    InvalidBoardSize(u32),
+    /// The file type can't store a board of this size, see [`FileType::board_size`].
+    #[error("{filetype:?} files can't store a {size}x{size} board")]
+    BoardSizeNotSupported { filetype: FileType, size: u32 },
+    #[error("unsuccessful parsing of file in pos format: {0}")]
+    PosParseError(String),
+    #[error("unsuccessful parsing of file in RIF format: {0}")]
+    RifParseError(String),
+    #[error("unsuccessful parsing of file in RenLib bdt format: {0}")]
+    BdtParseError(String),
+    #[error("unsuccessful parsing of file in RenLib pdb format: {0}")]
+    PdbParseError(String),
     #[error("unsuccessful parsing of file in RenLib format")]
     LibParseError,
+    /// The RenLib file does not have the expected structure.
+    #[error(
+        "malformed RenLib file at byte {offset:#x} (command {command:#x}), expected {expected}"
+    )]
+    MalformedLib {
+        /// Offset in the file of the node, see [`BoardMarker::index_in_file`](crate::board::BoardMarker::index_in_file)
+        offset: usize,
+        /// Command bits of the node, including any extension
+        command: u32,
+        /// Description of what was expected
+        expected: String,
+    },
+    /// A move or a [`Cursor`](crate::board::Cursor) doesn't fit the tree of the board.
+    #[error(transparent)]
+    Edit(#[from] crate::board::EditError),
     #[error(transparent)]
     IoError(#[from] std::io::Error),
     #[error(transparent)]
@@ -0,0 +1,212 @@
+//! Text encodings of comments and board texts in RenLib files.
+//!
+//! RenLib is a Windows application, so strings are stored in the ANSI code page of whoever wrote
+//! the file. Nothing in the file says which one, so [`TextEncoding::detect`] guesses from the
+//! bytes. The encoding used is recorded on the [`Board`](crate::board::Board), so saving writes
+//! the strings back the same way.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::errors::ParseError;
+
+/// Encoding of strings in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextEncoding {
+    /// Western European, the code page of RenLib itself.
+    #[default]
+    Windows1252,
+    /// Simplified Chinese.
+    Gbk,
+    /// Japanese.
+    ShiftJis,
+    Utf8,
+}
+
+impl TextEncoding {
+    pub const ALL: [Self; 4] = [Self::Windows1252, Self::Gbk, Self::ShiftJis, Self::Utf8];
+
+    fn encoding(self) -> &'static encoding_rs::Encoding {
+        match self {
+            Self::Windows1252 => encoding_rs::WINDOWS_1252,
+            Self::Gbk => encoding_rs::GBK,
+            Self::ShiftJis => encoding_rs::SHIFT_JIS,
+            Self::Utf8 => encoding_rs::UTF_8,
+        }
+    }
+
+    /// Decode `bytes`, invalid sequences are replaced with `U+FFFD`.
+    #[must_use]
+    pub fn decode(self, bytes: &[u8]) -> String {
+        self.encoding()
+            .decode_without_bom_handling(bytes)
+            .0
+            .into_owned()
+    }
+
+    /// Decode `bytes`, or `None` if they are not valid in this encoding.
+    #[must_use]
+    pub fn decode_strict(self, bytes: &[u8]) -> Option<String> {
+        self.encoding()
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(|s| s.into_owned())
+    }
+
+    /// Encode `text`, characters that can't be represented are written as `&#NNNN;`.
+    #[must_use]
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        let (bytes, _, unmappable) = self.encoding().encode(text);
+        if unmappable {
+            tracing::warn!(encoding = %self, text, "text can not be represented");
+        }
+        bytes.into_owned()
+    }
+
+    /// Guess the encoding of `texts`.
+    ///
+    /// Plain ASCII is [`TextEncoding::default`]. Otherwise the encodings that can decode every text
+    /// are scored by how common the decoded characters are, so text that happens to be valid in more
+    /// than one encoding goes to the most plausible one.
+    #[must_use]
+    pub fn detect<'a>(texts: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let texts = texts
+            .into_iter()
+            .filter(|t| !t.is_ascii())
+            .collect::<Vec<_>>();
+        if texts.is_empty() {
+            return Self::default();
+        }
+        if texts.iter().all(|t| std::str::from_utf8(t).is_ok()) {
+            return Self::Utf8;
+        }
+        [Self::Gbk, Self::ShiftJis]
+            .into_iter()
+            .filter_map(|encoding| {
+                let mut score = 0;
+                for text in &texts {
+                    score += encoding.score(text)?;
+                }
+                Some((encoding, score))
+            })
+            .filter(|(_, score)| *score > 0)
+            // on a tie, the first one wins
+            .rev()
+            .max_by_key(|(_, score)| *score)
+            .map_or(Self::Windows1252, |(encoding, _)| encoding)
+    }
+
+    /// Score how plausible `text` is in this encoding, `None` if it can't be decoded.
+    fn score(self, text: &[u8]) -> Option<i64> {
+        let decoded = self.decode_strict(text)?;
+        let score = match self {
+            Self::Gbk => {
+                // GB2312, the common characters, is the part of GBK with a lead byte in 0xA1..=0xF7
+                // and a trail byte in 0xA1..=0xFE.
+                let mut score = 0;
+                let mut bytes = text.iter();
+                while let Some(lead) = bytes.next() {
+                    if lead.is_ascii() {
+                        continue;
+                    }
+                    match bytes.next() {
+                        Some(trail) if (0xA1..=0xF7).contains(lead) && *trail >= 0xA1 => score += 1,
+                        _ => score -= 1,
+                    }
+                }
+                score
+            }
+            Self::ShiftJis => decoded
+                .chars()
+                .filter(|c| !c.is_ascii())
+                .map(|c| match c {
+                    // hiragana and katakana
+                    '\u{3040}'..='\u{30FF}' => 2,
+                    // punctuation and kanji
+                    '\u{3000}'..='\u{303F}' | '\u{4E00}'..='\u{9FFF}' => 1,
+                    // full width ASCII
+                    '\u{FF01}'..='\u{FF5E}' => 1,
+                    // half width katakana, rare in text but every byte of 0xA1..=0xDF
+                    _ => -1,
+                })
+                .sum(),
+            Self::Windows1252 | Self::Utf8 => 0,
+        };
+        Some(score)
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Windows1252 => "windows-1252",
+            Self::Gbk => "gbk",
+            Self::ShiftJis => "shift-jis",
+            Self::Utf8 => "utf-8",
+        })
+    }
+}
+
+impl FromStr for TextEncoding {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "windows-1252" | "cp1252" | "latin1" => Ok(Self::Windows1252),
+            "gbk" | "cp936" | "gb2312" => Ok(Self::Gbk),
+            "shift-jis" | "sjis" | "cp932" => Ok(Self::ShiftJis),
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            _ => Err(ParseError::Other(format!(
+                "unknown encoding {s:?}, expected one of {}",
+                Self::ALL.map(|e| e.to_string()).join(", ")
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHINESE: &str = "黑棋必胜，白棋防守失误";
+    const JAPANESE: &str = "黒の勝ち、白の守りが悪い";
+    const SWEDISH: &str = "Svart vinner, vit försvarar dåligt";
+
+    #[test]
+    fn detect() {
+        for (text, encoding) in [
+            (CHINESE, TextEncoding::Gbk),
+            (JAPANESE, TextEncoding::ShiftJis),
+            (SWEDISH, TextEncoding::Windows1252),
+            (CHINESE, TextEncoding::Utf8),
+            (JAPANESE, TextEncoding::Utf8),
+        ] {
+            let bytes = encoding.encode(text);
+            assert_eq!(
+                TextEncoding::detect([bytes.as_slice(), b"ascii".as_slice()]),
+                encoding,
+                "{text}"
+            );
+            assert_eq!(encoding.decode(&bytes), text);
+        }
+        assert_eq!(
+            TextEncoding::detect([b"only ascii".as_slice()]),
+            TextEncoding::Windows1252
+        );
+        assert_eq!(TextEncoding::detect([]), TextEncoding::Windows1252);
+    }
+
+    #[test]
+    fn names() {
+        for encoding in TextEncoding::ALL {
+            assert_eq!(
+                encoding.to_string().parse::<TextEncoding>().unwrap(),
+                encoding
+            );
+        }
+        assert_eq!(
+            "Shift_JIS".parse::<TextEncoding>().unwrap(),
+            TextEncoding::ShiftJis
+        );
+        assert!("ebcdic".parse::<TextEncoding>().is_err());
+    }
+}
@@ -1,17 +1,23 @@
 //! Used for reading files.
 //!
-//! Currently only supports _.pos_ and _.lib_ (`RenLib`) files of version 3.04+.
+//! Currently supports _.pos_, _.lib_ (`RenLib`) files of version 3.04+ with their _.bdt_ and _.pdb_
+//! companions, and _.rif_ databases.
 
 use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 
-use crate::board::{Board, BoardMarker, MoveIndex, Point, Stone};
+use crate::board::{Board, DEFAULT_SIZE};
 use crate::errors::ParseError;
+use crate::file_reader::encoding::TextEncoding;
 
+pub mod encoding;
+pub mod pos;
 pub mod renlib;
+pub mod rif;
 
 /// Describes the file
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FileType {
     /// Generic Renju _.pos_ file.
     ///
@@ -22,6 +28,7 @@ pub enum FileType {
     /// * 2: White move #2
     /// * N: Last move
     ///
+    /// Every move is stored as `x + y * 15`, a pass is stored as `0xF0`. See [`pos`].
     Pos,
     /// RenLib, _.lib_ file.
     ///
@@ -45,7 +52,8 @@ pub enum FileType {
     ///
     /// ## Known:
     /// * Libraries are stored as such: HEADER n * [POS:FLAGS:STRINGS:EXTENDEDINFO]. Since **.lib** supports
-    /// trees, we had to implement it [in rust too](#move_node::MoveGraph)
+    ///   trees, we had to implement it [in rust too](#move_node::MoveGraph)
+    ///
     /// Positions are stored in one byte. This means that 0x78 is the middle.
     ///
     ///     This is the layout for X, Y:
@@ -70,6 +78,7 @@ pub enum FileType {
     /// The _O_ is on `0x44`, the _X_ is on `0x78` (the middle)
     ///
     /// * The header consists of 20 bytes:
+    ///
     ///         0xFF,  'R',  'e',  'n',  'L',  'i',  'b', 0xFF, MAJV, MINV,
     ///         0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
     ///
@@ -98,71 +107,188 @@ pub enum FileType {
     ///
     /// *    See RenLib/RenLibDoc.cpp for implementation.
     Lib,
+    /// RenLib _.bdt_ file, lines of moves as text.
+    ///
+    /// Every line is inserted from the root, so loading it into a board read from the sibling _.lib_
+    /// file only adds lines that are missing. See [`renlib::bdt`].
+    Bdt,
+    /// RenLib _.pdb_ file, a Palm OS database of positions.
+    ///
+    /// Like [`FileType::Bdt`], every position is inserted from the root. See [`renlib::pdb`].
+    Pdb,
     /// Renju Database File
     ///
     /// These are generally quite large. They include multiple games, so these will really test my
     /// implementation of trees. They need support for findig comments as this is the way games are
     /// found.
+    ///
+    /// The database is XML, every game is merged into the same tree and described in a comment on its
+    /// final move. See [`rif`].
     Rif,
 }
 
 impl FileType {
-    fn new(path: &Path) -> Option<Self> {
+    /// Every supported file type, in the order they are checked by [`FileType::sniff`].
+    pub const ALL: [Self; 5] = [Self::Lib, Self::Pdb, Self::Rif, Self::Bdt, Self::Pos];
+
+    /// Guess the file type from the extension of `path`.
+    #[must_use]
+    pub fn new(path: &Path) -> Option<Self> {
         match path.extension() {
             Some(pos) if (pos == "pos") => Some(Self::Pos),
             Some(lib) if (lib == "lib") => Some(Self::Lib),
+            Some(bdt) if (bdt == "bdt") => Some(Self::Bdt),
+            Some(pdb) if (pdb == "pdb") => Some(Self::Pdb),
+            Some(rif) if (rif == "rif") => Some(Self::Rif),
             Some(_) => None,
             None => None,
         }
     }
+
+    /// Check if `bytes` could be a file of this type, judging from its contents.
+    #[must_use]
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        match self {
+            Self::Pos => pos::sniff(bytes),
+            Self::Lib => renlib::sniff(bytes),
+            Self::Bdt => renlib::bdt::sniff(bytes),
+            Self::Pdb => renlib::pdb::sniff(bytes),
+            Self::Rif => rif::sniff(bytes),
+        }
+    }
+
+    /// Width and height of the boards this file type can store.
+    #[must_use]
+    pub const fn board_size(&self) -> u32 {
+        match self {
+            Self::Pos | Self::Lib | Self::Bdt | Self::Pdb | Self::Rif => DEFAULT_SIZE,
+        }
+    }
+
+    /// Check that a board of `size`x`size` can be stored in this file type.
+    pub fn check_board_size(&self, size: u32) -> Result<(), ParseError> {
+        if size == self.board_size() {
+            Ok(())
+        } else {
+            Err(ParseError::BoardSizeNotSupported {
+                filetype: *self,
+                size,
+            })
+        }
+    }
+
+    /// Every file type `bytes` could be, judging from its contents.
+    #[must_use]
+    pub fn sniff(bytes: &[u8]) -> Vec<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|filetype| filetype.matches(bytes))
+            .collect()
+    }
+
+    /// Detect the file type of `bytes` from its contents.
+    ///
+    /// If the contents could be more than one type, `hint` (usually from [`FileType::new`]) is used
+    /// if it is one of them, otherwise [`ParseError::AmbiguousFileType`] lists the candidates.
+    pub fn detect(bytes: &[u8], hint: Option<&Self>) -> Result<Self, ParseError> {
+        match Self::sniff(bytes).as_slice() {
+            [] => Err(ParseError::NotSupported),
+            [filetype] => Ok(*filetype),
+            candidates => match hint {
+                Some(hint) if candidates.contains(hint) => Ok(*hint),
+                _ => Err(ParseError::AmbiguousFileType(candidates.to_vec())),
+            },
+        }
+    }
 }
 
 pub enum FileErr {
     ParseError,
 }
 
+/// Open a file of any supported type, the type is detected from the contents.
+///
+/// The extension of `path` is only used to decide between types when the contents are ambiguous,
+/// see [`FileType::detect`].
 #[tracing::instrument(fields(filetype))]
 pub fn open_file_path(path: &Path) -> Result<Board, color_eyre::Report> {
+    open_file_path_with_encoding(path, None)
+}
+
+/// Like [`open_file_path`], but decode texts with `encoding`, or detect it if it's `None`.
+///
+/// See [`encoding`] for which files this applies to.
+#[tracing::instrument(fields(filetype))]
+pub fn open_file_path_with_encoding(
+    path: &Path,
+    encoding: Option<TextEncoding>,
+) -> Result<Board, color_eyre::Report> {
     let mut board = Board::new();
 
-    let _display = path.display();
-    let filetype = FileType::new(path);
+    let bytes = std::fs::read(path)?;
+    tracing::trace!("file read");
+    let filetype = FileType::detect(&bytes, FileType::new(path).as_ref())?;
     tracing::Span::current().record("filetype", tracing::field::debug(&filetype));
-    let file: File = File::open(path)?;
-    // XXX: This gives a massive speedup.
-    let buffered = std::io::BufReader::new(file);
-    tracing::trace!("file opened");
-    read_bytes(buffered, filetype.as_ref(), &mut board)?;
+    read_bytes_with_encoding(bytes.as_slice(), Some(&filetype), &mut board, encoding)?;
     Ok(board)
 }
 
+/// Read `bytes` into `board`, if `filetype` is `None` it is detected with [`FileType::detect`].
 #[tracing::instrument(skip(bytes, board))]
 pub fn read_bytes(
     bytes: impl std::io::Read,
     filetype: Option<&FileType>,
     board: &mut Board,
 ) -> Result<(), color_eyre::Report> {
+    read_bytes_with_encoding(bytes, filetype, board, None)
+}
+
+/// Like [`read_bytes`], but decode texts with `encoding`, or detect it if it's `None`.
+#[tracing::instrument(skip(bytes, board))]
+pub fn read_bytes_with_encoding(
+    mut bytes: impl std::io::Read,
+    filetype: Option<&FileType>,
+    board: &mut Board,
+    encoding: Option<TextEncoding>,
+) -> Result<(), color_eyre::Report> {
+    let Some(filetype) = filetype else {
+        let mut buf = vec![];
+        bytes.read_to_end(&mut buf)?;
+        let filetype = FileType::detect(&buf, None)?;
+        tracing::debug!(?filetype, "detected file type");
+        return read_bytes_with_encoding(buf.as_slice(), Some(&filetype), board, encoding);
+    };
     match filetype {
-        Some(FileType::Pos) => {
-            let mut sequence: Vec<BoardMarker> = Vec::new();
-            for (index, pos) in bytes.bytes().skip(1).enumerate() {
-                // First value should always be the number of moves.
-                sequence.push(BoardMarker::new(
-                    Point::from_1d(u32::from(pos?), 15),
-                    if index % 2 == 0 {
-                        Stone::Black
-                    } else {
-                        Stone::White
-                    },
-                ));
-            }
-            let root = board.get_root();
-            let mut latest: MoveIndex = board.insert_move(root, sequence[0].clone());
-            for marker_move in sequence.into_iter().skip(1) {
-                latest = board.insert_move(latest, marker_move)
-            }
-        }
-        Some(FileType::Lib) => renlib::parse_lib(bytes, board)?,
+        FileType::Pos => pos::parse_pos(bytes, board)?,
+        FileType::Lib => renlib::parse_lib_with_encoding(bytes, board, encoding)?,
+        FileType::Bdt => renlib::bdt::parse_bdt(bytes, board)?,
+        FileType::Pdb => renlib::pdb::parse_pdb(bytes, board)?,
+        FileType::Rif => rif::parse_rif(bytes, board)?,
+    }
+    Ok(())
+}
+
+/// Save the board to a file, the format is decided by the extension of `path`.
+#[tracing::instrument(skip(board), fields(filetype))]
+pub fn save_file_path(path: &Path, board: &Board) -> Result<(), color_eyre::Report> {
+    let filetype = FileType::new(path);
+    tracing::Span::current().record("filetype", tracing::field::debug(&filetype));
+    let file: File = File::create(path)?;
+    let mut buffered = std::io::BufWriter::new(file);
+    write_bytes(&mut buffered, filetype.as_ref(), board)?;
+    buffered.flush()?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(bytes, board))]
+pub fn write_bytes(
+    bytes: impl std::io::Write,
+    filetype: Option<&FileType>,
+    board: &Board,
+) -> Result<(), color_eyre::Report> {
+    match filetype {
+        Some(FileType::Pos) => pos::write_pos(bytes, board, None)?,
+        Some(FileType::Lib) => renlib::write_lib(bytes, board, renlib::Version::V34)?,
         _ => return Err(ParseError::NotSupported.into()),
     }
     Ok(())
@@ -175,6 +301,29 @@ mod tests {
 
     use crate::board as mn;
 
+    #[test]
+    fn board_size_not_supported() {
+        let mut board = Board::with_size(19).unwrap();
+        let error = read_bytes(
+            std::fs::read("examplefiles/example.pos")
+                .unwrap()
+                .as_slice(),
+            Some(&FileType::Pos),
+            &mut board,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(ParseError::BoardSizeNotSupported {
+                filetype: FileType::Pos,
+                size: 19
+            })
+        ));
+        for filetype in [FileType::Pos, FileType::Lib] {
+            assert!(write_bytes(vec![], Some(&filetype), &board).is_err());
+        }
+    }
+
     #[test]
     fn open_pos_file() {
         let file = Path::new("examplefiles/example.pos");
@@ -194,4 +343,51 @@ mod tests {
         tracing::info!("\n{:?}", graph);
         // panic!("Intended!");
     }
+
+    #[test]
+    fn detect_examplefiles() -> Result<(), color_eyre::Report> {
+        for entry in std::fs::read_dir("examplefiles")? {
+            let path = entry?.path();
+            let Some(expected) = FileType::new(&path) else {
+                continue;
+            };
+            let bytes = std::fs::read(&path)?;
+            assert_eq!(FileType::sniff(&bytes), [expected], "{path:?}");
+            // the name should not matter
+            let mut board = Board::new();
+            read_bytes(bytes.as_slice(), None, &mut board)?;
+        }
+        // a library with the wrong extension
+        let bytes = std::fs::read("examplefiles/pos_two_top_rows.bak")?;
+        assert_eq!(FileType::detect(&bytes, None)?, FileType::Lib);
+        Ok(())
+    }
+
+    #[test]
+    fn detect_ambiguous() {
+        assert!(matches!(
+            FileType::detect(b"", None),
+            Err(ParseError::NotSupported)
+        ));
+        assert!(matches!(
+            FileType::detect(b"not a game", None),
+            Err(ParseError::NotSupported)
+        ));
+        // both the start of a database and a line of moves
+        let bytes = b"<database>[,,,88,,,]";
+        match FileType::detect(bytes, None) {
+            Err(ParseError::AmbiguousFileType(candidates)) => {
+                assert_eq!(candidates, [FileType::Rif, FileType::Bdt]);
+            }
+            other => panic!("expected ambiguous file type, got {other:?}"),
+        }
+        assert_eq!(
+            FileType::detect(bytes, Some(&FileType::Bdt)).unwrap(),
+            FileType::Bdt
+        );
+        assert!(matches!(
+            FileType::detect(bytes, Some(&FileType::Lib)),
+            Err(ParseError::AmbiguousFileType(_))
+        ));
+    }
 }
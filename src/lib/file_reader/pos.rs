@@ -0,0 +1,234 @@
+//! Functions for handling _.pos_ files, see [`FileType::Pos`](super::FileType::Pos).
+use std::io::{Read, Write};
+
+use crate::board::{Board, BoardMarker, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+use crate::file_reader::FileType;
+
+/// Width of the board, _.pos_ files only support 15x15.
+const WIDTH: u32 = 15;
+/// Position used for a pass.
+pub const PASS: u8 = 0xF0;
+
+/// Read a _.pos_ file into `board` as a line from the root.
+///
+/// Moves already in the board are followed, so the line is only added where it differs.
+#[tracing::instrument(skip(file, board))]
+pub fn parse_pos(mut file: impl Read, board: &mut Board) -> Result<(), ParseError> {
+    FileType::Pos.check_board_size(board.size())?;
+    let mut buf = vec![];
+    file.read_to_end(&mut buf)?;
+    let (count, moves) = buf
+        .split_first()
+        .ok_or_else(|| ParseError::PosParseError("file is empty".to_owned()))?;
+    if usize::from(*count) != moves.len() {
+        return Err(ParseError::PosParseError(format!(
+            "expected {count} moves, found {}",
+            moves.len()
+        )));
+    }
+
+    let mut played = [false; (WIDTH * WIDTH) as usize];
+    let mut latest = board.get_root();
+    for (index, pos) in moves.iter().enumerate() {
+        let point = match *pos {
+            PASS => Point::null(),
+            pos if u32::from(pos) < WIDTH * WIDTH => {
+                if std::mem::replace(&mut played[usize::from(pos)], true) {
+                    return Err(ParseError::PosParseError(format!(
+                        "move #{} at {:?} is already played",
+                        index + 1,
+                        Point::from_1d(u32::from(pos), WIDTH)
+                    )));
+                }
+                Point::from_1d(u32::from(pos), WIDTH)
+            }
+            pos => {
+                return Err(ParseError::PosParseError(format!(
+                    "move #{} has position {pos:#04x}, which is outside the board",
+                    index + 1
+                )))
+            }
+        };
+        let marker = BoardMarker::new(point, Stone::from_bool(index % 2 == 0));
+        latest = board.get_or_insert_move(latest, marker);
+    }
+    Ok(())
+}
+
+/// Check if `bytes` looks like a _.pos_ file, i.e the count matches and every move is valid.
+#[must_use]
+pub fn sniff(bytes: &[u8]) -> bool {
+    parse_pos(bytes, &mut Board::new()).is_ok()
+}
+
+/// Write the moves leading up to `end` as a _.pos_ file, or the main line if `end` is `None`.
+///
+/// See [`Board::main_line`] for what the main line is. Markers which are not moves are skipped.
+#[tracing::instrument(skip(file, board))]
+pub fn write_pos(
+    mut file: impl Write,
+    board: &Board,
+    end: Option<MoveIndex>,
+) -> Result<(), ParseError> {
+    FileType::Pos.check_board_size(board.size())?;
+    let line = match end {
+        Some(end) => {
+            let mut line = board
+                .ancestors(&end)
+                .map(|(node, ..)| node)
+                .collect::<Vec<_>>();
+            line.reverse();
+            line
+        }
+        None => board.main_line(&board.get_root()),
+    };
+    let mut bytes = vec![0];
+    for index in line {
+        let marker = board
+            .get_move(index)
+            .ok_or_else(|| ParseError::Other(format!("Couldn't get move at: {:?}", index)))?;
+        if !marker.command.is_move() {
+            continue;
+        }
+        bytes.push(if marker.point.is_null {
+            PASS
+        } else if marker.point.x < WIDTH && marker.point.y < WIDTH {
+            marker.point.to_1d(WIDTH) as u8
+        } else {
+            return Err(ParseError::PosParseError(format!(
+                "{:?} can not be represented",
+                marker.point
+            )));
+        });
+    }
+    bytes[0] = u8::try_from(bytes.len() - 1)
+        .map_err(|_| ParseError::PosParseError(format!("{} moves is too many", bytes.len() - 1)))?;
+    file.write_all(&bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use test_log::test;
+
+    fn parse(bytes: &[u8]) -> Result<Board, ParseError> {
+        let mut board = Board::new();
+        parse_pos(bytes, &mut board)?;
+        Ok(board)
+    }
+
+    fn write(board: &Board, end: Option<MoveIndex>) -> Result<Vec<u8>, ParseError> {
+        let mut bytes = vec![];
+        write_pos(&mut bytes, board, end)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn pass() -> Result<(), ParseError> {
+        let board = parse(&std::fs::read("examplefiles/4th_is_a_pass.pos")?)?;
+        let line = board.main_line(&board.get_root());
+        let markers = line
+            .iter()
+            .map(|i| board.get_move(*i).map(|m| (m.point, m.color)))
+            .collect::<Option<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            markers,
+            [
+                (p![H, 8], Stone::Black),
+                (p![I, 8], Stone::White),
+                (p![G, 8], Stone::Black),
+                (Point::null(), Stone::White),
+                (p![J, 8], Stone::Black),
+            ]
+        );
+        let (arr, moves) = board.as_board(line.last().unwrap())?;
+        assert_eq!(moves.len(), 5);
+        assert_eq!(arr.iter().filter(|m| !m.color.is_empty()).count(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), ParseError> {
+        for path in [
+            "examplefiles/4th_is_a_pass.pos",
+            "examplefiles/example.pos",
+            "examplefiles/pos_two_top_rows.pos",
+        ] {
+            let original = std::fs::read(path)?;
+            assert_eq!(write(&parse(&original)?, None)?, original, "{path}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(parse(&[]), Err(ParseError::PosParseError(_))));
+        // count is larger than the moves
+        assert!(matches!(
+            parse(&[0x03, 0x70, 0x71]),
+            Err(ParseError::PosParseError(_))
+        ));
+        // count is smaller than the moves
+        assert!(matches!(
+            parse(&[0x01, 0x70, 0x71]),
+            Err(ParseError::PosParseError(_))
+        ));
+        // 225 is just outside the board
+        assert!(matches!(
+            parse(&[0x02, 0x70, 0xE1]),
+            Err(ParseError::PosParseError(_))
+        ));
+        // same point twice
+        assert!(matches!(
+            parse(&[0x02, 0x70, 0x70]),
+            Err(ParseError::PosParseError(_))
+        ));
+        assert!(parse(&[0x00]).is_ok());
+    }
+
+    #[test]
+    fn shares_existing_moves() -> Result<(), ParseError> {
+        let mut board = parse(&[0x02, 0x70, 0x71])?;
+        parse_pos([0x02, 0x70, 0x72].as_slice(), &mut board)?;
+        let root = board.get_root();
+        let first = board.get_move_children(&root);
+        assert_eq!(first.len(), 1);
+        assert_eq!(board.get_move_children(&first[0]).len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn line_to_move() -> Result<(), color_eyre::Report> {
+        let mut board = Board::new();
+        crate::file_reader::renlib::parse_lib(
+            std::fs::read("examplefiles/lib_documented.lib")?.as_slice(),
+            &mut board,
+        )?;
+        // follow the last variation at the first branch, which is not the main line
+        let fork = board
+            .main_line(&board.get_root())
+            .into_iter()
+            .find(|m| board.get_move_children(m).len() > 1)
+            .unwrap();
+        let branch = *board.get_move_children(&fork).last().unwrap();
+        let end = board.main_line(&branch).pop().unwrap_or(branch);
+        let bytes = write(&board, Some(end))?;
+        let expected = board
+            .ancestors(&end)
+            .map(|(_, marker, _)| marker)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .filter(|m| m.command.is_move())
+            .map(|m| m.point.to_1d(WIDTH) as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(bytes[0] as usize, expected.len());
+        assert_eq!(bytes[1..], expected);
+        assert_ne!(bytes, write(&board, None)?);
+        Ok(())
+    }
+}
@@ -1,14 +1,19 @@
 //! Functions for handling renlib files.
 use bitflags::bitflags;
 
+use crate::file_reader::encoding::TextEncoding;
+use crate::file_reader::FileType;
 use crate::{board::Stone, errors::ParseError};
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::board::Board;
+use crate::board::{Board, BoardMarker};
 
+pub mod bdt;
 pub mod parser;
+pub mod pdb;
+pub mod writer;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Version {
     V30,
@@ -133,49 +138,122 @@ impl Command {
     }
 }
 
+/// Read a RenLib library into `board`.
+///
+/// Malformed files are reported with [`ParseError::MalformedLib`], this never panics. The encoding of
+/// the texts is detected, see [`parse_lib_with_encoding`].
 #[tracing::instrument(skip(file, board))]
-pub fn parse_lib(mut file: impl Read, board: &mut Board) -> Result<(), color_eyre::Report> {
-    let moves = match read_header(&mut file)? {
-        (v @ (Version::V30 | Version::V34), i) => parser::parse_v3x(file, v, i),
-    }?;
+pub fn parse_lib(file: impl Read, board: &mut Board) -> Result<(), ParseError> {
+    parse_lib_with_encoding(file, board, None)
+}
+
+/// Like [`parse_lib`], but decode the texts with `encoding`, or detect it if it's `None`.
+///
+/// The nodes are inserted as they are read, see [`parser::nodes`], so only the undecoded texts are
+/// held on to while the encoding is detected, and nothing when it is given. They are inserted into
+/// a copy of `board`, which takes its place once the whole file is read, so `board` is left as it
+/// was if the file is malformed. Transpositions are linked then, see
+/// [`Board::link_transpositions`].
+///
+/// The encoding used is recorded with [`Board::set_text_encoding`], so [`write_lib`] uses it too.
+/// If `board` already has one, e.g when merging a library into another, it's kept, so the texts
+/// that were there are saved in the code page they were read in.
+#[tracing::instrument(skip(file, board))]
+pub fn parse_lib_with_encoding(
+    mut file: impl Read,
+    board: &mut Board,
+    encoding: Option<TextEncoding>,
+) -> Result<(), ParseError> {
+    FileType::Lib.check_board_size(board.size())?;
+    let nodes = match read_header(&mut file)? {
+        (Version::V30 | Version::V34, i) => parser::nodes(file, i),
+    };
+    let mut scratch = board.clone();
+    insert_nodes(nodes, &mut scratch, encoding)?;
+    let links = scratch.link_transpositions();
+    tracing::debug!(links, "linked transpositions");
+    *board = scratch;
+    Ok(())
+}
+
+/// Insert the nodes of a library into `board`, see [`parse_lib_with_encoding`].
+fn insert_nodes(
+    nodes: impl Iterator<Item = Result<(BoardMarker, parser::RawTexts), ParseError>>,
+    board: &mut Board,
+    encoding: Option<TextEncoding>,
+) -> Result<(), ParseError> {
+    // the texts of the inserted moves, until the encoding is detected
+    let mut undecoded = vec![];
     let mut _new_moves = 0;
     let mut first_move = None;
-    let mut check_root = true;
     let mut stack = vec![];
+    // a leaf with nothing to return to has to be the last node
+    let mut dangling = None;
     // An adaptation of CRenLibDoc::AddLibrary
     board.move_to_root();
     let mut cur_move = board.current_move();
+    // The latest color played on every move in the move list, so the side to move is known
+    // without walking back through the list.
+    let mut colors = vec![Stone::Empty];
     tracing::debug!("starting parse of file");
-    let ten_percent = moves.len() / 10;
-    for (i, mut marker) in moves.into_iter().enumerate() {
+    for (i, node) in nodes.enumerate() {
+        let (mut marker, texts) = node?;
+        if let Some(error) = dangling.take() {
+            return Err(error);
+        }
         let span = tracing::debug_span!("processing", ?i);
         let _enter = span.enter();
-        if i % ten_percent == 0 {
+        if i % 100_000 == 0 {
             tracing::debug!("processing");
         }
+        let texts = match encoding {
+            Some(encoding) => {
+                texts.decode_into(&mut marker, encoding);
+                None
+            }
+            None => (!texts.is_empty()).then_some(texts),
+        };
+        // Files of version 3.4 start with the empty root node, that is the root of our board.
+        if i == 0 && marker.point.is_null {
+            let root = board.get_root();
+            if let Some(texts) = texts {
+                undecoded.push((root, texts));
+            }
+            let root = board
+                .get_move_mut(root)
+                .expect("root should always be in the graph");
+            if marker.oneline_comment.is_some() || marker.multiline_comment.is_some() {
+                root.oneline_comment = marker.oneline_comment;
+                root.multiline_comment = marker.multiline_comment;
+            }
+            if marker.board_text.is_some() {
+                root.board_text = marker.board_text;
+            }
+            continue;
+        }
         //tracing::trace!(marker = format!("{:#?}", marker), ?cur_move, "processing");
+        let last_color = *colors.last().expect("colors should follow the move list");
         if marker.command.is_move() {
-            let last_move = board
-                .move_list()
-                .iter()
-                .rev()
-                .filter_map(|i| board.get_move(*i))
-                .map(|m| m.color)
-                .find(|c| !c.is_empty())
-                .unwrap_or(Stone::White);
-            marker.color = match last_move {
+            marker.color = match last_color {
                 Stone::Black => Stone::White,
                 _ => Stone::Black,
             };
         }
-        // Not sure this is correct, oh well...
-        let next_move = board.get_variant_weird(&cur_move, &marker.point, &marker.color);
-        if let Some((m, next)) = next_move {
-            tracing::debug!(variant = ?(m, next), "found variant");
+        // Follow the move if it's already in the board, e.g when merging a library into another.
+        let existing = marker
+            .command
+            .is_move()
+            .then(|| board.find_move_child(&cur_move, &marker.point))
+            .flatten();
+        if let Some(next) = existing {
+            tracing::debug!(variant = ?next, "found variant");
             cur_move = next;
         } else {
             let next = board.insert_move(cur_move, marker.clone());
             cur_move = next;
+            if let Some(texts) = texts {
+                undecoded.push((next, texts));
+            }
             if marker.command.is_move() {
                 _new_moves += 1;
                 if first_move.is_none() {
@@ -183,32 +261,107 @@ pub fn parse_lib(mut file: impl Read, board: &mut Board) -> Result<(), color_eyr
                 }
             }
         }
-        if check_root && marker.point.is_null {
-            check_root = false;
+        board.add_move_to_move_list(cur_move);
+        colors.push(if marker.color.is_empty() {
+            last_color
         } else {
-            board.add_move_to_move_list(cur_move);
-        }
+            marker.color
+        });
 
         if marker.command.is_down() {
             stack.push(board.index())
         }
 
-        if marker.command.is_right() && !stack.is_empty() {
-            // FIXME: Proper error pls
-            let top = stack.pop().expect("stack should not be empty");
-            board.set_index(top - 1)?;
-            cur_move = board.current_move();
+        if marker.command.is_right() {
+            let offset = marker.index_in_file.unwrap_or_default();
+            match stack.pop() {
+                Some(top) => {
+                    board.set_index(top - 1).map_err(|_| {
+                        parser::malformed(
+                            offset,
+                            marker.command.bits(),
+                            "the DOWN node to return to to be on the current line",
+                        )
+                    })?;
+                    colors.truncate(top);
+                    cur_move = board.current_move();
+                }
+                None => {
+                    dangling = Some(parser::malformed(
+                        offset,
+                        marker.command.bits(),
+                        "a DOWN node to return to, as more nodes follow this leaf",
+                    ))
+                }
+            }
         }
     }
+
+    let encoding = encoding.unwrap_or_else(|| {
+        let encoding = TextEncoding::detect(
+            undecoded
+                .iter()
+                .flat_map(|(_, texts): &(_, parser::RawTexts)| texts.encoded()),
+        );
+        tracing::debug!(%encoding, "detected text encoding");
+        encoding
+    });
+    for (node, texts) in undecoded {
+        let marker = board
+            .get_move_mut(node)
+            .expect("moves are only added while reading");
+        texts.decode_into(marker, encoding);
+    }
+    if board.text_encoding().is_none() {
+        board.set_text_encoding(Some(encoding));
+    }
     Ok(())
 }
 
+/// Write `board` as a RenLib library of `version`.
+///
+/// The inverse of [`parse_lib`], see [`writer`] for how the tree is laid out. Texts are encoded with
+/// [`Board::text_encoding`], or [`TextEncoding::default`] if it's not known.
+#[tracing::instrument(skip(file, board))]
+pub fn write_lib(
+    mut file: impl Write,
+    board: &Board,
+    version: Version,
+) -> Result<(), color_eyre::Report> {
+    FileType::Lib.check_board_size(board.size())?;
+    write_header(&mut file, version)?;
+    writer::write_v3x(file, board, version)?;
+    Ok(())
+}
+
+/// Check if `bytes` starts like a RenLib library, the version is checked by [`validate_lib`].
+#[must_use]
+pub fn sniff(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"\xffRenLib\xff")
+}
+
 pub fn read_header(mut file: impl Read) -> Result<(Version, usize), ParseError> {
     let mut header = [0u8; 20];
-    file.read_exact(&mut header)?;
+    file.read_exact(&mut header).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => parser::malformed(0, 0, "a header of 20 bytes"),
+        _ => ParseError::IoError(e),
+    })?;
     Ok((validate_lib(&header)?, 20))
 }
 
+pub fn write_header(mut file: impl Write, version: Version) -> Result<(), ParseError> {
+    let (majv, minv) = match version {
+        Version::V30 => (3, 0),
+        Version::V34 => (3, 4),
+    };
+    let mut header = [0xff; 20];
+    header[1..7].copy_from_slice(b"RenLib");
+    header[8] = majv;
+    header[9] = minv;
+    file.write_all(&header)?;
+    Ok(())
+}
+
 pub fn validate_lib(header: &[u8]) -> Result<Version, ParseError> {
     match *header {
         [0xff, 0x52, 0x65, 0x6e, 0x4c, 0x69, 0x62, 0xff, majv, minv, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff] => {
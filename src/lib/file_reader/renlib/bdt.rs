@@ -0,0 +1,142 @@
+//! Functions for handling RenLib _.bdt_ files, see [`FileType::Bdt`](crate::file_reader::FileType::Bdt).
+//!
+//! A _.bdt_ file is text, with one line of moves per row:
+//!
+//! ```text
+//! ,=[,,,8887679586,,,]
+//! ```
+//!
+//! Every move is two hex digits, `column row`, counted from `1` in the top left corner. So `88` is
+//! the middle, the same as `0x78` in a _.lib_ file.
+use std::io::Read;
+
+use crate::board::{Board, BoardMarker, Point, Stone};
+use crate::errors::ParseError;
+use crate::file_reader::FileType;
+
+/// Index of the field containing the moves, inside the brackets.
+const MOVES_FIELD: usize = 3;
+
+/// Read every line of a _.bdt_ file into `board`, lines sharing a beginning share those moves.
+#[tracing::instrument(skip(file, board))]
+pub fn parse_bdt(mut file: impl Read, board: &mut Board) -> Result<(), ParseError> {
+    FileType::Bdt.check_board_size(board.size())?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    for (row, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let points = parse_line(line)
+            .map_err(|e| ParseError::BdtParseError(format!("row {}: {e}", row + 1)))?;
+        board.get_or_insert_line(
+            board.get_root(),
+            points
+                .into_iter()
+                .enumerate()
+                .map(|(index, point)| BoardMarker::new(point, Stone::from_bool(index % 2 == 0))),
+        );
+    }
+    Ok(())
+}
+
+/// Check if `bytes` looks like a _.bdt_ file, i.e it is text and every row is a line of moves.
+#[must_use]
+pub fn sniff(bytes: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return false;
+    };
+    let mut rows = text.lines().filter(|l| !l.trim().is_empty()).peekable();
+    rows.peek().is_some() && rows.all(|row| parse_line(row).is_ok())
+}
+
+/// Parse the moves of a single row.
+fn parse_line(line: &str) -> Result<Vec<Point>, String> {
+    let inner = line
+        .trim()
+        .split_once('[')
+        .and_then(|(_, rest)| rest.strip_suffix(']'))
+        .ok_or_else(|| format!("expected moves in brackets, found {line:?}"))?;
+    let moves = inner
+        .split(',')
+        .nth(MOVES_FIELD)
+        .ok_or_else(|| format!("expected at least {} fields", MOVES_FIELD + 1))?;
+    if moves.len() % 2 != 0 {
+        return Err(format!("{moves:?} is not a list of moves"));
+    }
+    moves
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let coordinate = |c: u8| {
+                (c as char)
+                    .to_digit(16)
+                    .filter(|d| (1..=15).contains(d))
+                    .map(|d| d - 1)
+            };
+            match (coordinate(pair[0]), coordinate(pair[1])) {
+                (Some(x), Some(y)) => Ok(Point::new(x, y)),
+                _ => Err(format!("{:?} is not a move", String::from_utf8_lossy(pair))),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MoveIndex;
+    use crate::file_reader::renlib::parse_lib;
+    use crate::p;
+    use test_log::test;
+
+    fn count(board: &Board, node: MoveIndex) -> usize {
+        1 + board
+            .get_move_children(&node)
+            .into_iter()
+            .map(|child| count(board, child))
+            .sum::<usize>()
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            parse_line(",=[,,,88876795,,,]"),
+            Ok(p![[H, 8], [H, 9], [F, 9], [I, 11]].to_vec())
+        );
+        assert_eq!(parse_line(",=[,,,,,,]"), Ok(vec![]));
+        assert!(parse_line(",=[,,,8,,,]").is_err());
+        assert!(parse_line(",=[,,,80,,,]").is_err());
+        assert!(parse_line(",=[,,,8G,,,]").is_err());
+        assert!(parse_line("8887").is_err());
+    }
+
+    #[test]
+    fn matches_lib() -> Result<(), color_eyre::Report> {
+        let mut board = Board::new();
+        parse_lib(
+            std::fs::read("examplefiles/nocomments.lib")?.as_slice(),
+            &mut board,
+        )?;
+        let nodes = count(&board, board.get_root());
+        // every line is already in the library
+        parse_bdt(
+            std::fs::read("examplefiles/nocomments.bdt")?.as_slice(),
+            &mut board,
+        )?;
+        assert_eq!(count(&board, board.get_root()), nodes);
+
+        let mut bdt = Board::new();
+        parse_bdt(
+            std::fs::read("examplefiles/nocomments.bdt")?.as_slice(),
+            &mut bdt,
+        )?;
+        let root = bdt.get_root();
+        // 4 rows, the second and third are the beginning of the first, the fourth shares H8
+        assert_eq!(count(&bdt, root), 1 + 10 + 7);
+        let last = *bdt.main_line(&root).last().unwrap();
+        let marker = bdt.get_move(last).unwrap();
+        assert_eq!((marker.point, marker.color), (p![J, 11], Stone::White));
+        Ok(())
+    }
+}
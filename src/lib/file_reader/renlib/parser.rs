@@ -1,4 +1,6 @@
 use crate::board::{BoardMarker, Point, Stone};
+use crate::errors::ParseError;
+use crate::file_reader::encoding::TextEncoding;
 
 use super::Version;
 pub use super::{Command, CommandVariant};
@@ -18,7 +20,103 @@ mod tests {
 
     fn parse_v30(bytes: &'static [u8]) -> Result<Vec<BoardMarker>, color_eyre::Report> {
         let mut bytes = buf(bytes);
-        parse_v3x(&mut bytes, Version::V30, 0)
+        Ok(parse_v3x(&mut bytes, Version::V30, 0)?)
+    }
+
+    #[track_caller]
+    fn assert_malformed(bytes: &'static [u8], offset: usize, command: u32) {
+        match parse_v3x(bytes, Version::V30, 0) {
+            Err(ParseError::MalformedLib {
+                offset: o,
+                command: c,
+                ..
+            }) => assert_eq!((o, c), (offset, command)),
+            other => panic!("expected malformed file, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        // extension is cut off
+        assert_malformed(&[0x78, 0x00, 0x79, 0x01, 0x00], 2, 0x01);
+        // unknown extended command
+        assert_malformed(&[0x78, 0x00, 0x79, 0x41, 0x80, 0x00], 2, 0x80_0041);
+        // comment without a terminating null
+        assert_malformed(&[0x78, 0x08, b'a', b'b'], 0, 0x08);
+        // board text without a terminating null
+        assert_malformed(&[0x78, 0x01, 0x00, 0x01, b'a'], 0, 0x0101);
+        // x is outside the board
+        assert_malformed(&[0x78, 0x00, 0x10, 0x00], 2, 0x00);
+        // y is outside the board
+        assert_malformed(&[0xF1, 0x00], 0, 0x00);
+    }
+
+    #[test]
+    fn nodes_one_at_a_time() {
+        // a move, then a comment without a terminating null
+        let mut nodes = nodes(&[0x78, 0x00, 0x79, 0x08, b'a', b'b'][..], 0);
+        let (marker, texts) = nodes.next().unwrap().unwrap();
+        assert_eq!(marker.point, p![H, 8]);
+        assert!(texts.is_empty());
+        assert!(matches!(
+            nodes.next(),
+            Some(Err(ParseError::MalformedLib { offset: 2, .. }))
+        ));
+        assert!(nodes.next().is_none());
+    }
+
+    #[test]
+    fn empty_texts() -> Result<(), color_eyre::Report> {
+        let markers = parse_v30(&[0x78, 0x09, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00])?;
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].oneline_comment.as_deref(), Some(""));
+        assert_eq!(markers[0].board_text.as_deref(), Some(""));
+        // the padding after the null is not part of the text
+        let markers = parse_v30(&[0x78, 0x08, b'a', b'b', 0x00, b'c', 0x79, 0x40])?;
+        assert_eq!(markers.len(), 2);
+        assert_eq!(markers[0].oneline_comment.as_deref(), Some("ab"));
+        Ok(())
+    }
+
+    /// Feed mutations of the example files through [`parse_lib`](super::super::parse_lib), it
+    /// should never panic. See `fuzz/` for the real fuzzer.
+    #[test]
+    fn mutations_do_not_panic() -> Result<(), color_eyre::Report> {
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = move || {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for entry in std::fs::read_dir("examplefiles")? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|e| e != "lib") {
+                continue;
+            }
+            let original = std::fs::read(&path)?;
+            for _ in 0..200 {
+                let mut bytes = original.clone();
+                match next() % 3 {
+                    0 => bytes.truncate(next() as usize % (bytes.len() + 1)),
+                    1 => {
+                        for _ in 0..=next() % 4 {
+                            let i = next() as usize % bytes.len();
+                            bytes[i] = next() as u8;
+                        }
+                    }
+                    _ => {
+                        // keep the header, so the nodes are parsed
+                        let i = 20 + next() as usize % (bytes.len() - 19);
+                        bytes.insert(i, next() as u8);
+                    }
+                }
+                let mut board = crate::board::Board::new();
+                let _ = super::super::parse_lib(bytes.as_slice(), &mut board);
+            }
+        }
+        Ok(())
     }
 
     #[test]
@@ -310,75 +408,231 @@ mod tests {
     }
 }
 
+/// Make a [`ParseError::MalformedLib`] for the node at `offset`.
+pub(crate) fn malformed(offset: usize, command: u32, expected: impl Into<String>) -> ParseError {
+    ParseError::MalformedLib {
+        offset,
+        command,
+        expected: expected.into(),
+    }
+}
+
+/// A reader that ended in the middle of the node at `offset` is malformed, other errors are passed on.
+fn eof_as_malformed(
+    error: std::io::Error,
+    offset: usize,
+    command: u32,
+    expected: &str,
+) -> ParseError {
+    match error.kind() {
+        std::io::ErrorKind::UnexpectedEof => malformed(offset, command, expected),
+        _ => ParseError::IoError(error),
+    }
+}
+
+/// Fill `buf` as far as possible, returns how many bytes were read.
+///
+/// Unlike [`std::io::Read::read_exact`], this makes it possible to tell a clean end of the file
+/// apart from a file ending in the middle of a node.
+fn fill(mut bytes: impl std::io::Read, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    let mut read = 0;
+    while read < buf.len() {
+        match bytes.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+/// Parse the nodes of a library, `index` is the offset in the file of the first node.
+///
+/// The nodes are returned in the order they are stored, see [`writer`](super::writer) for how the
+/// tree is laid out. Malformed input is reported as [`ParseError::MalformedLib`] with the offset of
+/// the node. The encoding of the texts is detected, see [`parse_v3x_with_encoding`], and [`nodes`]
+/// to read the nodes one at a time.
 #[tracing::instrument(skip(bytes, index))]
 pub fn parse_v3x(
-    mut bytes: impl std::io::Read,
-    _version: Version,
-    mut index: usize,
-) -> Result<Vec<BoardMarker>, color_eyre::eyre::Report> {
-    let mut vec = vec![];
-    let mut buf: [u8; 2] = [0, 0];
-    let mut string_buf = Vec::new();
+    bytes: impl std::io::Read,
+    version: Version,
+    index: usize,
+) -> Result<Vec<BoardMarker>, ParseError> {
+    parse_v3x_with_encoding(bytes, version, index, None).map(|(markers, _)| markers)
+}
 
-    loop {
-        match bytes.read_exact(&mut buf) {
-            Ok(_) => index += 2,
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::UnexpectedEof => break,
-                _ => todo!(),
-            },
+/// Texts of a node, before they are decoded.
+///
+/// The encoding of a library can only be told from all of its texts, see [`TextEncoding::detect`],
+/// so these are kept until it's known.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RawTexts {
+    comment: Option<Vec<u8>>,
+    old_comment: Option<Vec<u8>>,
+    board_text: Option<Vec<u8>>,
+}
+
+impl RawTexts {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.comment.is_none() && self.old_comment.is_none() && self.board_text.is_none()
+    }
+
+    /// The texts that are decoded with the encoding of the library, for [`TextEncoding::detect`].
+    pub fn encoded(&self) -> impl Iterator<Item = &[u8]> {
+        [self.comment.as_deref(), self.board_text.as_deref()]
+            .into_iter()
+            .flatten()
+    }
+
+    /// Decode the texts into the comments and board text of `marker`.
+    pub fn decode_into(self, marker: &mut BoardMarker, encoding: TextEncoding) {
+        if let Some(text) = self.comment {
+            (marker.oneline_comment, marker.multiline_comment) = parse_comments(&text, encoding);
+        } else if let Some(text) = self.old_comment {
+            (marker.oneline_comment, marker.multiline_comment) = parse_old_comments(&text);
         }
-        let point = if buf[0] == 0x00 {
-            Point::null()
-        } else {
-            Point::from_byte(buf[0])?
-        };
-        let mut mark = BoardMarker::new(point, Stone::Empty);
-        mark.index_in_file = Some(index - 2);
-        let command = Command::new(u32::from(buf[1]))?;
+        if let Some(text) = self.board_text {
+            marker.board_text = Some(parse_board_text(&text, encoding));
+        }
+    }
+}
 
-        let command = if command.is_extension() {
-            bytes.read_exact(&mut buf)?;
-            index += 2;
-            // tracing::trace!("extension: {:#4b}, {:#4b}", buf[0], buf[1]);
-            let mut cmd = command.0.bits() & 0xFF;
+/// The nodes of a library, read one at a time, see [`nodes`].
+#[derive(Debug)]
+pub struct Nodes<R> {
+    bytes: R,
+    /// Offset in the file of the next node.
+    index: usize,
+    done: bool,
+}
+
+/// Read the nodes of a library one at a time, `index` is the offset in the file of the first
+/// node.
+///
+/// Unlike [`parse_v3x`], nothing but the current node is kept, so libraries of any size can be
+/// read with the memory of one node. The texts are not decoded, see [`RawTexts::decode_into`].
+/// After an error, no more nodes are read.
+pub fn nodes<R: std::io::Read>(bytes: R, index: usize) -> Nodes<R> {
+    Nodes {
+        bytes,
+        index,
+        done: false,
+    }
+}
 
-            cmd |= ((u32::from(buf[0]) << 8) | u32::from(buf[1])) << 8;
-            Command::new(cmd)?
-        } else {
-            command
+impl<R: std::io::Read> Nodes<R> {
+    fn read_node(&mut self) -> Result<Option<(BoardMarker, RawTexts)>, ParseError> {
+        let mut buf: [u8; 2] = [0, 0];
+        let offset = self.index;
+        match fill(&mut self.bytes, &mut buf)? {
+            0 => return Ok(None),
+            // some files have a byte of padding at the end
+            1 => {
+                tracing::warn!(offset, "ignoring trailing byte");
+                return Ok(None);
+            }
+            _ => self.index += 2,
+        }
+        let mut bits = u32::from(buf[1]);
+        let point = match buf[0] {
+            0x00 => Point::null(),
+            byte => Point::from_byte(byte)?,
         };
+        if !point.is_null && (point.x >= 15 || point.y >= 15) {
+            return Err(malformed(
+                offset,
+                bits,
+                format!("a position on the board, found {:#04x}", buf[0]),
+            ));
+        }
+        let mut mark = BoardMarker::new(point, Stone::Empty);
+        mark.index_in_file = Some(offset);
 
-        if command.is_comment() {
-            let ((one, multi), read) = parse_comments(&mut bytes, &mut string_buf)?;
-            mark.oneline_comment = one;
-            mark.multiline_comment = multi;
-            // tracing::trace!(?mark.oneline_comment, ?mark.multiline_comment);
-            index += read;
-            string_buf.clear();
-        } else if command.is_old_comment() {
-            let ((one, multi), read) = parse_old_comments(&mut bytes, &mut string_buf)?;
-            mark.oneline_comment = one;
-            mark.multiline_comment = multi;
-            // tracing::trace!(?mark.oneline_comment, ?mark.multiline_comment);
-            index += read;
-            string_buf.clear();
+        if bits & CommandVariant::EXTENSION.bits() != 0 {
+            self.bytes
+                .read_exact(&mut buf)
+                .map_err(|e| eof_as_malformed(e, offset, bits, "two bytes of extended command"))?;
+            self.index += 2;
+            // tracing::trace!("extension: {:#4b}, {:#4b}", buf[0], buf[1]);
+            bits |= ((u32::from(buf[0]) << 8) | u32::from(buf[1])) << 8;
+        }
+        let command =
+            Command::new(bits).map_err(|_| malformed(offset, bits, "only known command flags"))?;
+
+        let mut raw = RawTexts::default();
+        if command.is_comment() || command.is_old_comment() {
+            let mut text = vec![];
+            self.index += read_text(&mut self.bytes, &mut text)
+                .map_err(|e| eof_as_malformed(e, offset, bits, "a null terminated comment"))?;
+            if command.is_comment() {
+                raw.comment = Some(text);
+            } else {
+                raw.old_comment = Some(text);
+            }
         }
 
         if command.is_board_text() {
-            let (board_text, read) = parse_board_text(&mut bytes, &mut string_buf)?;
-            mark.board_text = Some(board_text);
-            index += read;
-            string_buf.clear();
+            let mut text = vec![];
+            self.index += read_text(&mut self.bytes, &mut text)
+                .map_err(|e| eof_as_malformed(e, offset, bits, "a null terminated board text"))?;
+            raw.board_text = Some(text);
         }
 
         // tracing::trace!(?mark, ?command, "evaluated");
         mark.command = command;
-        vec.push(mark)
+        Ok(Some((mark, raw)))
+    }
+}
+
+impl<R: std::io::Read> Iterator for Nodes<R> {
+    type Item = Result<(BoardMarker, RawTexts), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let node = self.read_node().transpose();
+        self.done = !matches!(node, Some(Ok(_)));
+        node
     }
-    Ok(vec)
 }
 
+impl<R: std::io::Read> std::iter::FusedIterator for Nodes<R> {}
+
+/// Like [`parse_v3x`], but decode the texts with `encoding`, or detect it with
+/// [`TextEncoding::detect`] if it's `None`.
+///
+/// Returns the nodes and the encoding used.
+#[tracing::instrument(skip(bytes, index))]
+pub fn parse_v3x_with_encoding(
+    bytes: impl std::io::Read,
+    version: Version,
+    index: usize,
+    encoding: Option<TextEncoding>,
+) -> Result<(Vec<BoardMarker>, TextEncoding), ParseError> {
+    let (markers, texts): (Vec<_>, Vec<_>) = nodes(bytes, index).collect::<Result<_, _>>()?;
+    let encoding = encoding.unwrap_or_else(|| {
+        let encoding = TextEncoding::detect(texts.iter().flat_map(RawTexts::encoded));
+        tracing::debug!(%encoding, "detected text encoding");
+        encoding
+    });
+    let markers = markers
+        .into_iter()
+        .zip(texts)
+        .map(|(mut marker, texts)| {
+            texts.decode_into(&mut marker, encoding);
+            marker
+        })
+        .collect();
+    Ok((markers, encoding))
+}
+
+/// Read a null terminated string stored in chunks of two bytes.
+///
+/// `buf` will contain the string followed by a single null, anything after the first null is
+/// padding and is dropped.
 pub fn read_text(
     mut bytes: impl std::io::Read,
     buf: &mut Vec<u8>,
@@ -391,89 +645,53 @@ pub fn read_text(
     loop {
         bytes.read_exact(&mut t_buf)?;
         index += 2;
-        match t_buf {
-            [0, 0] => {
-                buf.push(0);
-            }
-            s => buf.extend(s),
-        }
+        buf.extend(t_buf);
         if t_buf.contains(&0) {
             break;
         }
     }
-    assert!(buf.len() > 1);
+    let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len() - 1);
+    buf.truncate(end + 1);
     Ok(index)
 }
 
-#[derive(thiserror::Error, Debug)]
-pub enum ParseBoardTextError {
-    #[error("read from board text buffer failed")]
-    Io(#[from] std::io::Error),
-}
-
-fn parse_board_text(
-    bytes: impl std::io::Read,
-    buf: &mut Vec<u8>,
-) -> Result<(String, usize), ParseBoardTextError> {
+/// Decode a board text read by [`read_text`].
+fn parse_board_text(buf: &[u8], encoding: TextEncoding) -> String {
     // Board text is a null padded null-ending string, iff len % 2 == 1
     // so: the string "AA\0" becomes "AA\0\0"
-
-    let read = read_text(bytes, buf)?;
-    assert!(buf.len() > 1);
-    assert!(buf.last().unwrap() == &0);
-
-    Ok((
-        String::from_utf8_lossy(&buf[..buf.len() - 1]).to_string(),
-        read,
-    ))
-}
-
-#[derive(thiserror::Error, Debug)]
-pub enum ParseCommentError {
-    #[error("read from comment buffer failed")]
-    Io(#[from] std::io::Error),
+    encoding.decode(&buf[..buf.len() - 1])
 }
 
-#[allow(clippy::type_complexity)]
-pub fn parse_comments(
-    bytes: impl std::io::Read,
-    buf: &mut Vec<u8>,
-) -> Result<((Option<String>, Option<String>), usize), ParseCommentError> {
-    // The comments are either:
-    //
-    // oneline + 0
-    // oneline + 8 + multiline + 0
-    // 8 + multiline + 0
-    // if the bytes are uneven, they will be padded with an extra 0, this is accounted for with out buffer read.
-
-    let mut one = None;
-    let mut multi = None;
-
-    let read = read_text(bytes, buf)?;
-
-    if &0x08 == buf.first().unwrap() {
+/// Split and decode a comment read by [`read_text`] into the oneline and multiline comment.
+///
+/// The comments are either:
+///
+/// * `oneline + 0`
+/// * `oneline + 8 + multiline + 0`
+/// * `8 + multiline + 0`
+///
+/// The split is done on the bytes, `0x08` is never part of a multibyte character in the supported
+/// encodings.
+#[must_use]
+pub fn parse_comments(buf: &[u8], encoding: TextEncoding) -> (Option<String>, Option<String>) {
+    // without the null
+    let text = &buf[..buf.len().saturating_sub(1)];
+    match text.iter().position(|b| *b == 0x08) {
         // FIXME: Could be empty
-        multi = Some(String::from_utf8_lossy(&buf[1..buf.len() - 1]).to_string())
-    } else if let Some(pos) = buf.iter().position(|b| *b == 0x08) {
-        one = Some(String::from_utf8_lossy(&buf[0..pos]).to_string());
-        multi = Some(String::from_utf8_lossy(&buf[(pos + 1)..buf.len() - 1]).to_string());
-    } else {
-        one = Some(String::from_utf8_lossy(&buf[..buf.len() - 1]).to_string());
+        Some(0) => (None, Some(encoding.decode(&text[1..]))),
+        Some(pos) => (
+            Some(encoding.decode(&text[..pos])),
+            Some(encoding.decode(&text[(pos + 1)..])),
+        ),
+        None => (Some(encoding.decode(text)), None),
     }
-
-    Ok(((one, multi), read))
 }
 
-#[allow(clippy::type_complexity)]
-pub fn parse_old_comments(
-    bytes: impl std::io::Read,
-    buf: &mut Vec<u8>,
-) -> Result<((Option<String>, Option<String>), usize), ParseCommentError> {
-    let mut one = None;
-    let mut multi = None;
-    let read = read_text(bytes, buf)?;
+/// Like [`parse_comments`], for the old comments which store `åäöÅÄÖ` as ASCII.
+#[must_use]
+pub fn parse_old_comments(buf: &[u8]) -> (Option<String>, Option<String>) {
     let buf = buf
-        .iter_mut()
+        .iter()
         .map(|c| match c {
             // FIXME: There has to be more like this, no?
             b'}' => 0xE5,
@@ -485,15 +703,6 @@ pub fn parse_old_comments(
             other => *other,
         })
         .collect::<Vec<_>>();
-
-    if &0x08 == buf.first().unwrap() {
-        // FIXME: Could be empty
-        multi = Some(String::from_utf8_lossy(&buf[1..buf.len() - 1]).to_string())
-    } else if let Some(pos) = buf.iter().position(|b| *b == 0x08) {
-        one = Some(String::from_utf8_lossy(&buf[0..pos]).to_string());
-        multi = Some(String::from_utf8_lossy(&buf[(pos + 1)..buf.len() - 1]).to_string());
-    } else {
-        one = Some(String::from_utf8_lossy(&buf[..buf.len() - 1]).to_string());
-    }
-    Ok(((one, multi), read))
+    // the replacements are in windows-1252
+    parse_comments(&buf, TextEncoding::Windows1252)
 }
@@ -0,0 +1,207 @@
+//! Functions for handling RenLib _.pdb_ files, see [`FileType::Pdb`](crate::file_reader::FileType::Pdb).
+//!
+//! These are Palm OS databases of positions, with type `DATA` and creator `5Ren`.
+//!
+//! # Layout in binary
+//!
+//! All numbers are big endian.
+//!
+//! * `0x00..0x20`: name of the database, null terminated
+//! * `0x3C..0x40`: type, `DATA`
+//! * `0x40..0x44`: creator, `5Ren`
+//! * `0x4C..0x4E`: number of records, `N`
+//! * `0x4E..`: `N` record entries of 8 bytes, the first 4 is the offset of the record in the file
+//!
+//! Every record is one position:
+//!
+//! * 0..2: unknown, `00 08`
+//! * 2: number of stones, `S`
+//! * 3..6: unknown
+//! * 6..: `S` stones as `POS:COLOR`, `POS` is `(x << 4) + y` and `COLOR` is `2` for black, `1` for white
+//! * title, null terminated
+use std::io::Read;
+
+use crate::board::{Board, BoardMarker, Point, Stone};
+use crate::errors::ParseError;
+use crate::file_reader::FileType;
+
+const HEADER_LEN: usize = 0x4E;
+const RECORD_ENTRY_LEN: usize = 8;
+const RECORD_HEADER_LEN: usize = 6;
+
+/// A position stored in a _.pdb_ file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub title: String,
+    pub stones: Vec<(Point, Stone)>,
+}
+
+/// Read every record of a _.pdb_ file into `board` as a line from the root.
+///
+/// The title of the record is set as a comment on the last stone.
+#[tracing::instrument(skip(file, board))]
+pub fn parse_pdb(mut file: impl Read, board: &mut Board) -> Result<(), ParseError> {
+    FileType::Pdb.check_board_size(board.size())?;
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes)?;
+    for record in read_records(&bytes)? {
+        let last = board.get_or_insert_line(
+            board.get_root(),
+            record
+                .stones
+                .iter()
+                .map(|(point, color)| BoardMarker::new(*point, *color)),
+        );
+        if last != board.get_root() && !record.title.is_empty() {
+            let marker = board.get_move_mut(last).expect("move was just inserted");
+            if marker.oneline_comment.is_none() {
+                marker.set_oneline_comment(record.title);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check if `bytes` looks like a _.pdb_ file, i.e it has the type and creator of RenLib.
+#[must_use]
+pub fn sniff(bytes: &[u8]) -> bool {
+    bytes.get(0x3C..0x44) == Some(b"DATA5Ren")
+}
+
+/// Read the records of a _.pdb_ file.
+pub fn read_records(bytes: &[u8]) -> Result<Vec<Record>, ParseError> {
+    let error = |msg: String| ParseError::PdbParseError(msg);
+    if bytes.len() < HEADER_LEN {
+        return Err(error(format!("file is too short, {} bytes", bytes.len())));
+    }
+    if !sniff(bytes) {
+        return Err(error(format!(
+            "expected type and creator \"DATA5Ren\", found {:?}",
+            String::from_utf8_lossy(&bytes[0x3C..0x44])
+        )));
+    }
+    let count = usize::from(u16::from_be_bytes([bytes[0x4C], bytes[0x4D]]));
+    let entries = bytes
+        .get(HEADER_LEN..HEADER_LEN + count * RECORD_ENTRY_LEN)
+        .ok_or_else(|| error(format!("file is too short for {count} records")))?;
+    let offsets = entries
+        .chunks(RECORD_ENTRY_LEN)
+        .map(|entry| u32::from_be_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize)
+        .collect::<Vec<_>>();
+
+    let mut records = Vec::with_capacity(count);
+    for (index, start) in offsets.iter().enumerate() {
+        let end = offsets.get(index + 1).copied().unwrap_or(bytes.len());
+        let record = bytes
+            .get(*start..end)
+            .ok_or_else(|| error(format!("record #{index} at {start:#x} is outside the file")))?;
+        records.push(read_record(record).map_err(|e| error(format!("record #{index}: {e}")))?);
+    }
+    Ok(records)
+}
+
+fn read_record(record: &[u8]) -> Result<Record, String> {
+    let header = record
+        .get(..RECORD_HEADER_LEN)
+        .ok_or_else(|| "record is too short".to_owned())?;
+    let count = usize::from(header[2]);
+    let stones = record
+        .get(RECORD_HEADER_LEN..RECORD_HEADER_LEN + count * 2)
+        .ok_or_else(|| format!("record is too short for {count} stones"))?
+        .chunks(2)
+        .map(|stone| {
+            let (x, y) = (u32::from(stone[0] >> 4), u32::from(stone[0] & 0xF));
+            if x >= 15 || y >= 15 {
+                return Err(format!("{:#04x} is outside the board", stone[0]));
+            }
+            let color = match stone[1] {
+                1 => Stone::White,
+                2 => Stone::Black,
+                other => return Err(format!("unknown color {other}")),
+            };
+            Ok((Point::new(x, y), color))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let title = &record[RECORD_HEADER_LEN + count * 2..];
+    let title = title.split(|b| *b == 0).next().unwrap_or_default();
+    Ok(Record {
+        title: String::from_utf8_lossy(title).into_owned(),
+        stones,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_reader::renlib::parse_lib;
+    use test_log::test;
+
+    #[test]
+    fn records() -> Result<(), ParseError> {
+        let records = read_records(&std::fs::read("examplefiles/lib_two_top_rows.pdb")?)?;
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.title, "No.1");
+        assert_eq!(record.stones.len(), 30);
+        assert_eq!(record.stones[0], (Point::new(0, 0), Stone::Black));
+        assert_eq!(record.stones[1], (Point::new(1, 0), Stone::White));
+        assert_eq!(record.stones[29], (Point::new(14, 1), Stone::White));
+        Ok(())
+    }
+
+    #[test]
+    fn matches_lib() -> Result<(), color_eyre::Report> {
+        let mut lib = Board::new();
+        parse_lib(
+            std::fs::read("examplefiles/lib_two_top_rows.lib")?.as_slice(),
+            &mut lib,
+        )?;
+        let mut pdb = Board::new();
+        parse_pdb(
+            std::fs::read("examplefiles/lib_two_top_rows.pdb")?.as_slice(),
+            &mut pdb,
+        )?;
+        let stones = |board: &Board| {
+            board
+                .main_line(&board.get_root())
+                .into_iter()
+                .map(|m| {
+                    let marker = board.get_move(m).unwrap();
+                    (marker.point, marker.color)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(stones(&lib), stones(&pdb));
+        let last = *pdb.main_line(&pdb.get_root()).last().unwrap();
+        assert_eq!(
+            pdb.get_move(last).unwrap().oneline_comment.as_deref(),
+            Some("No.1")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            read_records(&[]),
+            Err(ParseError::PdbParseError(_))
+        ));
+        let mut bytes = std::fs::read("examplefiles/lib_two_top_rows.pdb").unwrap();
+        // unknown color of the first stone
+        bytes[0x5D] = 3;
+        assert!(matches!(
+            read_records(&bytes),
+            Err(ParseError::PdbParseError(_))
+        ));
+        bytes.truncate(0x60);
+        assert!(matches!(
+            read_records(&bytes),
+            Err(ParseError::PdbParseError(_))
+        ));
+        bytes[0x40] = b'X';
+        assert!(matches!(
+            read_records(&bytes),
+            Err(ParseError::PdbParseError(_))
+        ));
+    }
+}
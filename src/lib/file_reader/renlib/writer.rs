@@ -0,0 +1,384 @@
+//! Serialization of a [`Board`] into the RenLib format read by [`parse_v3x`](super::parser::parse_v3x).
+//!
+//! The tree is written depth first, every node as `POS:FLAGS[:EXTENSION][:COMMENT][:BOARDTEXT]`.
+//! The structure is described by two flags:
+//!
+//! * `DOWN` - the node has a sibling that is written after its subtree.
+//! * `RIGHT` - the node has no children, the next node is the sibling of the latest `DOWN` node.
+use std::io::Write;
+
+use crate::board::{Board, BoardMarker, MoveIndex};
+use crate::file_reader::encoding::TextEncoding;
+
+use super::{CommandVariant, Version};
+
+/// Flags that are decided by the writer, based on the tree and the contents of the marker.
+pub const LAYOUT_FLAGS: CommandVariant = CommandVariant::DOWN
+    .union(CommandVariant::RIGHT)
+    .union(CommandVariant::OLDCOMMENT)
+    .union(CommandVariant::COMMENT)
+    .union(CommandVariant::EXTENSION)
+    .union(CommandVariant::BOARDTEXT);
+
+#[tracing::instrument(skip(bytes, board))]
+pub fn write_v3x(
+    mut bytes: impl Write,
+    board: &Board,
+    version: Version,
+) -> Result<(), std::io::Error> {
+    let encoding = board.text_encoding().unwrap_or_default();
+    let root = board.get_root();
+    let root_marker = board
+        .get_move(root)
+        .expect("root should always be in the graph");
+    let top = board.get_move_children(&root);
+    match version {
+        Version::V34 => {
+            let mut command = CommandVariant::empty();
+            if top.is_empty() {
+                command |= CommandVariant::RIGHT;
+            }
+            write_marker(&mut bytes, root_marker, command, encoding)?;
+        }
+        Version::V30 => {
+            if root_marker.oneline_comment.is_some()
+                || root_marker.multiline_comment.is_some()
+                || root_marker.board_text.is_some()
+            {
+                tracing::warn!("version 3.0 has no root node, dropping comments on root");
+            }
+        }
+    }
+
+    // (node, has a sibling after it)
+    let mut stack: Vec<(MoveIndex, bool)> = Vec::new();
+    push_children(&mut stack, top);
+    while let Some((node, down)) = stack.pop() {
+        let marker = board.get_move(node).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("couldn't get move at: {node:?}"),
+            )
+        })?;
+        let children = board.get_move_children(&node);
+        let mut command = marker.command.difference(LAYOUT_FLAGS);
+        if down {
+            command |= CommandVariant::DOWN;
+        }
+        if children.is_empty() {
+            command |= CommandVariant::RIGHT;
+        }
+        write_marker(&mut bytes, marker, command, encoding)?;
+        push_children(&mut stack, children);
+    }
+    Ok(())
+}
+
+/// Push children in reverse, so that the first child is popped first.
+fn push_children(stack: &mut Vec<(MoveIndex, bool)>, children: Vec<MoveIndex>) {
+    let last = children.len().saturating_sub(1);
+    stack.extend(
+        children
+            .into_iter()
+            .enumerate()
+            .rev()
+            .map(|(i, child)| (child, i != last)),
+    );
+}
+
+/// Write a single node, `command` should only contain the layout flags `DOWN` and `RIGHT`
+/// together with the flags kept from the marker.
+fn write_marker(
+    mut bytes: impl Write,
+    marker: &BoardMarker,
+    mut command: CommandVariant,
+    encoding: TextEncoding,
+) -> Result<(), std::io::Error> {
+    let comment = comment_bytes(marker, encoding);
+    let board_text = marker.board_text.as_deref().filter(|t| !t.is_empty());
+    if comment.is_some() {
+        command |= CommandVariant::COMMENT;
+    }
+    if board_text.is_some() {
+        command |= CommandVariant::BOARDTEXT;
+    }
+    if command.bits() > 0xFF {
+        command |= CommandVariant::EXTENSION;
+    }
+
+    bytes.write_all(&[marker.point.to_byte(), command.bits() as u8])?;
+    if command.contains(CommandVariant::EXTENSION) {
+        let extension = command.bits() >> 8;
+        bytes.write_all(&[(extension >> 8) as u8, extension as u8])?;
+    }
+    if let Some(comment) = comment {
+        write_text(&mut bytes, &comment)?;
+    }
+    if let Some(board_text) = board_text {
+        write_text(&mut bytes, &encoding.encode(board_text))?;
+    }
+    Ok(())
+}
+
+/// The comments are stored as `oneline + 8 + multiline`, see [`parse_comments`](super::parser::parse_comments)
+fn comment_bytes(marker: &BoardMarker, encoding: TextEncoding) -> Option<Vec<u8>> {
+    let one = marker.oneline_comment.as_deref().filter(|c| !c.is_empty());
+    let multi = marker
+        .multiline_comment
+        .as_deref()
+        .filter(|c| !c.is_empty());
+    if one.is_none() && multi.is_none() {
+        return None;
+    }
+    let mut buf = Vec::new();
+    if let Some(one) = one {
+        buf.extend(encoding.encode(one));
+    }
+    if let Some(multi) = multi {
+        buf.push(0x08);
+        buf.extend(encoding.encode(multi));
+    }
+    Some(buf)
+}
+
+/// Write a null terminated string, padded with an extra null to an even length.
+fn write_text(mut bytes: impl Write, text: &[u8]) -> Result<(), std::io::Error> {
+    // a null in the text would end it early
+    let text = text.iter().copied().filter(|b| *b != 0).collect::<Vec<_>>();
+    bytes.write_all(&text)?;
+    if text.len() % 2 == 0 {
+        bytes.write_all(&[0, 0])
+    } else {
+        bytes.write_all(&[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_lib, write_lib};
+    use super::*;
+    use crate::board::{Point, Stone};
+    use crate::file_reader::encoding::TextEncoding;
+    use crate::p;
+    use color_eyre::eyre::WrapErr;
+    use test_log::test;
+
+    fn parse(bytes: &[u8]) -> Result<Board, color_eyre::Report> {
+        let mut board = Board::new();
+        parse_lib(bytes, &mut board)?;
+        Ok(board)
+    }
+
+    fn write(board: &Board, version: Version) -> Result<Vec<u8>, color_eyre::Report> {
+        let mut bytes = vec![];
+        write_lib(&mut bytes, board, version)?;
+        Ok(bytes)
+    }
+
+    #[track_caller]
+    fn assert_same_tree(left: &Board, l: MoveIndex, right: &Board, r: MoveIndex) {
+        fn non_empty(s: &Option<String>) -> Option<&str> {
+            s.as_deref().filter(|s| !s.is_empty())
+        }
+        let (lm, rm) = (left.get_move(l).unwrap(), right.get_move(r).unwrap());
+        assert_eq!(
+            (
+                lm.point,
+                lm.color,
+                non_empty(&lm.oneline_comment),
+                non_empty(&lm.multiline_comment),
+                non_empty(&lm.board_text),
+                lm.command.difference(LAYOUT_FLAGS),
+            ),
+            (
+                rm.point,
+                rm.color,
+                non_empty(&rm.oneline_comment),
+                non_empty(&rm.multiline_comment),
+                non_empty(&rm.board_text),
+                rm.command.difference(LAYOUT_FLAGS),
+            ),
+            "{lm:#?} != {rm:#?}"
+        );
+        let (lc, rc) = (left.get_move_children(&l), right.get_move_children(&r));
+        assert_eq!(lc.len(), rc.len(), "children of {lm:?} differ");
+        for (l, r) in lc.into_iter().zip(rc) {
+            assert_same_tree(left, l, right, r);
+        }
+    }
+
+    #[test]
+    fn round_trip_examplefiles() -> Result<(), color_eyre::Report> {
+        let mut paths = std::fs::read_dir("examplefiles")?
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.push("tests/I7.lib".into());
+        paths.push("tests/rps-fail.lib".into());
+        paths.push("tests/null_move2.lib".into());
+        for path in paths
+            .iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "lib"))
+        {
+            let span = tracing::info_span!("round trip", ?path);
+            let _enter = span.enter();
+            let original =
+                parse(&std::fs::read(path)?).wrap_err_with(|| format!("while parsing {path:?}"))?;
+            for version in [Version::V30, Version::V34] {
+                let written = write(&original, version)?;
+                let reparsed =
+                    parse(&written).wrap_err_with(|| format!("while parsing written {path:?}"))?;
+                assert_same_tree(
+                    &original,
+                    original.get_root(),
+                    &reparsed,
+                    reparsed.get_root(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn writes_same_bytes() -> Result<(), color_eyre::Report> {
+        for (path, version) in [
+            ("examplefiles/nocomments.lib", Version::V30),
+            ("examplefiles/lib_documented.lib", Version::V30),
+            ("examplefiles/lib_4_left_right_up_down.lib", Version::V30),
+            ("examplefiles/lib_two_top_rows.lib", Version::V34),
+            ("examplefiles/lib_3_is_pass.lib", Version::V34),
+            ("examplefiles/lib_two_stone_15xB.lib", Version::V34),
+        ] {
+            let original = std::fs::read(path)?;
+            let written = write(&parse(&original)?, version)?;
+            assert_eq!(original, written, "{path}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_encoding() -> Result<(), color_eyre::Report> {
+        for (text, encoding) in [
+            ("黑棋必胜，白棋防守失误", TextEncoding::Gbk),
+            ("黒の勝ち、白の守りが悪い", TextEncoding::ShiftJis),
+            ("Svart vinner på tid", TextEncoding::Windows1252),
+        ] {
+            let mut board = Board::new();
+            board.set_text_encoding(Some(encoding));
+            let root = board.get_root();
+            let mut marker = BoardMarker::new(Point::new(7, 7), Stone::Black);
+            marker.set_oneline_comment(text.to_owned());
+            marker.board_text = Some(text.chars().take(1).collect());
+            board.insert_move(root, marker);
+
+            let written = write(&board, Version::V30)?;
+            // the text is stored in the encoding, not as UTF-8
+            assert!(written.windows(text.len()).all(|w| w != text.as_bytes()));
+            let reparsed = parse(&written)?;
+            assert_eq!(reparsed.text_encoding(), Some(encoding), "{text}");
+            assert_same_tree(&board, board.get_root(), &reparsed, reparsed.get_root());
+            assert_eq!(write(&reparsed, Version::V30)?, written);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn merging_keeps_encoding() -> Result<(), color_eyre::Report> {
+        let text = "黑棋必胜，白棋防守失误";
+        let mut board = Board::new();
+        board.set_text_encoding(Some(TextEncoding::Gbk));
+        let mut marker = BoardMarker::new(Point::new(7, 7), Stone::Black);
+        marker.set_oneline_comment(text.to_owned());
+        board.insert_move(board.get_root(), marker);
+
+        let mut other = Board::new();
+        other.set_text_encoding(Some(TextEncoding::ShiftJis));
+        let mut marker = BoardMarker::new(Point::new(8, 8), Stone::Black);
+        marker.set_oneline_comment("黒の勝ち".to_owned());
+        other.insert_move(other.get_root(), marker);
+        parse_lib(write(&other, Version::V30)?.as_slice(), &mut board)?;
+        assert_eq!(board.text_encoding(), Some(TextEncoding::Gbk));
+
+        let reparsed = parse(&write(&board, Version::V30)?)?;
+        assert_eq!(reparsed.text_encoding(), Some(TextEncoding::Gbk));
+        let first = reparsed.get_move_children(&reparsed.get_root())[0];
+        assert_eq!(
+            reparsed.get_move(first).unwrap().oneline_comment.as_deref(),
+            Some(text)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_file_changes_nothing() -> Result<(), color_eyre::Report> {
+        let mut board = Board::new();
+        let h8 = board.insert_move(board.get_root(), BoardMarker::new(p![H, 8], Stone::Black));
+
+        let mut other = Board::new();
+        let first = other.insert_move(other.get_root(), BoardMarker::new(p![H, 8], Stone::Black));
+        let second = other.insert_move(first, BoardMarker::new(p![I, 9], Stone::White));
+        let mut marker = BoardMarker::new(p![J, 10], Stone::Black);
+        marker.set_oneline_comment("cut off".to_owned());
+        other.insert_move(second, marker);
+        let bytes = write(&other, Version::V30)?;
+        // the comment loses its terminating null, after the first moves were read
+        let end = bytes
+            .windows(7)
+            .position(|window| window == b"cut off")
+            .unwrap()
+            + 7;
+
+        assert!(parse_lib(&bytes[..end], &mut board).is_err());
+        assert_eq!(board.depth_first(&board.get_root()).count(), 2);
+        assert!(board.get_move_children(&h8).is_empty());
+        assert_eq!(board.text_encoding(), None);
+        parse_lib(bytes.as_slice(), &mut board)?;
+        assert_eq!(board.depth_first(&board.get_root()).count(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn transpositions_are_linked() -> Result<(), color_eyre::Report> {
+        let mut board = Board::new();
+        let root = board.get_root();
+        board.get_or_insert_points(root, Point::parse_line("h8 i9 g7")?);
+        board.get_or_insert_points(root, Point::parse_line("g7 i9 h8")?);
+        assert!(board.links().is_empty());
+
+        let parsed = parse(&write(&board, Version::V30)?)?;
+        let find = |line| {
+            *parsed
+                .find_line(&parsed.get_root(), Point::parse_line(line).unwrap())
+                .unwrap()
+                .last()
+                .unwrap()
+        };
+        assert_eq!(parsed.links(), vec![(find("g7 i9 h8"), find("h8 i9 g7"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn extension_and_texts() -> Result<(), color_eyre::Report> {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let mut marker = BoardMarker::new(Point::new(7, 7), Stone::Black);
+        marker.set_oneline_comment("one".to_owned());
+        marker.set_multiline_comment("multi\r\nline".to_owned());
+        marker.board_text = Some("A".to_owned());
+        board.insert_move(root, marker);
+
+        let written = write(&board, Version::V34)?;
+        assert_eq!(
+            &written[20..],
+            [
+                0x00, 0x00, // root
+                0x78, 0x49, 0x00, 0x01, // RIGHT | COMMENT | EXTENSION, BOARDTEXT
+                b'o', b'n', b'e', 0x08, b'm', b'u', b'l', b't', b'i', b'\r', b'\n', b'l', b'i',
+                b'n', b'e', 0x00, // comment
+                b'A', 0x00, // board text
+            ]
+        );
+        let reparsed = parse(&written)?;
+        assert_same_tree(&board, board.get_root(), &reparsed, reparsed.get_root());
+        Ok(())
+    }
+}
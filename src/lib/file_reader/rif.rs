@@ -0,0 +1,436 @@
+//! Functions for handling RIF database files, see [`FileType::Rif`](super::FileType::Rif).
+//!
+//! A database is an XML document looking like this:
+//!
+//! ```xml
+//! <database>
+//!   <players><player id="1" name="Anna" surname="Andersson" country="1" /></players>
+//!   <tournaments><tournament id="1" name="Nordic Open" year="2019" /></tournaments>
+//!   <rules><rule id="1" name="RIF" /></rules>
+//!   <openings><opening id="2" name="Kagetsu" /></openings>
+//!   <games>
+//!     <game id="1" bpl="1" wpl="2" bresult="1" tournament="1" round="1" rule="1" opening="2">
+//!       <move>h8 i9 i8 g8 j9</move>
+//!     </game>
+//!   </games>
+//! </database>
+//! ```
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::board::{Board, BoardMarker, Point, Stone};
+use crate::errors::ParseError;
+use crate::file_reader::FileType;
+
+/// Width of the board, RIF databases only contain 15x15 games.
+const WIDTH: u32 = 15;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    pub name: String,
+    pub surname: String,
+    pub country: Option<u32>,
+}
+
+impl std::fmt::Display for Player {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.name.is_empty(), self.surname.is_empty()) {
+            (false, false) => write!(f, "{} {}", self.name, self.surname),
+            (true, false) => f.write_str(&self.surname),
+            _ => f.write_str(&self.name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tournament {
+    pub name: String,
+    pub year: Option<u32>,
+    pub month: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    BlackWon,
+    WhiteWon,
+    Draw,
+}
+
+impl std::fmt::Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GameResult::BlackWon => "1-0",
+            GameResult::WhiteWon => "0-1",
+            GameResult::Draw => "½-½",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub id: u32,
+    pub black: Option<u32>,
+    pub white: Option<u32>,
+    /// Result from the view of black, `bresult` in the file.
+    pub result: Option<GameResult>,
+    pub tournament: Option<u32>,
+    pub round: Option<String>,
+    pub rule: Option<u32>,
+    pub opening: Option<u32>,
+    pub moves: Vec<Point>,
+}
+
+/// The contents of a RIF database.
+#[derive(Debug, Default, Clone)]
+pub struct Database {
+    pub countries: HashMap<u32, String>,
+    pub players: HashMap<u32, Player>,
+    pub tournaments: HashMap<u32, Tournament>,
+    pub rules: HashMap<u32, String>,
+    pub openings: HashMap<u32, String>,
+    pub games: Vec<Game>,
+}
+
+impl Database {
+    #[tracing::instrument(skip(text))]
+    pub fn from_xml(text: &str) -> Result<Self, ParseError> {
+        let document = roxmltree::Document::parse(text)
+            .map_err(|e| ParseError::RifParseError(e.to_string()))?;
+        let root = document.root_element();
+        if !root.has_tag_name("database") {
+            return Err(ParseError::RifParseError(format!(
+                "expected <database>, found <{}>",
+                root.tag_name().name()
+            )));
+        }
+        let mut database = Self::default();
+        for node in root.descendants().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "country" => {
+                    database
+                        .countries
+                        .insert(id(&node)?, attr(&node, "name").to_owned());
+                }
+                "player" => {
+                    database.players.insert(
+                        id(&node)?,
+                        Player {
+                            name: attr(&node, "name").to_owned(),
+                            surname: attr(&node, "surname").to_owned(),
+                            country: number(&node, "country")?,
+                        },
+                    );
+                }
+                "tournament" => {
+                    database.tournaments.insert(
+                        id(&node)?,
+                        Tournament {
+                            name: attr(&node, "name").to_owned(),
+                            year: number(&node, "year")?,
+                            month: number(&node, "month")?,
+                        },
+                    );
+                }
+                "rule" => {
+                    database
+                        .rules
+                        .insert(id(&node)?, attr(&node, "name").to_owned());
+                }
+                "opening" => {
+                    database
+                        .openings
+                        .insert(id(&node)?, attr(&node, "name").to_owned());
+                }
+                "game" => database.games.push(game(&node)?),
+                _ => (),
+            }
+        }
+        Ok(database)
+    }
+
+    /// Describe `game`, as `(oneline, multiline)`.
+    #[must_use]
+    pub fn describe(&self, game: &Game) -> (String, String) {
+        let player = |id: Option<u32>| {
+            id.and_then(|id| self.players.get(&id))
+                .map_or_else(|| "?".to_owned(), ToString::to_string)
+        };
+        let mut oneline = format!("{} - {}", player(game.black), player(game.white));
+        if let Some(result) = game.result {
+            oneline.push_str(&format!(" {result}"));
+        }
+
+        let mut lines = vec![format!("Game #{}", game.id)];
+        if let Some(tournament) = game.tournament.and_then(|t| self.tournaments.get(&t)) {
+            let mut line = format!("Tournament: {}", tournament.name);
+            match (tournament.year, tournament.month) {
+                (Some(year), Some(month)) => line.push_str(&format!(" ({year}-{month:02})")),
+                (Some(year), None) => line.push_str(&format!(" ({year})")),
+                _ => (),
+            }
+            if let Some(round) = &game.round {
+                line.push_str(&format!(", round {round}"));
+            }
+            lines.push(line);
+        }
+        if let Some(rule) = game.rule.and_then(|r| self.rules.get(&r)) {
+            lines.push(format!("Rule: {rule}"));
+        }
+        if let Some(opening) = game.opening.and_then(|o| self.openings.get(&o)) {
+            lines.push(format!("Opening: {opening}"));
+        }
+        (oneline, lines.join("\n"))
+    }
+
+    /// Merge every game into `board`, games sharing a beginning share those moves in the tree.
+    ///
+    /// The game is described on its final move, see [`Database::describe`]. If more than one game
+    /// ends on the same move, the descriptions are joined, a description that is already there is
+    /// not added again, so reading the same database twice leaves the comments as they were.
+    pub fn insert_into(&self, board: &mut Board) {
+        for game in &self.games {
+            if game.moves.is_empty() {
+                tracing::warn!(game.id, "game has no moves, skipping");
+                continue;
+            }
+            let latest = board.get_or_insert_line(
+                board.get_root(),
+                game.moves.iter().enumerate().map(|(index, point)| {
+                    BoardMarker::new(*point, Stone::from_bool(index % 2 == 0))
+                }),
+            );
+            let (oneline, multiline) = self.describe(game);
+            let marker = board.get_move_mut(latest).expect("move was just inserted");
+            let oneline = join(marker.oneline_comment.take(), oneline, "; ");
+            let multiline = join(marker.multiline_comment.take(), multiline, "\n\n");
+            marker.set_oneline_comment(oneline);
+            marker.set_multiline_comment(multiline);
+        }
+    }
+}
+
+/// Add `text` after `existing` with `separator` between, unless it's one of the texts in it.
+fn join(existing: Option<String>, text: String, separator: &str) -> String {
+    match existing {
+        Some(existing) if existing.split(separator).any(|part| part == text) => existing,
+        Some(existing) => format!("{existing}{separator}{text}"),
+        None => text,
+    }
+}
+
+fn attr<'a>(node: &roxmltree::Node<'a, '_>, name: &str) -> &'a str {
+    node.attribute(name).unwrap_or_default()
+}
+
+/// Get a numeric attribute, missing and empty attributes are `None`.
+fn number(node: &roxmltree::Node, name: &str) -> Result<Option<u32>, ParseError> {
+    match node.attribute(name).map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => value.parse().map(Some).map_err(|_| {
+            ParseError::RifParseError(format!(
+                "attribute {name}={value:?} on <{}> is not a number",
+                node.tag_name().name()
+            ))
+        }),
+    }
+}
+
+fn id(node: &roxmltree::Node) -> Result<u32, ParseError> {
+    number(node, "id")?.ok_or_else(|| {
+        ParseError::RifParseError(format!("<{}> is missing an id", node.tag_name().name()))
+    })
+}
+
+fn game(node: &roxmltree::Node) -> Result<Game, ParseError> {
+    let id = id(node)?;
+    let result = match node.attribute("bresult").map(str::trim) {
+        None | Some("") => None,
+        Some("1") => Some(GameResult::BlackWon),
+        Some("0") => Some(GameResult::WhiteWon),
+        Some("0.5") => Some(GameResult::Draw),
+        Some(other) => {
+            return Err(ParseError::RifParseError(format!(
+                "game #{id} has an unknown result: {other:?}"
+            )))
+        }
+    };
+    let text = node
+        .children()
+        .find(|n| n.has_tag_name("move"))
+        .and_then(|n| n.text())
+        .unwrap_or_default();
+    let mut played = [false; (WIDTH * WIDTH) as usize];
+    let mut moves = vec![];
+    for coordinate in text.split_whitespace() {
+        let point = Point::from_notation(coordinate, WIDTH)
+            .ok()
+            .filter(|point| !point.is_null)
+            .ok_or_else(|| {
+                ParseError::RifParseError(format!("game #{id} has an invalid move: {coordinate:?}"))
+            })?;
+        if std::mem::replace(&mut played[point.to_1d(WIDTH) as usize], true) {
+            return Err(ParseError::RifParseError(format!(
+                "game #{id} plays {coordinate} twice"
+            )));
+        }
+        moves.push(point);
+    }
+    Ok(Game {
+        id,
+        black: number(node, "bpl")?,
+        white: number(node, "wpl")?,
+        result,
+        tournament: number(node, "tournament")?,
+        round: node.attribute("round").map(ToOwned::to_owned),
+        rule: number(node, "rule")?,
+        opening: number(node, "opening")?,
+        moves,
+    })
+}
+
+/// Check if `bytes` looks like a RIF database, i.e it is XML with a `<database>` element.
+#[must_use]
+pub fn sniff(bytes: &[u8]) -> bool {
+    // the element should be close to the start, no need to look through all games
+    let start = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]);
+    let start = start.trim_start_matches('\u{feff}').trim_start();
+    start.starts_with('<') && start.contains("<database")
+}
+
+/// Read a RIF database into `board`, see [`Database::insert_into`].
+#[tracing::instrument(skip(file, board))]
+pub fn parse_rif(mut file: impl Read, board: &mut Board) -> Result<(), ParseError> {
+    FileType::Rif.check_board_size(board.size())?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    let database = Database::from_xml(&text)?;
+    tracing::debug!(
+        games = database.games.len(),
+        players = database.players.len(),
+        "parsed database"
+    );
+    database.insert_into(board);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use test_log::test;
+
+    fn example() -> Result<Database, ParseError> {
+        Database::from_xml(&std::fs::read_to_string("examplefiles/example.rif")?)
+    }
+
+    #[test]
+    fn database() -> Result<(), ParseError> {
+        let database = example()?;
+        assert_eq!(database.players.len(), 3);
+        assert_eq!(database.players[&2].to_string(), "Mart Tamm");
+        assert_eq!(database.tournaments[&1].name, "Nordic Open");
+        assert_eq!(database.rules[&2], "Yamaguchi");
+        assert_eq!(database.openings[&2], "Kagetsu");
+        assert_eq!(database.games.len(), 3);
+        let game = &database.games[1];
+        assert_eq!(game.result, Some(GameResult::WhiteWon));
+        assert_eq!(
+            game.moves,
+            p![[H, 8], [I, 9], [I, 8], [J, 8], [G, 10], [F, 11]]
+        );
+        let (oneline, multiline) = database.describe(game);
+        assert_eq!(oneline, "Mart Tamm - Erik Berg 0-1");
+        assert_eq!(
+            multiline,
+            "Game #2\nTournament: Nordic Open (2019-07), round 2\nRule: Yamaguchi\nOpening: Kagetsu"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merges_games() -> Result<(), ParseError> {
+        let mut board = Board::new();
+        parse_rif(
+            std::fs::read("examplefiles/example.rif")?.as_slice(),
+            &mut board,
+        )?;
+        let root = board.get_root();
+        // every game starts with h8 i9
+        let h8 = board.get_move_children(&root);
+        assert_eq!(h8.len(), 1);
+        let i9 = board.get_move_children(&h8[0]);
+        assert_eq!(i9.len(), 1);
+        // then i8 or j10
+        let third = board.get_move_children(&i9[0]);
+        assert_eq!(third.len(), 2);
+        // after i8 g8 or j8
+        assert_eq!(board.get_move_children(&third[0]).len(), 2);
+
+        let end = *board.main_line(&third[1]).last().unwrap_or(&third[1]);
+        let marker = board.get_move(end).unwrap();
+        assert_eq!(marker.point, p![J, 10]);
+        assert_eq!(marker.color, Stone::Black);
+        assert_eq!(
+            marker.oneline_comment.as_deref(),
+            Some("Erik Berg - Anna Andersson ½-½")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn same_game_twice() -> Result<(), ParseError> {
+        let database = example()?;
+        let mut board = Board::new();
+        database.insert_into(&mut board);
+        database.insert_into(&mut board);
+        let end = *board.main_line(&board.get_root()).last().unwrap();
+        let marker = board.get_move(end).unwrap();
+        assert_eq!(
+            marker.oneline_comment.as_deref(),
+            Some("Anna Andersson - Mart Tamm 1-0")
+        );
+        assert_eq!(
+            marker
+                .multiline_comment
+                .as_deref()
+                .map(|c| c.matches("Game #1").count()),
+            Some(1)
+        );
+
+        // another game ending on the same move is still added
+        let mut other = database.clone();
+        other.games.truncate(1);
+        other.games[0].id = 4;
+        other.insert_into(&mut board);
+        let marker = board.get_move(end).unwrap();
+        assert_eq!(
+            marker.oneline_comment.as_deref(),
+            Some("Anna Andersson - Mart Tamm 1-0")
+        );
+        assert!(marker
+            .multiline_comment
+            .as_deref()
+            .unwrap()
+            .contains("\n\nGame #4"));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        for xml in [
+            "",
+            "<games></games>",
+            r#"<database><games><game id="1"><move>h8 h8</move></game></games></database>"#,
+            r#"<database><games><game id="1"><move>h8 p1</move></game></games></database>"#,
+            r#"<database><games><game id="1"><move>h16</move></game></games></database>"#,
+            r#"<database><games><game id="1"><move>h8 pass</move></game></games></database>"#,
+            r#"<database><games><game id="1" bresult="2"></game></games></database>"#,
+            r#"<database><games><game><move>h8</move></game></games></database>"#,
+        ] {
+            assert!(
+                matches!(Database::from_xml(xml), Err(ParseError::RifParseError(_))),
+                "{xml}"
+            );
+        }
+    }
+}
@@ -0,0 +1,741 @@
+//! The Gomocup (Piskvork) engine protocol, to play against other engines and to let them play
+//! against ours.
+//!
+//! The manager writes one command per line to the engine's stdin, and the engine answers on its
+//! stdout. Points are written `x,y`, counted from `0` at the top left corner, which is the same as
+//! a [`Point`].
+//!
+//! A [`Client`] drives an engine in a subprocess. A [`Server`] answers the commands with our own
+//! [`Engine`](crate::board::engine::Engine), it's what the `pbrain-renju` binary runs.
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use crate::board::engine::Engine;
+use crate::board::rules::{IllegalMove, Outcome, RuleSet};
+use crate::board::{Board, BoardArr, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GomocupError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The engine didn't answer in time.
+    #[error("the engine didn't answer within {0:?}")]
+    Timeout(Duration),
+    /// The engine closed its output.
+    #[error("the engine exited")]
+    Closed,
+    /// The engine answered with `ERROR` or `UNKNOWN`.
+    #[error("the engine answered {0:?}")]
+    Engine(String),
+    /// The engine answered something that isn't valid in the protocol.
+    #[error("unexpected answer {0:?}")]
+    Protocol(String),
+    /// The engine played a move that isn't legal.
+    #[error(transparent)]
+    Illegal(#[from] IllegalMove),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Whose stone is on a point in a [`Command::Board`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// The engine's stone.
+    Own,
+    /// The opponent's stone.
+    Opponent,
+    /// A stone from a continuous game, which neither player placed.
+    Continuous,
+}
+
+/// A command from the manager to the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Start a game on a board of this size.
+    Start(u32),
+    /// The engine plays the first move.
+    Begin,
+    /// The opponent played this move, the engine plays the next.
+    Turn(Point),
+    /// Set up this position, the engine plays the next move. Sent as `BOARD`, one line per stone
+    /// and `DONE`.
+    Board(Vec<(Point, Field)>),
+    /// Information about the game, like `timeout_turn` or `rule`.
+    Info(String, String),
+    /// Start a new game on the same board.
+    Restart,
+    /// Take back this move.
+    Takeback(Point),
+    /// Describe the engine.
+    About,
+    /// Exit.
+    End,
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Start(size) => write!(f, "START {size}"),
+            Self::Begin => f.write_str("BEGIN"),
+            Self::Turn(point) => write!(f, "TURN {}", coordinates(*point)),
+            Self::Board(stones) => {
+                f.write_str("BOARD")?;
+                for (point, field) in stones {
+                    let field = match field {
+                        Field::Own => 1,
+                        Field::Opponent => 2,
+                        Field::Continuous => 3,
+                    };
+                    write!(f, "\n{},{field}", coordinates(*point))?;
+                }
+                f.write_str("\nDONE")
+            }
+            Self::Info(key, value) => write!(f, "INFO {key} {value}"),
+            Self::Restart => f.write_str("RESTART"),
+            Self::Takeback(point) => write!(f, "TAKEBACK {}", coordinates(*point)),
+            Self::About => f.write_str("ABOUT"),
+            Self::End => f.write_str("END"),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = ParseError;
+
+    /// Parse the first line of a command. The stones of a `BOARD` are on the lines after it, so it
+    /// parses to an empty [`Command::Board`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, rest) = s.split_once(' ').unwrap_or((s, ""));
+        let rest = rest.trim();
+        let error = || ParseError::Other(format!("{s:?} is not a Gomocup command"));
+        Ok(match name.to_uppercase().as_str() {
+            "START" => Self::Start(rest.parse()?),
+            "BEGIN" => Self::Begin,
+            "TURN" => Self::Turn(parse_point(rest)?),
+            "BOARD" => Self::Board(vec![]),
+            "INFO" => {
+                let (key, value) = rest.split_once(' ').ok_or_else(error)?;
+                Self::Info(key.to_owned(), value.trim().to_owned())
+            }
+            "RESTART" => Self::Restart,
+            "TAKEBACK" => Self::Takeback(parse_point(rest)?),
+            "ABOUT" => Self::About,
+            "END" => Self::End,
+            _ => return Err(error()),
+        })
+    }
+}
+
+/// The value of `INFO rule` for `rules`.
+#[must_use]
+pub fn rule_code(rules: RuleSet) -> u32 {
+    match rules {
+        RuleSet::Freestyle => 0,
+        RuleSet::Standard => 1,
+        RuleSet::Renju => 4,
+        RuleSet::Caro => 8,
+    }
+}
+
+/// The rules of an `INFO rule` value, a bit mask where `1` is exactly five, `2` a continuous game,
+/// `4` renju and `8` caro.
+#[must_use]
+pub fn rules_from_code(code: u32) -> RuleSet {
+    if code & 4 != 0 {
+        RuleSet::Renju
+    } else if code & 8 != 0 {
+        RuleSet::Caro
+    } else if code & 1 != 0 {
+        RuleSet::Standard
+    } else {
+        RuleSet::Freestyle
+    }
+}
+
+fn coordinates(point: Point) -> String {
+    format!("{},{}", point.x, point.y)
+}
+
+fn parse_point(s: &str) -> Result<Point, ParseError> {
+    let error = || ParseError::PointParseError(s.to_owned());
+    let (x, y) = s.split_once(',').ok_or_else(error)?;
+    let x = x.trim().parse().map_err(|_| error())?;
+    let y = y.trim().parse().map_err(|_| error())?;
+    Ok(Point::new(x, y))
+}
+
+/// An engine running in a subprocess.
+///
+/// The engine is killed when the client is dropped, [`Client::end`] lets it exit on its own.
+#[derive(Debug)]
+pub struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    /// The lines the engine writes, read on another thread so that reading can time out.
+    lines: Receiver<io::Result<String>>,
+    timeout: Option<Duration>,
+    /// The `MESSAGE` lines of the engine.
+    messages: Vec<String>,
+}
+
+impl Client {
+    /// Run `program` with `args` as an engine.
+    pub fn spawn<I, S>(program: impl AsRef<std::ffi::OsStr>, args: I) -> Result<Self, GomocupError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let mut command = std::process::Command::new(program);
+        command.args(args);
+        Self::from_command(command)
+    }
+
+    /// Run `command` as an engine, its stdin and stdout are replaced with pipes.
+    pub fn from_command(mut command: std::process::Command) -> Result<Self, GomocupError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(GomocupError::Closed)?;
+        let stdout = child.stdout.take().ok_or(GomocupError::Closed)?;
+        let (sender, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            timeout: None,
+            messages: vec![],
+        })
+    }
+
+    /// Give up on an answer after `timeout`.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The `MESSAGE`s the engine has sent.
+    #[must_use]
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+
+    pub fn send(&mut self, command: &Command) -> Result<(), GomocupError> {
+        tracing::trace!(%command, "to engine");
+        writeln!(self.stdin, "{command}")?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    /// Read the next answer, skipping messages.
+    fn answer(&mut self) -> Result<String, GomocupError> {
+        loop {
+            let line = match self.timeout {
+                Some(timeout) => self
+                    .lines
+                    .recv_timeout(timeout)
+                    .map_err(|error| match error {
+                        RecvTimeoutError::Timeout => GomocupError::Timeout(timeout),
+                        RecvTimeoutError::Disconnected => GomocupError::Closed,
+                    })?,
+                None => self.lines.recv().map_err(|_| GomocupError::Closed)?,
+            }?;
+            let line = line.trim();
+            tracing::trace!(line, "from engine");
+            let (kind, text) = line.split_once(' ').unwrap_or((line, ""));
+            match kind.to_uppercase().as_str() {
+                "MESSAGE" => self.messages.push(text.to_owned()),
+                "DEBUG" => tracing::debug!(text, "engine"),
+                "" => {}
+                "ERROR" | "UNKNOWN" => return Err(GomocupError::Engine(line.to_owned())),
+                _ => return Ok(line.to_owned()),
+            }
+        }
+    }
+
+    fn expect_ok(&mut self) -> Result<(), GomocupError> {
+        match self.answer()? {
+            ok if ok.eq_ignore_ascii_case("OK") => Ok(()),
+            other => Err(GomocupError::Protocol(other)),
+        }
+    }
+
+    fn expect_move(&mut self) -> Result<Point, GomocupError> {
+        let answer = self.answer()?;
+        parse_point(&answer).map_err(|_| GomocupError::Protocol(answer))
+    }
+
+    /// Start a game on a board of `size`.
+    pub fn start(&mut self, size: u32) -> Result<(), GomocupError> {
+        self.send(&Command::Start(size))?;
+        self.expect_ok()
+    }
+
+    /// Tell the engine about the game, there is no answer.
+    pub fn info(&mut self, key: &str, value: impl fmt::Display) -> Result<(), GomocupError> {
+        self.send(&Command::Info(key.to_owned(), value.to_string()))
+    }
+
+    /// Tell the engine which rules to play by.
+    pub fn set_rules(&mut self, rules: RuleSet) -> Result<(), GomocupError> {
+        self.info("rule", rule_code(rules))
+    }
+
+    /// The engine's first move.
+    pub fn begin(&mut self) -> Result<Point, GomocupError> {
+        self.send(&Command::Begin)?;
+        self.expect_move()
+    }
+
+    /// The engine's answer to `point`.
+    pub fn turn(&mut self, point: Point) -> Result<Point, GomocupError> {
+        self.send(&Command::Turn(point))?;
+        self.expect_move()
+    }
+
+    /// The engine's move in the position of `stones`.
+    pub fn board(&mut self, stones: Vec<(Point, Field)>) -> Result<Point, GomocupError> {
+        self.send(&Command::Board(stones))?;
+        self.expect_move()
+    }
+
+    /// The engine's description of itself, like `name="pbrain", version="1.0"`.
+    pub fn about(&mut self) -> Result<String, GomocupError> {
+        self.send(&Command::About)?;
+        self.answer()
+    }
+
+    /// Let the engine play the next move after `node` and add it to `board`.
+    ///
+    /// The engine gets the whole position with `BOARD`. Returns the new move, or an error if the
+    /// engine plays a move that isn't legal under `rules`.
+    pub fn play(
+        &mut self,
+        board: &mut Board,
+        node: MoveIndex,
+        rules: RuleSet,
+    ) -> Result<MoveIndex, GomocupError> {
+        let (position, _) = board.as_board(&node)?;
+        let stone = board.side_to_move(&node);
+        let stones = position
+            .iter()
+            .filter(|m| !m.color.is_empty())
+            .map(|m| {
+                let field = if m.color == stone {
+                    Field::Own
+                } else {
+                    Field::Opponent
+                };
+                (m.point, field)
+            })
+            .collect();
+        let point = self.board(stones)?;
+        position.check_move(point, stone, rules)?;
+        Ok(board.get_or_insert_points(node, [point]))
+    }
+
+    /// Ask the engine to exit, and wait for it.
+    pub fn end(mut self) -> Result<(), GomocupError> {
+        self.send(&Command::End)?;
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Let `black` and `white` play from `from` until the game is over, adding the moves to `board`.
+///
+/// Both engines have to be started on a board of the same size. Returns the last move and the
+/// outcome.
+pub fn play_game(
+    black: &mut Client,
+    white: &mut Client,
+    board: &mut Board,
+    from: MoveIndex,
+    rules: RuleSet,
+) -> Result<(MoveIndex, Outcome), GomocupError> {
+    let mut node = from;
+    loop {
+        let (position, _) = board.as_board(&node)?;
+        if let Some(outcome) = position.outcome(rules) {
+            return Ok((node, outcome));
+        }
+        let to_move = match board.get_move(node).map(|m| m.color) {
+            Some(Stone::Empty) | None => Stone::Black,
+            Some(color) => color.opposite(),
+        };
+        let engine = if to_move.is_black() {
+            &mut *black
+        } else {
+            &mut *white
+        };
+        node = engine.play(board, node, rules)?;
+    }
+}
+
+/// Answers the Gomocup protocol with our [`Engine`].
+#[derive(Debug, Clone)]
+pub struct Server {
+    rules: RuleSet,
+    depth: u32,
+    /// The time for a move, from `INFO timeout_turn`.
+    time_limit: Option<Duration>,
+    position: Option<BoardArr>,
+    /// The stones of the engine.
+    own: Stone,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            rules: RuleSet::Freestyle,
+            depth: Engine::DEPTH,
+            time_limit: None,
+            position: None,
+            own: Stone::Empty,
+        }
+    }
+
+    /// Search at most `depth` moves deep.
+    #[must_use]
+    pub fn with_depth(mut self, depth: u32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Answer the commands from `input` on `output` until `END` or the end of the input.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        while let Some(line) = lines.next() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let answer = match line.parse::<Command>() {
+                Ok(Command::End) => return Ok(()),
+                Ok(Command::Board(_)) => {
+                    let mut stones = vec![];
+                    let mut error = None;
+                    for line in lines.by_ref() {
+                        let line = line?;
+                        let line = line.trim();
+                        if line.eq_ignore_ascii_case("DONE") {
+                            break;
+                        }
+                        match parse_stone(line) {
+                            Ok(stone) => stones.push(stone),
+                            Err(e) => error = Some(e),
+                        }
+                    }
+                    match error {
+                        Some(error) => Some(format!("ERROR {error}")),
+                        None => self.handle(Command::Board(stones)),
+                    }
+                }
+                Ok(command) => self.handle(command),
+                Err(_) => Some(format!("UNKNOWN {line}")),
+            };
+            if let Some(answer) = answer {
+                writeln!(output, "{answer}")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The answer to `command`, if there is one.
+    fn handle(&mut self, command: Command) -> Option<String> {
+        match command {
+            Command::Start(size) => Some(if (5..=crate::board::MAX_SIZE).contains(&size) {
+                self.position = Some(BoardArr::new(size));
+                self.own = Stone::Empty;
+                "OK".to_owned()
+            } else {
+                format!("ERROR unsupported size {size}")
+            }),
+            Command::Restart => Some(match &mut self.position {
+                Some(position) => {
+                    position.clear();
+                    self.own = Stone::Empty;
+                    "OK".to_owned()
+                }
+                None => "ERROR no game started".to_owned(),
+            }),
+            Command::Info(key, value) => {
+                match key.to_lowercase().as_str() {
+                    "rule" => {
+                        if let Ok(code) = value.parse() {
+                            self.rules = rules_from_code(code);
+                        }
+                    }
+                    "timeout_turn" => {
+                        if let Ok(ms) = value.parse::<u64>() {
+                            // leave some time to answer
+                            self.time_limit = (ms > 0).then(|| Duration::from_millis(ms * 9 / 10));
+                        }
+                    }
+                    _ => {}
+                }
+                None
+            }
+            Command::About => Some(format!(
+                "name=\"{}\", version=\"{}\"",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            )),
+            Command::Begin => {
+                self.own = Stone::Black;
+                Some(self.reply())
+            }
+            Command::Turn(point) => {
+                let Some(position) = &mut self.position else {
+                    return Some("ERROR no game started".to_owned());
+                };
+                if !position
+                    .get_point(point)
+                    .is_some_and(|m| m.color.is_empty())
+                {
+                    return Some(format!(
+                        "ERROR {} is not an empty point",
+                        coordinates(point)
+                    ));
+                }
+                let opponent = position.to_move();
+                position
+                    .set_point(point, opponent)
+                    .expect("the point was checked");
+                self.own = opponent.opposite();
+                Some(self.reply())
+            }
+            Command::Takeback(point) => Some(match &mut self.position {
+                Some(position) if !point.is_valid(position.size()) => {
+                    format!("ERROR {} is outside the board", coordinates(point))
+                }
+                Some(position) if position.get_point(point).is_none_or(|m| m.color.is_empty()) => {
+                    format!("ERROR there is no stone on {}", coordinates(point))
+                }
+                Some(position) => {
+                    position
+                        .set_point(point, Stone::Empty)
+                        .expect("the point was checked");
+                    "OK".to_owned()
+                }
+                None => "ERROR no game started".to_owned(),
+            }),
+            Command::Board(stones) => {
+                let Some(position) = &mut self.position else {
+                    return Some("ERROR no game started".to_owned());
+                };
+                if stones.iter().any(|(_, field)| *field == Field::Continuous) {
+                    return Some("ERROR continuous games are not supported".to_owned());
+                }
+                // the game so far is kept if a stone is wrong
+                let mut scratch = BoardArr::new(position.size());
+                let own = stones.iter().filter(|(_, f)| *f == Field::Own).count();
+                // the engine is to move, so it's black if both have as many stones
+                let own = Stone::from_bool(own == stones.len() - own);
+                for (point, field) in stones {
+                    let stone = match field {
+                        Field::Own => own,
+                        _ => own.opposite(),
+                    };
+                    if scratch.set_point(point, stone).is_err() {
+                        return Some(format!("ERROR {} is outside the board", coordinates(point)));
+                    }
+                }
+                *position = scratch;
+                self.own = own;
+                Some(self.reply())
+            }
+            Command::End => None,
+        }
+    }
+
+    /// Play the engine's move.
+    fn reply(&mut self) -> String {
+        let Some(position) = &mut self.position else {
+            return "ERROR no game started".to_owned();
+        };
+        let mut engine = Engine::new(self.rules).with_depth(self.depth);
+        if let Some(limit) = self.time_limit {
+            engine = engine.with_time_limit(limit);
+        }
+        match engine.analyse(position, self.own).best {
+            Some(point) => {
+                position
+                    .set_point(point, self.own)
+                    .expect("the engine plays on the board");
+                coordinates(point)
+            }
+            None => "ERROR the game is over".to_owned(),
+        }
+    }
+}
+
+fn parse_stone(line: &str) -> Result<(Point, Field), ParseError> {
+    let error = || ParseError::Other(format!("{line:?} is not a stone, expected x,y,field"));
+    let (point, field) = line.rsplit_once(',').ok_or_else(error)?;
+    let field = match field.trim() {
+        "1" => Field::Own,
+        "2" => Field::Opponent,
+        "3" => Field::Continuous,
+        _ => return Err(error()),
+    };
+    Ok((parse_point(point)?, field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    fn run(server: &mut Server, input: &str) -> Vec<String> {
+        let mut output = vec![];
+        server.run(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn commands() {
+        for command in [
+            Command::Start(15),
+            Command::Begin,
+            Command::Turn(Point::new(7, 8)),
+            Command::Info("timeout_turn".to_owned(), "1000".to_owned()),
+            Command::Restart,
+            Command::Takeback(Point::new(0, 14)),
+            Command::About,
+            Command::End,
+        ] {
+            assert_eq!(command.to_string().parse::<Command>().unwrap(), command);
+        }
+        let board = Command::Board(vec![
+            (Point::new(7, 7), Field::Opponent),
+            (Point::new(8, 8), Field::Own),
+        ]);
+        assert_eq!(board.to_string(), "BOARD\n7,7,2\n8,8,1\nDONE");
+        assert_eq!(
+            "turn 3, 4".parse::<Command>().unwrap(),
+            Command::Turn(Point::new(3, 4))
+        );
+        assert!("TURN 3".parse::<Command>().is_err());
+        assert!("PLAY 3,4".parse::<Command>().is_err());
+
+        for rules in RuleSet::ALL {
+            assert_eq!(rules_from_code(rule_code(rules)), rules);
+        }
+        assert_eq!(rules_from_code(5), RuleSet::Renju);
+    }
+
+    #[test]
+    fn server() {
+        let mut server = Server::new().with_depth(2);
+        let answers = run(
+            &mut server,
+            "ABOUT\nSTART 15\nINFO rule 4\nBEGIN\nTURN 14,14\nRESTART\nTURN 7,7\nSTART 99\nFOO\nEND\nBEGIN\n",
+        );
+        assert!(answers[0].starts_with("name=\"renju-board\""));
+        assert_eq!(answers[1], "OK");
+        // black starts in the center
+        assert_eq!(answers[2], "7,7");
+        assert!(parse_point(&answers[3]).is_ok());
+        assert_eq!(answers[4], "OK");
+        assert!(parse_point(&answers[5]).is_ok_and(|point| point != Point::new(7, 7)));
+        assert!(answers[6].starts_with("ERROR"));
+        assert_eq!(answers[7], "UNKNOWN FOO");
+        // nothing after END
+        assert_eq!(answers.len(), 8);
+        assert_eq!(server.rules, RuleSet::Renju);
+    }
+
+    #[test]
+    fn board() {
+        let mut server = Server::new().with_depth(2);
+        // the engine has four in a row and is to move
+        let answers = run(
+            &mut server,
+            "START 15\nBOARD\n3,7,1\n4,7,1\n5,7,1\n6,7,1\n0,0,2\n1,0,2\n2,0,2\n3,0,2\nDONE\n",
+        );
+        assert_eq!(answers[1..], ["2,7"]);
+        assert_eq!(server.own, Stone::Black);
+        // with the opponent to block
+        let answers = run(
+            &mut server,
+            "BOARD\n3,7,2\n4,7,2\n5,7,2\n6,7,2\n2,7,1\n0,0,1\n1,0,1\nDONE\n",
+        );
+        assert_eq!(answers, ["7,7"]);
+        assert_eq!(server.own, Stone::White);
+        let answers = run(&mut server, "BOARD\n7,7,3\nDONE\nBOARD\n7,7\nDONE\n");
+        assert!(answers.iter().all(|answer| answer.starts_with("ERROR")));
+
+        // a stone outside the board leaves the game as it was
+        let before = *server.position.as_ref().unwrap().zobrist();
+        let answers = run(&mut server, "BOARD\n7,8,1\n15,3,2\nDONE\n");
+        assert_eq!(answers, ["ERROR 15,3 is outside the board"]);
+        assert_eq!(*server.position.as_ref().unwrap().zobrist(), before);
+        assert_ne!(before.hash(), 0);
+        assert_eq!(server.own, Stone::White);
+    }
+
+    #[test]
+    fn takeback() {
+        let mut server = Server::new().with_depth(2);
+        let answers = run(
+            &mut server,
+            "START 15\nTURN 7,7\nTAKEBACK 15,0\nTAKEBACK 0,0\nTAKEBACK 7,7\n",
+        );
+        assert_eq!(
+            answers[2..],
+            [
+                "ERROR 15,0 is outside the board",
+                "ERROR there is no stone on 0,0",
+                "OK"
+            ]
+        );
+        let position = server.position.as_ref().unwrap();
+        assert_eq!(
+            position.get_point(Point::new(7, 7)).unwrap().color,
+            Stone::Empty
+        );
+        // the engine's move is still there
+        let reply = parse_point(&answers[1]).unwrap();
+        assert_eq!(position.get_point(reply).unwrap().color, Stone::White);
+        assert_eq!(
+            run(&mut server, "TAKEBACK 7,7\n"),
+            ["ERROR there is no stone on 7,7"]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod board;
 pub mod errors;
 pub mod file_reader;
+pub mod gomocup;
 pub mod util;
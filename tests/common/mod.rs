@@ -0,0 +1,75 @@
+//! Helpers shared by the integration tests and the benchmarks.
+#![allow(dead_code)]
+
+use renju::board::{Board, BoardMarker, Point, Stone};
+use renju::file_reader::renlib::{write_lib, Version};
+
+/// Deterministic xorshift64, so the same library is generated every time.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Make a library of `games` games of `moves` moves each, all starting in the middle.
+///
+/// The moves close to the middle are more likely, so the games share beginnings like real ones.
+pub fn synthetic_library(games: usize, moves: usize, seed: u64) -> Board {
+    let mut rng = Rng(seed | 1);
+    let mut board = Board::new();
+    for _ in 0..games {
+        let mut played = [false; 225];
+        let mut line = Vec::with_capacity(moves);
+        let mut last = Point::new(7, 7);
+        played[last.to_1d(15) as usize] = true;
+        line.push(BoardMarker::new(last, Stone::Black));
+        let mut spread = 2;
+        while line.len() < moves {
+            // play close to the last move, further away if it is crowded
+            let mut offset = || (rng.next() % (2 * spread + 1)) as i64 - spread as i64;
+            let x = (last.x as i64 + offset()).clamp(0, 14) as u32;
+            let y = (last.y as i64 + offset()).clamp(0, 14) as u32;
+            let point = Point::new(x, y);
+            if std::mem::replace(&mut played[point.to_1d(15) as usize], true) {
+                spread = (spread + 1).min(14);
+                continue;
+            }
+            spread = 2;
+            line.push(BoardMarker::new(
+                point,
+                Stone::from_bool(line.len() % 2 == 0),
+            ));
+            last = point;
+        }
+        board.get_or_insert_line(board.get_root(), line);
+    }
+    board
+}
+
+/// Count the nodes reachable from the root, including the root.
+pub fn count_nodes(board: &Board) -> usize {
+    let mut count = 0;
+    let mut stack = vec![board.get_root()];
+    while let Some(node) = stack.pop() {
+        count += 1;
+        stack.extend(board.get_move_children(&node));
+    }
+    count
+}
+
+/// [`synthetic_library`] written as a RenLib library.
+pub fn synthetic_lib_bytes(games: usize, moves: usize, seed: u64) -> Vec<u8> {
+    let mut bytes = vec![];
+    write_lib(
+        &mut bytes,
+        &synthetic_library(games, moves, seed),
+        Version::V30,
+    )
+    .expect("writing to a vec should not fail");
+    bytes
+}
@@ -0,0 +1,45 @@
+//! Play games between two of our own brains, driven through the Gomocup protocol.
+
+extern crate renju;
+use renju::board::rules::{Outcome, RuleSet};
+use renju::board::{Board, Point};
+use renju::gomocup::{play_game, Client, GomocupError};
+use std::time::Duration;
+
+fn brain() -> Client {
+    Client::spawn(env!("CARGO_BIN_EXE_pbrain-renju"), ["--depth", "2"])
+        .expect("couldn't start the brain")
+        .with_timeout(Duration::from_secs(30))
+}
+
+#[test]
+fn game() {
+    let mut board = Board::new();
+    let (mut black, mut white) = (brain(), brain());
+    for brain in [&mut black, &mut white] {
+        assert!(brain.about().unwrap().contains("renju-board"));
+        brain.start(15).unwrap();
+        brain.set_rules(RuleSet::Renju).unwrap();
+    }
+    let root = board.get_root();
+    let from = board.get_or_insert_points(root, [Point::new(7, 7), Point::new(8, 8)]);
+    let (last, outcome) = play_game(&mut black, &mut white, &mut board, from, RuleSet::Renju)
+        .expect("the game didn't finish");
+    let (position, moves) = board.as_board(&last).unwrap();
+    assert_eq!(position.outcome(RuleSet::Renju), Some(outcome));
+    assert!(moves.len() > 5);
+    if let Outcome::Win { winner, .. } = outcome {
+        assert_eq!(board.get_move(last).unwrap().color, winner);
+    }
+    black.end().unwrap();
+    white.end().unwrap();
+}
+
+#[test]
+fn errors() {
+    let mut brain = brain();
+    assert!(matches!(brain.start(99), Err(GomocupError::Engine(_))));
+    assert!(matches!(brain.begin(), Err(GomocupError::Engine(_))));
+    brain.start(15).unwrap();
+    assert_eq!(brain.begin().unwrap(), Point::new(7, 7));
+}
@@ -8,20 +8,38 @@ extern crate renju;
 use renju::{board, file_reader};
 use std::path::Path;
 
+mod common;
+
+#[test]
+/// Load a library about the size of all games on renju.net.
+fn large_synthetic_library() {
+    let bytes = common::synthetic_lib_bytes(5_000, 40, 0x5EED);
+    let mut graph = board::Board::new();
+    let start = std::time::Instant::now();
+    file_reader::renlib::parse_lib(bytes.as_slice(), &mut graph).expect("couldn't parse library");
+    tracing::info!(elapsed = ?start.elapsed(), "parsed synthetic library");
+    let nodes = common::count_nodes(&graph);
+    assert!(nodes > 100_000, "only {nodes} nodes");
+    assert_eq!(
+        nodes,
+        common::count_nodes(&common::synthetic_library(5_000, 40, 0x5EED))
+    );
+}
+
 #[test]
 #[ignore]
-/// This crashes on current implementation of `move_node`.
-fn large_file() {
+/// Download all games from renju.net/media/games.php, convert it to a .lib file in RenLib and
+/// place it in `tests/norelease_all_games.lib`.
+fn all_games() {
     let graph: board::Board = match file_reader::open_file_path(Path::new(
         "tests/norelease_all_games.lib",
     )) {
         Ok(val) => val,
         Err(err) => {
-            panic!("Couldn't parse file! Error: {:?}.\nPlease download all games from renju.net/media/games.php and then convert it to a .lib file in renlib and place in RenLib/norelease_all_games.lib",
+            panic!("Couldn't parse file! Error: {:?}.\nPlease download all games from renju.net/media/games.php and then convert it to a .lib file in renlib and place in tests/norelease_all_games.lib",
                    err)
         }
     };
 
-    tracing::info!("\n{:?}", graph);
-    panic!("intended!");
+    tracing::info!(nodes = common::count_nodes(&graph), "parsed all games");
 }